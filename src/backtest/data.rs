@@ -0,0 +1,132 @@
+use anyhow::Result;
+use chrono::NaiveDate;
+use log::info;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// Daily closing price for an ETF
+#[derive(Debug, Clone)]
+pub struct DailyBar {
+    pub date: NaiveDate,
+    pub close: f64,
+}
+
+/// Raw signal inputs known on a given day.
+///
+/// * `hdd` - 7-day heating degree day total averaged across regions, the same
+///   quantity `WeatherDataFetcher::get_regional_hdd_signal` builds from forecasts
+/// * `storage_bcf` - EIA working gas in storage, dated by release day
+/// * `storm_signal` - storm component as produced by `NOAADataFetcher`
+///
+/// Empty cells are `None`; the engine carries the last known value forward.
+#[derive(Debug, Clone)]
+pub struct HistoricalInput {
+    pub date: NaiveDate,
+    pub hdd: Option<f64>,
+    pub storage_bcf: Option<f64>,
+    pub storm_signal: Option<f64>,
+}
+
+struct CsvTable {
+    columns: HashMap<String, usize>,
+    rows: Vec<Vec<String>>,
+}
+
+impl CsvTable {
+    fn load(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path.display(), e))?;
+        let mut lines = text.lines().filter(|l| !l.trim().is_empty());
+        
+        let header = lines
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("{} is empty", path.display()))?;
+        let columns = header
+            .split(',')
+            .enumerate()
+            .map(|(idx, name)| (name.trim().to_lowercase().replace(' ', "_"), idx))
+            .collect();
+        
+        let rows = lines
+            .map(|line| line.split(',').map(|cell| cell.trim().to_string()).collect())
+            .collect();
+        
+        Ok(Self { columns, rows })
+    }
+    
+    fn column(&self, names: &[&str]) -> Option<usize> {
+        names.iter().find_map(|name| self.columns.get(*name).copied())
+    }
+    
+    fn require(&self, names: &[&str], path: &Path) -> Result<usize> {
+        self.column(names)
+            .ok_or_else(|| anyhow::anyhow!("{} has no '{}' column", path.display(), names[0]))
+    }
+}
+
+fn parse_date(value: &str) -> Result<NaiveDate> {
+    // Accept plain dates as well as timestamps such as 2024-01-02T00:00:00Z
+    let date_part = value.get(..10).unwrap_or(value);
+    NaiveDate::parse_from_str(date_part, "%Y-%m-%d")
+        .map_err(|e| anyhow::anyhow!("Invalid date '{}': {}", value, e))
+}
+
+fn parse_optional(row: &[String], idx: Option<usize>) -> Result<Option<f64>> {
+    match idx.and_then(|i| row.get(i)).map(|s| s.as_str()) {
+        None | Some("") => Ok(None),
+        Some(cell) => cell
+            .parse()
+            .map(Some)
+            .map_err(|e| anyhow::anyhow!("Invalid number '{}': {}", cell, e)),
+    }
+}
+
+/// Load daily bars from a CSV with at least `date` and `close` columns
+/// (open/high/low/volume are ignored). Rows are returned sorted by date.
+pub fn load_bars_csv(path: &Path) -> Result<Vec<DailyBar>> {
+    let table = CsvTable::load(path)?;
+    let date_idx = table.require(&["date", "timestamp"], path)?;
+    let close_idx = table.require(&["close", "adj_close"], path)?;
+    
+    let mut bars = Vec::new();
+    for (line_no, row) in table.rows.iter().enumerate() {
+        let field = |idx: usize| -> Result<f64> {
+            parse_optional(row, Some(idx))?
+                .ok_or_else(|| anyhow::anyhow!("{} row {}: missing price", path.display(), line_no + 2))
+        };
+        bars.push(DailyBar {
+            date: parse_date(row.get(date_idx).map(|s| s.as_str()).unwrap_or(""))?,
+            close: field(close_idx)?,
+        });
+    }
+    
+    bars.sort_by_key(|bar| bar.date);
+    info!("Loaded {} bars from {}", bars.len(), path.display());
+    Ok(bars)
+}
+
+/// Load signal inputs from a CSV with `date,hdd,storage_bcf,storm_signal` columns.
+/// Any of the value columns may be missing or left blank.
+pub fn load_inputs_csv(path: &Path) -> Result<Vec<HistoricalInput>> {
+    let table = CsvTable::load(path)?;
+    let date_idx = table.require(&["date"], path)?;
+    let hdd_idx = table.column(&["hdd"]);
+    let storage_idx = table.column(&["storage_bcf", "storage"]);
+    let storm_idx = table.column(&["storm_signal", "storm"]);
+    
+    let mut inputs = Vec::new();
+    for row in &table.rows {
+        inputs.push(HistoricalInput {
+            date: parse_date(row.get(date_idx).map(|s| s.as_str()).unwrap_or(""))?,
+            hdd: parse_optional(row, hdd_idx)?,
+            storage_bcf: parse_optional(row, storage_idx)?,
+            storm_signal: parse_optional(row, storm_idx)?,
+        });
+    }
+    
+    inputs.sort_by_key(|input| input.date);
+    info!("Loaded {} input rows from {}", inputs.len(), path.display());
+    Ok(inputs)
+}
+
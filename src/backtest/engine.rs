@@ -0,0 +1,363 @@
+use crate::backtest::data::{DailyBar, HistoricalInput};
+use crate::backtest::metrics::PerformanceMetrics;
+use crate::config::TradingConfig;
+use crate::data_sources::{EIADataFetcher, WeatherDataFetcher};
+use crate::signals::{ComponentSignal, SignalProcessor, SignalRegistry};
+use crate::trading::protection::{ExitReason, PositionMonitor};
+use crate::trading::{Broker, SimulatedBroker, TradeExecutor};
+use anyhow::Result;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use log::{info, LevelFilter};
+use rust_decimal::prelude::{FromPrimitive, ToPrimitive};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EquityPoint {
+    pub date: NaiveDate,
+    pub equity: f64,
+    pub total_signal: f64,
+    pub action: String,
+    pub symbol: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BacktestTrade {
+    pub date: NaiveDate,
+    pub symbol: String,
+    pub side: String,
+    pub qty: f64,
+    pub price: f64,
+    pub notional: f64,
+    /// Realized P&L for sells, measured against the average entry price
+    pub realized_pl: Option<f64>,
+    /// Protective rule that closed the position, for stop-loss/take-profit exits
+    pub exit_reason: Option<ExitReason>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BacktestReport {
+    pub equity_curve: Vec<EquityPoint>,
+    pub trades: Vec<BacktestTrade>,
+    pub metrics: PerformanceMetrics,
+}
+
+/// Replays historical inputs day by day through the live signal and execution code.
+///
+/// Each day the signal is built from inputs dated on or before that day and
+/// executed at the day's close through `TradeExecutor` on a `SimulatedBroker`,
+/// so the mutual-exclusivity rules are exactly the ones used when trading live.
+/// Protective exits are checked against the same close before the signal
+/// trades, since daily bars carry no intraday path.
+///
+/// A backtest trades its own fresh account, so the live risk limits, circuit
+/// breaker, kill switch, state database and JSON logs never apply to it.
+pub struct Backtester {
+    config: TradingConfig,
+}
+
+impl Backtester {
+    pub fn new(config: TradingConfig) -> Self {
+        // Never created, so nothing is logged and no kill switch can exist there
+        let scratch = std::env::temp_dir().join(format!("algotrade-backtest-{}", std::process::id()));
+        let in_scratch = |name: &str| scratch.join(name).to_string_lossy().into_owned();
+        let config = TradingConfig {
+            max_order_notional: Decimal::ZERO,
+            max_gross_exposure: Decimal::ZERO,
+            max_exposure_pct_equity: Decimal::ZERO,
+            max_daily_trades: 0,
+            daily_loss_limit_pct: Decimal::ZERO,
+            weekly_loss_limit_pct: Decimal::ZERO,
+            max_drawdown_pct: Decimal::ZERO,
+            kill_switch_file: in_scratch("KILL_SWITCH"),
+            risk_state_file: in_scratch("risk_state.json"),
+            breaker_state_file: in_scratch("circuit_breaker.json"),
+            state_db_path: String::new(),
+            log_dir: scratch.to_string_lossy().into_owned(),
+            ..config
+        };
+        Self { config }
+    }
+    
+    pub async fn run(
+        &self,
+        inputs: &[HistoricalInput],
+        bars: &[DailyBar],
+        inverse_bars: &[DailyBar],
+    ) -> Result<BacktestReport> {
+        let inverse_closes: HashMap<NaiveDate, f64> =
+            inverse_bars.iter().map(|bar| (bar.date, bar.close)).collect();
+        
+        let sim = Arc::new(SimulatedBroker::new(
+            self.config.sim_starting_cash,
+            self.config.sim_commission_per_order,
+            self.config.sim_slippage_bps,
+        ));
+        let broker: Arc<dyn Broker> = sim.clone();
+        let executor = TradeExecutor::new(self.config.clone(), broker.clone());
+        let monitor = PositionMonitor::new(self.config.clone(), broker.clone());
+        let processor = SignalProcessor::new(self.config.clone());
+        let registry = SignalRegistry::from_config(&self.config, broker.clone());
+        
+        info!(
+            "Running backtest over {} bars of {} / {} bars of {}",
+            bars.len(), self.config.symbol, inverse_bars.len(), self.config.inverse_symbol
+        );
+        
+        // The executor and signal processor narrate every step at INFO level,
+        // which is far too chatty when replaying years of history.
+        let quiet = QuietLogs::new();
+        
+        let mut equity_curve = Vec::new();
+        let mut trades = Vec::new();
+        let mut book: HashMap<String, (f64, f64)> = HashMap::new(); // symbol -> (qty, cost)
+        let mut input_idx = 0;
+        let mut last_hdd = None;
+        let mut storage_series: Vec<(DateTime<Utc>, f64)> = Vec::new();
+        
+        for bar in bars {
+            let Some(inverse_close) = inverse_closes.get(&bar.date).copied() else {
+                continue;
+            };
+            
+            // Advance inputs up to and including today. Days without a storm
+            // reading had no relevant alerts, which is a genuine 0.0.
+            let mut storm_value = 0.0;
+            while input_idx < inputs.len() && inputs[input_idx].date <= bar.date {
+                let input = &inputs[input_idx];
+                if let Some(hdd) = input.hdd {
+                    last_hdd = Some((input.date, hdd));
+                }
+                if let Some(storage) = input.storage_bcf {
+                    storage_series.push((to_utc(input.date), storage));
+                }
+                if input.date == bar.date {
+                    storm_value = input.storm_signal.unwrap_or(0.0);
+                }
+                input_idx += 1;
+            }
+            
+            let temp_signal = match last_hdd {
+                Some((date, hdd)) => ComponentSignal::available(WeatherDataFetcher::hdd_signal(hdd), Some(to_utc(date))),
+                None => ComponentSignal::unavailable("no HDD history yet"),
+            };
+            
+            // Same one-year lookback the live EIA fetcher requests
+            let window_start = to_utc(bar.date) - Duration::days(365);
+            let window: Vec<(DateTime<Utc>, f64)> = storage_series
+                .iter()
+                .filter(|(date, _)| *date >= window_start)
+                .copied()
+                .collect();
+            let inventory_signal = match EIADataFetcher::inventory_signal_from_series(&window) {
+                Some(value) => ComponentSignal::available(value, window.last().map(|(date, _)| *date)),
+                None => ComponentSignal::unavailable("insufficient storage history"),
+            };
+            
+            let storm_signal = ComponentSignal::available(storm_value, Some(to_utc(bar.date)));
+            
+            // Today's close is set first so price-based sources see it
+            sim.set_time(to_utc(bar.date));
+            sim.set_price(&self.config.symbol, Decimal::from_f64(bar.close).unwrap_or_default());
+            sim.set_price(&self.config.inverse_symbol, Decimal::from_f64(inverse_close).unwrap_or_default());
+            
+            // The built-in drivers replay the inputs file; any other source
+            // computes against the simulated broker's price history.
+            let mut components = BTreeMap::new();
+            for source in registry.sources() {
+                let component = match source.name() {
+                    "temperature" => temp_signal.clone(),
+                    "inventory" => inventory_signal.clone(),
+                    "storm" => storm_signal.clone(),
+                    _ => source.compute().await,
+                };
+                let component = component.with_weight(source.weight());
+                components.insert(source.name().to_string(), component);
+            }
+            
+            let mut signal = processor.create_trading_signal(components);
+            signal.timestamp = to_utc(bar.date);
+            
+            let cycle_id = bar.date.format("%Y%m%d").to_string();
+            let exit_reasons: HashMap<String, ExitReason> = monitor
+                .check(&cycle_id)
+                .await
+                .into_iter()
+                .map(|exit| (exit.trade.order_id, exit.reason))
+                .collect();
+            executor.execute_trade(&signal, &cycle_id).await;
+            
+            for fill in sim.take_fills() {
+                // Performance statistics are computed in floating point
+                let price = fill.filled_avg_price.and_then(|p| p.to_f64()).unwrap_or(0.0);
+                let shares = fill.filled_qty.unwrap_or(fill.qty).to_f64().unwrap_or(0.0);
+                let entry = book.entry(fill.symbol.clone()).or_insert((0.0, 0.0));
+                let realized_pl = if fill.side == "sell" {
+                    let avg_cost = if entry.0 > 0.0 { entry.1 / entry.0 } else { price };
+                    entry.0 -= shares;
+                    entry.1 -= avg_cost * shares;
+                    Some((price - avg_cost) * shares)
+                } else {
+                    entry.0 += shares;
+                    entry.1 += price * shares;
+                    None
+                };
+                let exit_reason = exit_reasons.get(&fill.order_id).copied();
+                trades.push(BacktestTrade {
+                    date: bar.date,
+                    symbol: fill.symbol,
+                    side: fill.side,
+                    qty: shares,
+                    price,
+                    notional: price * shares,
+                    realized_pl,
+                    exit_reason,
+                });
+            }
+            
+            let account = broker.get_account_info().await?;
+            equity_curve.push(EquityPoint {
+                date: bar.date,
+                equity: account.equity.to_f64().unwrap_or(0.0),
+                total_signal: signal.total_signal,
+                action: signal.action,
+                symbol: signal.symbol,
+            });
+        }
+        
+        drop(quiet);
+        
+        let curve: Vec<(NaiveDate, f64)> = equity_curve.iter().map(|p| (p.date, p.equity)).collect();
+        let closed_pnl: Vec<f64> = trades.iter().filter_map(|t| t.realized_pl).collect();
+        let traded_notional: f64 = trades.iter().map(|t| t.notional).sum();
+        let metrics = PerformanceMetrics::compute(&curve, &closed_pnl, traded_notional, trades.len());
+        
+        Ok(BacktestReport {
+            equity_curve,
+            trades,
+            metrics,
+        })
+    }
+}
+
+impl BacktestReport {
+    pub fn log_summary(&self) {
+        let m = &self.metrics;
+        info!("{}", "=".repeat(60));
+        info!("BACKTEST RESULTS");
+        info!("{}", "=".repeat(60));
+        info!("  Days simulated: {}", m.num_days);
+        info!("  Start equity: ${:.2}", m.start_equity);
+        info!("  End equity: ${:.2}", m.end_equity);
+        info!("  Total return: {:.2}%", m.total_return * 100.0);
+        info!("  CAGR: {:.2}%", m.cagr * 100.0);
+        info!("  Sharpe: {:.3}", m.sharpe);
+        info!("  Sortino: {:.3}", m.sortino);
+        info!("  Max drawdown: {:.2}%", m.max_drawdown * 100.0);
+        info!("  Hit rate: {:.1}%", m.hit_rate * 100.0);
+        info!("  Turnover: {:.2}x per year", m.turnover);
+        info!("  Trades: {}", m.num_trades);
+        info!("{}", "=".repeat(60));
+    }
+    
+    /// Write `equity_curve.csv`, `trades.csv` and `summary.json` into `dir`
+    pub fn write_to_dir(&self, dir: &Path) -> Result<()> {
+        fs::create_dir_all(dir)?;
+        
+        let mut equity_file = fs::File::create(dir.join("equity_curve.csv"))?;
+        writeln!(equity_file, "date,equity,total_signal,action,symbol")?;
+        for point in &self.equity_curve {
+            writeln!(
+                equity_file,
+                "{},{:.2},{:.6},{},{}",
+                point.date, point.equity, point.total_signal, point.action, point.symbol
+            )?;
+        }
+        
+        let mut trades_file = fs::File::create(dir.join("trades.csv"))?;
+        writeln!(trades_file, "date,symbol,side,qty,price,notional,realized_pl,exit_reason")?;
+        for trade in &self.trades {
+            writeln!(
+                trades_file,
+                "{},{},{},{},{:.4},{:.2},{},{}",
+                trade.date,
+                trade.symbol,
+                trade.side,
+                trade.qty,
+                trade.price,
+                trade.notional,
+                trade.realized_pl.map(|p| format!("{:.2}", p)).unwrap_or_default(),
+                trade.exit_reason.map(|r| r.to_string()).unwrap_or_default()
+            )?;
+        }
+        
+        fs::write(dir.join("summary.json"), serde_json::to_string_pretty(&self.metrics)?)?;
+        
+        info!("Backtest results written to {}", dir.display());
+        Ok(())
+    }
+}
+
+/// Lowers the global log level to WARN until dropped, so an early return
+/// through `?` still restores it
+pub(crate) struct QuietLogs {
+    previous: LevelFilter,
+}
+
+impl QuietLogs {
+    pub(crate) fn new() -> Self {
+        let previous = log::max_level();
+        log::set_max_level(LevelFilter::Warn.min(previous));
+        Self { previous }
+    }
+}
+
+impl Drop for QuietLogs {
+    fn drop(&mut self) {
+        log::set_max_level(self.previous);
+    }
+}
+
+fn to_utc(date: NaiveDate) -> DateTime<Utc> {
+    date.and_hms_opt(0, 0, 0).unwrap().and_utc()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+    
+    #[tokio::test]
+    async fn live_limits_and_kill_switch_do_not_apply_to_backtests() {
+        let kill_switch = std::env::temp_dir().join(format!("algotrade-live-kill-{}", std::process::id()));
+        fs::write(&kill_switch, "engaged").unwrap();
+        let config = TradingConfig {
+            max_order_notional: dec!(1),
+            max_daily_trades: 1,
+            max_drawdown_pct: dec!(1),
+            kill_switch_file: kill_switch.to_string_lossy().into_owned(),
+            ..TradingConfig::default()
+        };
+        
+        let first = NaiveDate::from_ymd_opt(2024, 1, 2).unwrap();
+        let days: Vec<NaiveDate> = (0..5).map(|i| first + Duration::days(i)).collect();
+        // A cold spell is a strong buy, then a mild one a strong sell
+        let inputs: Vec<HistoricalInput> = days
+            .iter()
+            .enumerate()
+            .map(|(i, date)| HistoricalInput { date: *date, hdd: Some(if i < 2 { 50.0 } else { 0.0 }), storage_bcf: None, storm_signal: None })
+            .collect();
+        let bars: Vec<DailyBar> = days.iter().map(|date| DailyBar { date: *date, close: 20.0 }).collect();
+        let inverse_bars: Vec<DailyBar> = days.iter().map(|date| DailyBar { date: *date, close: 50.0 }).collect();
+        
+        let report = Backtester::new(config).run(&inputs, &bars, &inverse_bars).await.unwrap();
+        fs::remove_file(&kill_switch).unwrap();
+        assert!(report.trades.len() > 1, "expected trades, got {:?}", report.trades);
+    }
+}
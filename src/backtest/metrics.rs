@@ -0,0 +1,188 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+const TRADING_DAYS_PER_YEAR: f64 = 252.0;
+
+/// Summary statistics for a backtest run
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PerformanceMetrics {
+    pub start_equity: f64,
+    pub end_equity: f64,
+    pub total_return: f64,
+    /// Compound annual growth rate. Windows shorter than a year report their
+    /// total return instead, since extrapolating a few days is meaningless.
+    pub cagr: f64,
+    pub sharpe: f64,
+    pub sortino: f64,
+    pub max_drawdown: f64,
+    /// Fraction of closing trades that realized a profit
+    pub hit_rate: f64,
+    /// Annualized traded notional divided by average equity
+    pub turnover: f64,
+    pub num_trades: usize,
+    pub num_days: usize,
+}
+
+impl PerformanceMetrics {
+    /// Compute metrics from a daily equity curve, the realized P&L of each
+    /// closing trade and the total traded notional.
+    pub fn compute(
+        equity_curve: &[(NaiveDate, f64)],
+        closed_trade_pnl: &[f64],
+        traded_notional: f64,
+        num_trades: usize,
+    ) -> Self {
+        if equity_curve.len() < 2 {
+            return Self {
+                num_trades,
+                num_days: equity_curve.len(),
+                ..Self::default()
+            };
+        }
+        
+        let start_equity = equity_curve[0].1;
+        let end_equity = equity_curve[equity_curve.len() - 1].1;
+        let total_return = end_equity / start_equity - 1.0;
+        
+        let days = (equity_curve[equity_curve.len() - 1].0 - equity_curve[0].0).num_days() as f64;
+        let years = (days / 365.25).max(1.0 / 365.25);
+        let cagr = if years < 1.0 {
+            total_return
+        } else if end_equity > 0.0 {
+            (end_equity / start_equity).powf(1.0 / years) - 1.0
+        } else {
+            -1.0
+        };
+        
+        let returns: Vec<f64> = equity_curve
+            .windows(2)
+            .map(|w| if w[0].1 != 0.0 { w[1].1 / w[0].1 - 1.0 } else { 0.0 })
+            .collect();
+        let mean = returns.iter().sum::<f64>() / returns.len() as f64;
+        let variance = returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / returns.len() as f64;
+        let downside_variance = returns.iter().map(|r| r.min(0.0).powi(2)).sum::<f64>() / returns.len() as f64;
+        
+        let annualize = TRADING_DAYS_PER_YEAR.sqrt();
+        let sharpe = if variance > 0.0 { mean / variance.sqrt() * annualize } else { 0.0 };
+        let sortino = if downside_variance > 0.0 {
+            mean / downside_variance.sqrt() * annualize
+        } else {
+            0.0
+        };
+        
+        let mut peak = f64::MIN;
+        let mut max_drawdown: f64 = 0.0;
+        for (_, equity) in equity_curve {
+            peak = peak.max(*equity);
+            if peak > 0.0 {
+                max_drawdown = max_drawdown.max((peak - equity) / peak);
+            }
+        }
+        
+        let hit_rate = if closed_trade_pnl.is_empty() {
+            0.0
+        } else {
+            closed_trade_pnl.iter().filter(|pnl| **pnl > 0.0).count() as f64 / closed_trade_pnl.len() as f64
+        };
+        
+        let avg_equity = equity_curve.iter().map(|(_, e)| e).sum::<f64>() / equity_curve.len() as f64;
+        let turnover = if avg_equity > 0.0 {
+            traded_notional / avg_equity / years
+        } else {
+            0.0
+        };
+        
+        Self {
+            start_equity,
+            end_equity,
+            total_return,
+            cagr,
+            sharpe,
+            sortino,
+            max_drawdown,
+            hit_rate,
+            turnover,
+            num_trades,
+            num_days: equity_curve.len(),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+    
+    fn daily(start: NaiveDate, equity: &[f64]) -> Vec<(NaiveDate, f64)> {
+        equity
+            .iter()
+            .enumerate()
+            .map(|(i, e)| (start + chrono::Duration::days(i as i64), *e))
+            .collect()
+    }
+    
+    fn assert_close(actual: f64, expected: f64, tolerance: f64) {
+        assert!((actual - expected).abs() < tolerance, "{} != {}", actual, expected);
+    }
+    
+    #[test]
+    fn multi_year_growth_is_annualized() {
+        let curve = [(date(2020, 1, 1), 100.0), (date(2021, 1, 1), 110.0), (date(2022, 1, 1), 121.0)];
+        let m = PerformanceMetrics::compute(&curve, &[], 4.0 * 110.0, 2);
+        
+        assert_close(m.total_return, 0.21, 1e-12);
+        assert_close(m.cagr, 0.10, 1e-3);
+        assert_eq!(m.max_drawdown, 0.0);
+        // Four times the average equity traded over two years
+        assert_close(m.turnover, 2.0, 1e-2);
+        assert_eq!((m.num_trades, m.num_days), (2, 3));
+    }
+    
+    #[test]
+    fn short_windows_report_the_total_return_as_cagr() {
+        let m = PerformanceMetrics::compute(&daily(date(2024, 1, 1), &[100.0, 105.0, 110.0]), &[], 0.0, 0);
+        assert_close(m.total_return, 0.10, 1e-12);
+        assert_close(m.cagr, 0.10, 1e-12);
+    }
+    
+    #[test]
+    fn drawdown_is_measured_from_the_running_peak() {
+        let m = PerformanceMetrics::compute(&daily(date(2024, 1, 1), &[100.0, 120.0, 90.0, 130.0, 117.0]), &[], 0.0, 0);
+        assert_close(m.max_drawdown, 0.25, 1e-12);
+    }
+    
+    #[test]
+    fn sharpe_and_sortino_from_daily_returns() {
+        // Daily returns of +3% then -1%: mean 1%, deviation 2%, downside deviation 1%/sqrt(2)
+        let m = PerformanceMetrics::compute(&daily(date(2024, 1, 1), &[100.0, 103.0, 101.97]), &[], 0.0, 0);
+        assert_close(m.sharpe, 0.5 * TRADING_DAYS_PER_YEAR.sqrt(), 1e-9);
+        assert_close(m.sortino, 2f64.sqrt() * TRADING_DAYS_PER_YEAR.sqrt(), 1e-9);
+        
+        // Without losing days there is no downside risk to divide by
+        let m = PerformanceMetrics::compute(&daily(date(2024, 1, 1), &[100.0, 102.0, 102.0]), &[], 0.0, 0);
+        assert_close(m.sharpe, TRADING_DAYS_PER_YEAR.sqrt(), 1e-9);
+        assert_eq!(m.sortino, 0.0);
+    }
+    
+    #[test]
+    fn hit_rate_counts_profitable_closing_trades() {
+        let curve = daily(date(2024, 1, 1), &[100.0, 100.0]);
+        assert_eq!(PerformanceMetrics::compute(&curve, &[10.0, -5.0, 3.0, 0.0], 0.0, 4).hit_rate, 0.5);
+        assert_eq!(PerformanceMetrics::compute(&curve, &[], 0.0, 0).hit_rate, 0.0);
+    }
+    
+    #[test]
+    fn degenerate_curves() {
+        let m = PerformanceMetrics::compute(&[(date(2024, 1, 1), 100.0)], &[], 0.0, 1);
+        assert_eq!((m.cagr, m.sharpe, m.num_trades, m.num_days), (0.0, 0.0, 1, 1));
+        
+        let wiped_out = [(date(2020, 1, 1), 100.0), (date(2022, 1, 1), 0.0)];
+        let m = PerformanceMetrics::compute(&wiped_out, &[], 0.0, 0);
+        assert_eq!(m.cagr, -1.0);
+        assert_eq!(m.max_drawdown, 1.0);
+    }
+}
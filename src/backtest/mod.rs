@@ -0,0 +1,9 @@
+pub mod data;
+pub mod metrics;
+pub mod engine;
+pub mod optimizer;
+
+pub use data::{load_bars_csv, load_inputs_csv};
+pub use engine::Backtester;
+pub use optimizer::{Objective, Optimizer, ParameterSpace};
+
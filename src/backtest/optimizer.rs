@@ -0,0 +1,483 @@
+use crate::backtest::data::{DailyBar, HistoricalInput};
+use crate::backtest::engine::{Backtester, QuietLogs};
+use crate::backtest::metrics::PerformanceMetrics;
+use crate::config::TradingConfig;
+use anyhow::Result;
+use chrono::NaiveDate;
+use log::info;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+/// The tunable subset of `TradingConfig`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ParameterSet {
+    pub temperature_weight: f64,
+    pub inventory_weight: f64,
+    pub storm_weight: f64,
+    pub buy_threshold: f64,
+    pub sell_threshold: f64,
+}
+
+impl ParameterSet {
+    pub fn apply(&self, config: &TradingConfig) -> TradingConfig {
+        let mut config = config.clone();
+        for (name, weight) in [
+            ("temperature", self.temperature_weight),
+            ("inventory", self.inventory_weight),
+            ("storm", self.storm_weight),
+        ] {
+            config.signal_weights.insert(name.to_string(), weight);
+        }
+        config.buy_threshold = self.buy_threshold;
+        config.sell_threshold = self.sell_threshold;
+        config
+    }
+    
+    /// Render as environment-file lines that `TradingConfig::from_file` can load
+    pub fn to_env(&self) -> String {
+        format!(
+            "TEMPERATURE_WEIGHT={}\nINVENTORY_WEIGHT={}\nSTORM_WEIGHT={}\nBUY_THRESHOLD={}\nSELL_THRESHOLD={}\n",
+            self.temperature_weight, self.inventory_weight, self.storm_weight,
+            self.buy_threshold, self.sell_threshold
+        )
+    }
+}
+
+/// Candidate values for every tunable parameter
+#[derive(Debug, Clone)]
+pub struct ParameterSpace {
+    pub temperature_weights: Vec<f64>,
+    pub inventory_weights: Vec<f64>,
+    pub storm_weights: Vec<f64>,
+    pub buy_thresholds: Vec<f64>,
+    pub sell_thresholds: Vec<f64>,
+}
+
+impl ParameterSpace {
+    /// Parse either a comma separated list ("0.3,0.5") or an inclusive
+    /// range with a step ("0.1:0.9:0.2"). An empty string yields `default`.
+    pub fn parse_values(spec: &str, default: f64) -> Result<Vec<f64>> {
+        let spec = spec.trim();
+        if spec.is_empty() {
+            return Ok(vec![default]);
+        }
+        
+        let parts: Vec<&str> = spec.split(':').collect();
+        if parts.len() == 3 {
+            let start: f64 = parts[0].trim().parse()?;
+            let end: f64 = parts[1].trim().parse()?;
+            let step: f64 = parts[2].trim().parse()?;
+            if step <= 0.0 || end < start {
+                return Err(anyhow::anyhow!("Invalid range '{}': expected start:end:step with step > 0", spec));
+            }
+            let count = ((end - start) / step + 1e-9).floor() as usize;
+            return Ok((0..=count).map(|i| round4(start + step * i as f64)).collect());
+        }
+        
+        spec.split(',')
+            .map(|v| v.trim().parse::<f64>().map_err(|e| anyhow::anyhow!("Invalid value '{}': {}", v, e)))
+            .collect()
+    }
+    
+    /// Every combination of the candidate values
+    pub fn grid(&self) -> Vec<ParameterSet> {
+        let mut sets = Vec::new();
+        for &temperature_weight in &self.temperature_weights {
+            for &inventory_weight in &self.inventory_weights {
+                for &storm_weight in &self.storm_weights {
+                    for &buy_threshold in &self.buy_thresholds {
+                        for &sell_threshold in &self.sell_thresholds {
+                            sets.push(ParameterSet {
+                                temperature_weight,
+                                inventory_weight,
+                                storm_weight,
+                                buy_threshold,
+                                sell_threshold,
+                            });
+                        }
+                    }
+                }
+            }
+        }
+        sets
+    }
+    
+    /// `samples` points drawn uniformly between the min and max of each axis
+    pub fn random(&self, samples: usize, seed: u64) -> Vec<ParameterSet> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut draw = |values: &[f64]| -> f64 {
+            let lo = values.iter().cloned().fold(f64::INFINITY, f64::min);
+            let hi = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+            if hi > lo { round4(rng.gen_range(lo..=hi)) } else { lo }
+        };
+        
+        (0..samples)
+            .map(|_| ParameterSet {
+                temperature_weight: draw(&self.temperature_weights),
+                inventory_weight: draw(&self.inventory_weights),
+                storm_weight: draw(&self.storm_weights),
+                buy_threshold: draw(&self.buy_thresholds),
+                sell_threshold: draw(&self.sell_thresholds),
+            })
+            .collect()
+    }
+}
+
+/// Metric the optimizer maximizes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Objective {
+    Sharpe,
+    Sortino,
+    Cagr,
+    /// CAGR divided by max drawdown
+    Calmar,
+}
+
+impl Objective {
+    pub fn score(&self, metrics: &PerformanceMetrics) -> f64 {
+        match self {
+            Objective::Sharpe => metrics.sharpe,
+            Objective::Sortino => metrics.sortino,
+            Objective::Cagr => metrics.cagr,
+            Objective::Calmar => {
+                if metrics.max_drawdown > 0.0 {
+                    metrics.cagr / metrics.max_drawdown
+                } else {
+                    metrics.cagr
+                }
+            }
+        }
+    }
+}
+
+/// One rolling train/test split, in trading days of the primary symbol
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Fold {
+    pub train_start: NaiveDate,
+    pub train_end: NaiveDate,
+    pub test_start: NaiveDate,
+    pub test_end: NaiveDate,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CandidateResult {
+    pub rank: usize,
+    pub params: ParameterSet,
+    pub mean_train_score: f64,
+    pub mean_test_score: f64,
+    pub mean_test_cagr: f64,
+    pub worst_test_drawdown: f64,
+    pub test_trades: usize,
+}
+
+/// Parameters chosen on a fold's training window and how they did out of sample
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FoldSelection {
+    pub fold: Fold,
+    pub params: ParameterSet,
+    pub train_score: f64,
+    pub test_score: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OptimizationReport {
+    pub objective: String,
+    pub folds: Vec<FoldSelection>,
+    /// Mean out-of-sample score of the per-fold selections
+    pub walk_forward_score: f64,
+    pub ranked: Vec<CandidateResult>,
+}
+
+/// Walk-forward search over `ParameterSet` candidates.
+///
+/// Every candidate is backtested on each fold's train and test windows.
+/// Candidates are ranked, and the winner exported, by their mean train score
+/// only; test scores are reported alongside but never used to choose, so the
+/// out-of-sample estimate is not inflated by selecting on the test data. For
+/// each fold the best-on-train candidate is reported with its test score.
+pub struct Optimizer {
+    config: TradingConfig,
+    objective: Objective,
+}
+
+impl Optimizer {
+    pub fn new(config: TradingConfig, objective: Objective) -> Self {
+        Self { config, objective }
+    }
+    
+    pub fn build_folds(bars: &[DailyBar], train_days: usize, test_days: usize) -> Vec<Fold> {
+        let mut folds = Vec::new();
+        if train_days == 0 || test_days == 0 {
+            return folds;
+        }
+        
+        let mut start = 0;
+        while start + train_days + test_days <= bars.len() {
+            folds.push(Fold {
+                train_start: bars[start].date,
+                train_end: bars[start + train_days - 1].date,
+                test_start: bars[start + train_days].date,
+                test_end: bars[start + train_days + test_days - 1].date,
+            });
+            start += test_days;
+        }
+        folds
+    }
+    
+    pub async fn run(
+        &self,
+        inputs: &[HistoricalInput],
+        bars: &[DailyBar],
+        inverse_bars: &[DailyBar],
+        candidates: &[ParameterSet],
+        folds: &[Fold],
+    ) -> Result<OptimizationReport> {
+        if candidates.is_empty() {
+            return Err(anyhow::anyhow!("No parameter sets to evaluate"));
+        }
+        if folds.is_empty() {
+            return Err(anyhow::anyhow!("Not enough bars for a single train/test fold"));
+        }
+        
+        info!(
+            "Evaluating {} parameter sets over {} walk-forward folds ({:?} objective)",
+            candidates.len(), folds.len(), self.objective
+        );
+        
+        let mut quiet = QuietLogs::new();
+        
+        // scores[candidate][fold] = (train metrics, test metrics)
+        let mut scores: Vec<Vec<(PerformanceMetrics, PerformanceMetrics)>> = Vec::new();
+        for (idx, params) in candidates.iter().enumerate() {
+            let backtester = Backtester::new(params.apply(&self.config));
+            let mut per_fold = Vec::new();
+            for fold in folds {
+                let train = backtester
+                    .run(inputs, &window(bars, fold.train_start, fold.train_end), &window(inverse_bars, fold.train_start, fold.train_end))
+                    .await?;
+                let test = backtester
+                    .run(inputs, &window(bars, fold.test_start, fold.test_end), &window(inverse_bars, fold.test_start, fold.test_end))
+                    .await?;
+                per_fold.push((train.metrics, test.metrics));
+            }
+            scores.push(per_fold);
+            
+            if (idx + 1) % 25 == 0 || idx + 1 == candidates.len() {
+                drop(quiet);
+                info!("  Evaluated {}/{} parameter sets", idx + 1, candidates.len());
+                quiet = QuietLogs::new();
+            }
+        }
+        
+        drop(quiet);
+        
+        let mut selections = Vec::new();
+        for (fold_idx, fold) in folds.iter().enumerate() {
+            let best = (0..candidates.len())
+                .max_by(|&a, &b| {
+                    self.objective.score(&scores[a][fold_idx].0)
+                        .total_cmp(&self.objective.score(&scores[b][fold_idx].0))
+                })
+                .unwrap();
+            selections.push(FoldSelection {
+                fold: fold.clone(),
+                params: candidates[best].clone(),
+                train_score: self.objective.score(&scores[best][fold_idx].0),
+                test_score: self.objective.score(&scores[best][fold_idx].1),
+            });
+        }
+        let walk_forward_score = selections.iter().map(|s| s.test_score).sum::<f64>() / selections.len() as f64;
+        
+        let mut ranked: Vec<CandidateResult> = candidates
+            .iter()
+            .zip(&scores)
+            .map(|(params, per_fold)| {
+                let n = per_fold.len() as f64;
+                CandidateResult {
+                    rank: 0,
+                    params: params.clone(),
+                    mean_train_score: per_fold.iter().map(|(train, _)| self.objective.score(train)).sum::<f64>() / n,
+                    mean_test_score: per_fold.iter().map(|(_, test)| self.objective.score(test)).sum::<f64>() / n,
+                    mean_test_cagr: per_fold.iter().map(|(_, test)| test.cagr).sum::<f64>() / n,
+                    worst_test_drawdown: per_fold.iter().map(|(_, test)| test.max_drawdown).fold(0.0, f64::max),
+                    test_trades: per_fold.iter().map(|(_, test)| test.num_trades).sum(),
+                }
+            })
+            .collect();
+        ranked.sort_by(|a, b| b.mean_train_score.total_cmp(&a.mean_train_score));
+        for (idx, result) in ranked.iter_mut().enumerate() {
+            result.rank = idx + 1;
+        }
+        
+        Ok(OptimizationReport {
+            objective: format!("{:?}", self.objective).to_lowercase(),
+            folds: selections,
+            walk_forward_score,
+            ranked,
+        })
+    }
+}
+
+impl OptimizationReport {
+    pub fn log_summary(&self) {
+        info!("{}", "=".repeat(60));
+        info!("OPTIMIZATION RESULTS ({})", self.objective);
+        info!("{}", "=".repeat(60));
+        for selection in &self.folds {
+            info!(
+                "  Fold {} -> {}: train {:.3}, test {:.3} with {:?}",
+                selection.fold.test_start, selection.fold.test_end,
+                selection.train_score, selection.test_score, selection.params
+            );
+        }
+        info!("  Walk-forward out-of-sample score: {:.3}", self.walk_forward_score);
+        for result in self.ranked.iter().take(5) {
+            info!(
+                "  #{}: train {:.3} (test {:.3}) {:?}",
+                result.rank, result.mean_train_score, result.mean_test_score, result.params
+            );
+        }
+        info!("{}", "=".repeat(60));
+    }
+    
+    /// Write `ranked.csv`, `ranked.json` and `best_params.env` into `dir`
+    pub fn write_to_dir(&self, dir: &Path) -> Result<()> {
+        fs::create_dir_all(dir)?;
+        
+        let mut csv = fs::File::create(dir.join("ranked.csv"))?;
+        writeln!(
+            csv,
+            "rank,temperature_weight,inventory_weight,storm_weight,buy_threshold,sell_threshold,mean_train_score,mean_test_score,mean_test_cagr,worst_test_drawdown,test_trades"
+        )?;
+        for r in &self.ranked {
+            writeln!(
+                csv,
+                "{},{},{},{},{},{},{:.6},{:.6},{:.6},{:.6},{}",
+                r.rank,
+                r.params.temperature_weight,
+                r.params.inventory_weight,
+                r.params.storm_weight,
+                r.params.buy_threshold,
+                r.params.sell_threshold,
+                r.mean_train_score,
+                r.mean_test_score,
+                r.mean_test_cagr,
+                r.worst_test_drawdown,
+                r.test_trades
+            )?;
+        }
+        
+        fs::write(dir.join("ranked.json"), serde_json::to_string_pretty(self)?)?;
+        
+        if let Some(best) = self.ranked.first() {
+            let env = format!(
+                "# Best parameters by mean in-sample (train) {} (rank 1 of {})\n{}",
+                self.objective,
+                self.ranked.len(),
+                best.params.to_env()
+            );
+            fs::write(dir.join("best_params.env"), env)?;
+        }
+        
+        info!("Optimization results written to {}", dir.display());
+        Ok(())
+    }
+}
+
+fn window(bars: &[DailyBar], start: NaiveDate, end: NaiveDate) -> Vec<DailyBar> {
+    bars.iter()
+        .filter(|bar| bar.date >= start && bar.date <= end)
+        .cloned()
+        .collect()
+}
+
+fn round4(value: f64) -> f64 {
+    (value * 10_000.0).round() / 10_000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn bars(count: usize) -> Vec<DailyBar> {
+        let first = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        (0..count)
+            .map(|i| DailyBar { date: first + chrono::Duration::days(i as i64), close: 10.0 })
+            .collect()
+    }
+    
+    fn day(n: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 1, n).unwrap()
+    }
+    
+    #[test]
+    fn folds_roll_forward_by_the_test_window() {
+        let folds = Optimizer::build_folds(&bars(10), 4, 2);
+        let spans: Vec<_> = folds.iter().map(|f| (f.train_start, f.train_end, f.test_start, f.test_end)).collect();
+        assert_eq!(spans, vec![
+            (day(1), day(4), day(5), day(6)),
+            (day(3), day(6), day(7), day(8)),
+            (day(5), day(8), day(9), day(10)),
+        ]);
+        // A trailing partial test window is dropped
+        assert_eq!(Optimizer::build_folds(&bars(11), 4, 2).len(), 3);
+    }
+    
+    #[test]
+    fn folds_need_enough_bars_and_non_empty_windows() {
+        assert!(Optimizer::build_folds(&bars(5), 4, 2).is_empty());
+        assert_eq!(Optimizer::build_folds(&bars(6), 4, 2).len(), 1);
+        assert!(Optimizer::build_folds(&bars(10), 0, 2).is_empty());
+        assert!(Optimizer::build_folds(&bars(10), 4, 0).is_empty());
+        assert!(Optimizer::build_folds(&[], 4, 2).is_empty());
+    }
+    
+    #[test]
+    fn parses_lists_and_ranges() {
+        assert_eq!(ParameterSpace::parse_values("0.3, 0.5,0.7", 0.0).unwrap(), vec![0.3, 0.5, 0.7]);
+        assert_eq!(ParameterSpace::parse_values("0.1:0.9:0.2", 0.0).unwrap(), vec![0.1, 0.3, 0.5, 0.7, 0.9]);
+        // The end is included only when a step lands on it
+        assert_eq!(ParameterSpace::parse_values("0:1:0.3", 0.0).unwrap(), vec![0.0, 0.3, 0.6, 0.9]);
+        assert_eq!(ParameterSpace::parse_values("0.5:0.5:0.1", 0.0).unwrap(), vec![0.5]);
+        assert_eq!(ParameterSpace::parse_values("-0.2", 0.0).unwrap(), vec![-0.2]);
+    }
+    
+    #[test]
+    fn empty_spec_uses_the_default() {
+        assert_eq!(ParameterSpace::parse_values("", 0.4).unwrap(), vec![0.4]);
+        assert_eq!(ParameterSpace::parse_values("  ", 0.4).unwrap(), vec![0.4]);
+    }
+    
+    #[test]
+    fn rejects_invalid_specs() {
+        for spec in ["0.3,abc", "0.3,", "1:0:0.1", "0:1:0", "0:1:-0.1", "0:x:0.1", "0:1"] {
+            assert!(ParameterSpace::parse_values(spec, 0.0).is_err(), "{} should be rejected", spec);
+        }
+    }
+    
+    #[test]
+    fn grid_and_random_cover_the_space() {
+        let space = ParameterSpace {
+            temperature_weights: vec![0.2, 0.4],
+            inventory_weights: vec![0.3, 0.5, 0.7],
+            storm_weights: vec![0.1],
+            buy_thresholds: vec![0.3, 0.4],
+            sell_thresholds: vec![-0.3],
+        };
+        assert_eq!(space.grid().len(), 12);
+        
+        let samples = space.random(20, 7);
+        assert_eq!(samples, space.random(20, 7));
+        for set in &samples {
+            assert!((0.2..=0.4).contains(&set.temperature_weight));
+            assert!((0.3..=0.7).contains(&set.inventory_weight));
+            assert_eq!(set.storm_weight, 0.1);
+            assert_eq!(set.sell_threshold, -0.3);
+        }
+    }
+}
+
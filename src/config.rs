@@ -1,4 +1,8 @@
+use crate::signals::registry::{self, SOURCES};
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::env;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -7,18 +11,85 @@ pub struct TradingConfig {
     pub alpaca_api_key: String,
     pub alpaca_secret_key: String,
     pub alpaca_base_url: String,
+    /// Host of Alpaca's market data API (bars and quotes), separate from the trading API
+    pub alpaca_data_url: String,
+    /// Stream trade updates over WebSocket instead of relying on polling alone
+    pub alpaca_stream_enabled: bool,
+    pub alpaca_stream_url: String,
+    
+    // Broker Selection ("alpaca" or "simulated")
+    pub broker: String,
+    
+    // Simulated Broker Configuration
+    pub sim_starting_cash: Decimal,
+    pub sim_commission_per_order: Decimal,
+    pub sim_slippage_bps: Decimal,
+    pub sim_prices: HashMap<String, Decimal>,
+    /// Daily bar CSVs replayed into the simulated broker's price history, by symbol
+    pub sim_bars: HashMap<String, String>,
     
     // Trading Parameters
     pub symbol: String,
     pub inverse_symbol: String,
-    pub position_size: f64,
+    pub position_size: Decimal,
+    /// "fixed", "confidence", "vol_target", "kelly" or "percent_equity"
+    pub position_sizing: String,
+    pub vol_target_pct: Decimal,
+    pub vol_lookback_days: usize,
+    pub vol_max_scale: Decimal,
+    pub kelly_win_rate: Decimal,
+    pub kelly_payoff_ratio: Decimal,
+    pub kelly_fraction: Decimal,
+    pub position_pct_equity: Decimal,
     pub buy_threshold: f64,
     pub sell_threshold: f64,
+    /// "whole" shares, "fractional" shares, or "notional" dollar amounts for buys
+    pub order_qty_mode: String,
+    /// Minimum drift (percent of the target quantity) before an existing
+    /// position is topped up or trimmed
+    pub rebalance_band_pct: f64,
+    
+    // Risk Limits (0 disables a limit)
+    pub max_order_notional: Decimal,
+    pub max_gross_exposure: Decimal,
+    pub max_exposure_pct_equity: Decimal,
+    pub max_daily_trades: u32,
+    pub daily_loss_limit_pct: Decimal,
+    pub weekly_loss_limit_pct: Decimal,
+    /// While this file exists no orders are placed
+    pub kill_switch_file: String,
+    /// Where the risk engine keeps its daily and weekly counters
+    pub risk_state_file: String,
+    /// Drawdown from the equity high-water mark that trips the circuit breaker
+    pub max_drawdown_pct: Decimal,
+    /// Where the circuit breaker keeps its high-water mark and tripped state
+    pub breaker_state_file: String,
+    /// What to do when broker positions or orders drift from the bot's
+    /// records: "alert" only, "adopt" the broker's state, or "flatten"
+    pub reconcile_policy: String,
+    
+    // Position Protection (percentages, 0 disables a rule)
+    pub stop_loss_pct: f64,
+    pub trailing_stop_pct: f64,
+    pub take_profit_pct: f64,
+    /// How often open positions are checked between trading cycles
+    pub protection_check_interval_secs: u64,
     
-    // Signal Weights
-    pub temperature_weight: f64,
-    pub inventory_weight: f64,
-    pub storm_weight: f64,
+    // Signal Sources
+    /// Components combined into the trading signal (see `SignalRegistry`)
+    pub signal_sources: Vec<String>,
+    /// Weight of each source, from `<NAME>_WEIGHT`
+    pub signal_weights: HashMap<String, f64>,
+    /// Daily closes the momentum source measures its return over
+    pub momentum_lookback_days: usize,
+    /// Return (in %) at which the momentum component reaches +/-1
+    pub momentum_full_scale_pct: f64,
+    
+    // Missing Data Handling
+    /// "renormalize" spreads weight over available components; "refuse" holds
+    /// when any of `required_components` is unavailable
+    pub missing_data_policy: String,
+    pub required_components: Vec<String>,
     
     // Weather API Configuration
     pub weather_api_url: String,
@@ -27,28 +98,157 @@ pub struct TradingConfig {
     // EIA API Configuration
     pub eia_api_key: String,
     pub eia_api_url: String,
+    pub eia_max_age_days: i64,
     
     // NOAA API Configuration
     pub noaa_api_url: String,
     
+    // Market Hours Configuration
+    /// What to do when a cycle fires while the market is closed: "skip"
+    /// execution, "defer" until the next open, or "ignore" the clock
+    pub market_closed_policy: String,
+    /// Minutes after the open at which deferred and `--at-open` cycles trade
+    pub trade_open_offset_minutes: i64,
+    
+    // Scheduler Configuration
+    /// Jobs for the `schedule` command: cron expressions in US/Eastern time or
+    /// `eia` for the weekly storage report, separated by ';'
+    pub schedule: Vec<String>,
+    pub eia_release_delay_minutes: i64,
+    /// "YYYY-MM-DD HH:MM" Eastern release times for irregular weeks, comma separated
+    pub eia_release_overrides: String,
+    
+    // Order Lifecycle Configuration
+    pub order_fill_timeout_secs: u64,
+    pub order_poll_interval_ms: u64,
+    /// Cancel every open order when the bot exits
+    pub cancel_orders_on_shutdown: bool,
+    /// What to do when a leg of a position flip fails: "retry" the leg,
+    /// "rollback" to the pre-trade positions, or "halt" trading
+    pub flip_failure_policy: String,
+    pub flip_max_retries: u32,
+    /// Order pricing and escalation for symbols without their own policy
+    pub execution: ExecutionPolicy,
+    /// Per-symbol policies from `ORDER_TYPE_<SYMBOL>` etc.
+    pub symbol_execution: HashMap<String, ExecutionPolicy>,
+    
+    // HTTP Retry Configuration
+    pub http_timeout_secs: u64,
+    pub http_max_retries: u32,
+    pub http_backoff_base_ms: u64,
+    pub http_backoff_max_ms: u64,
+    
     // Logging Configuration
     pub log_level: String,
     pub log_file: String,
+    /// Directory for the JSON logs and the default state files
+    pub log_dir: String,
+    /// SQLite history of cycles, signals, orders and portfolio snapshots; empty disables it
+    pub state_db_path: String,
 }
 
 impl Default for TradingConfig {
     fn default() -> Self {
+        let alpaca_base_url = env::var("ALPACA_BASE_URL")
+            .unwrap_or_else(|_| "https://paper-api.alpaca.markets".to_string());
+        let default_stream_url = format!("{}/stream", alpaca_base_url.replacen("https://", "wss://", 1).trim_end_matches('/'));
+        let broker = env::var("BROKER")
+            .map(|s| s.to_lowercase())
+            .unwrap_or_else(|_| "alpaca".to_string());
+        let log_dir = env::var("LOG_DIR").unwrap_or_else(|_| "logs".to_string());
+        let symbol = env::var("SYMBOL").unwrap_or_else(|_| "BOIL".to_string());
+        let inverse_symbol = env::var("INVERSE_SYMBOL").unwrap_or_else(|_| "KOLD".to_string());
+        let execution = ExecutionPolicy::from_env("", &ExecutionPolicy::default());
+        let symbol_execution = [&symbol, &inverse_symbol]
+            .into_iter()
+            .map(|s| (s.clone(), ExecutionPolicy::from_env(&format!("_{}", s.to_uppercase()), &execution)))
+            .collect();
+        let signal_sources: Vec<String> = env::var("SIGNAL_SOURCES")
+            .unwrap_or_else(|_| "temperature,inventory,storm".to_string())
+            .split(',')
+            .map(|s| s.trim().to_lowercase())
+            .filter(|s| !s.is_empty())
+            .collect();
+        // Sources without a registered default weight must be given one
+        let signal_weights = signal_sources
+            .iter()
+            .filter_map(|name| {
+                let default = registry::registration(name).and_then(|source| source.default_weight);
+                env::var(format!("{}_WEIGHT", name.to_uppercase()))
+                    .ok()
+                    .and_then(|s| s.parse().ok())
+                    .or(default)
+                    .map(|weight| (name.clone(), weight))
+            })
+            .collect();
+        
         Self {
             alpaca_api_key: env::var("ALPACA_API_KEY").unwrap_or_default(),
             alpaca_secret_key: env::var("ALPACA_SECRET_KEY").unwrap_or_default(),
-            alpaca_base_url: env::var("ALPACA_BASE_URL")
-                .unwrap_or_else(|_| "https://paper-api.alpaca.markets".to_string()),
-            symbol: env::var("SYMBOL").unwrap_or_else(|_| "BOIL".to_string()),
-            inverse_symbol: env::var("INVERSE_SYMBOL").unwrap_or_else(|_| "KOLD".to_string()),
+            alpaca_base_url,
+            alpaca_data_url: env::var("ALPACA_DATA_URL")
+                .unwrap_or_else(|_| "https://data.alpaca.markets".to_string()),
+            alpaca_stream_enabled: env::var("ALPACA_STREAM_ENABLED")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(true),
+            alpaca_stream_url: env::var("ALPACA_STREAM_URL").unwrap_or(default_stream_url),
+            broker: broker.clone(),
+            sim_starting_cash: env::var("SIM_STARTING_CASH")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(dec!(100000)),
+            sim_commission_per_order: env::var("SIM_COMMISSION_PER_ORDER")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(Decimal::ZERO),
+            sim_slippage_bps: env::var("SIM_SLIPPAGE_BPS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(dec!(5)),
+            sim_prices: env::var("SIM_PRICES")
+                .map(|s| parse_symbol_prices(&s))
+                .unwrap_or_default(),
+            sim_bars: env::var("SIM_BARS")
+                .map(|s| parse_symbol_paths(&s))
+                .unwrap_or_default(),
+            symbol,
+            inverse_symbol,
             position_size: env::var("POSITION_SIZE")
                 .ok()
                 .and_then(|s| s.parse().ok())
-                .unwrap_or(1000.0),
+                .unwrap_or(dec!(1000)),
+            position_sizing: env::var("POSITION_SIZING")
+                .map(|s| s.to_lowercase())
+                .unwrap_or_else(|_| "fixed".to_string()),
+            vol_target_pct: env::var("VOL_TARGET_PCT")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(dec!(40)),
+            vol_lookback_days: env::var("VOL_LOOKBACK_DAYS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(20),
+            vol_max_scale: env::var("VOL_MAX_SCALE")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(dec!(2)),
+            kelly_win_rate: env::var("KELLY_WIN_RATE")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(dec!(0.55)),
+            kelly_payoff_ratio: env::var("KELLY_PAYOFF_RATIO")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(Decimal::ONE),
+            kelly_fraction: env::var("KELLY_FRACTION")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(dec!(0.5)),
+            position_pct_equity: env::var("POSITION_PCT_EQUITY")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(dec!(5)),
             buy_threshold: env::var("BUY_THRESHOLD")
                 .ok()
                 .and_then(|s| s.parse().ok())
@@ -57,18 +257,86 @@ impl Default for TradingConfig {
                 .ok()
                 .and_then(|s| s.parse().ok())
                 .unwrap_or(-0.3),
-            temperature_weight: env::var("TEMPERATURE_WEIGHT")
+            order_qty_mode: env::var("ORDER_QTY_MODE")
+                .map(|s| s.to_lowercase())
+                .unwrap_or_else(|_| "whole".to_string()),
+            rebalance_band_pct: env::var("REBALANCE_BAND_PCT")
                 .ok()
                 .and_then(|s| s.parse().ok())
-                .unwrap_or(0.5),
-            inventory_weight: env::var("INVENTORY_WEIGHT")
+                .unwrap_or(10.0),
+            max_order_notional: env::var("MAX_ORDER_NOTIONAL")
                 .ok()
                 .and_then(|s| s.parse().ok())
-                .unwrap_or(0.4),
-            storm_weight: env::var("STORM_WEIGHT")
+                .unwrap_or(Decimal::ZERO),
+            max_gross_exposure: env::var("MAX_GROSS_EXPOSURE")
                 .ok()
                 .and_then(|s| s.parse().ok())
-                .unwrap_or(0.1),
+                .unwrap_or(Decimal::ZERO),
+            max_exposure_pct_equity: env::var("MAX_EXPOSURE_PCT_EQUITY")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(Decimal::ZERO),
+            max_daily_trades: env::var("MAX_DAILY_TRADES")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(0),
+            daily_loss_limit_pct: env::var("DAILY_LOSS_LIMIT_PCT")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(Decimal::ZERO),
+            weekly_loss_limit_pct: env::var("WEEKLY_LOSS_LIMIT_PCT")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(Decimal::ZERO),
+            kill_switch_file: env::var("KILL_SWITCH_FILE").unwrap_or_else(|_| "KILL_SWITCH".to_string()),
+            risk_state_file: env::var("RISK_STATE_FILE")
+                .unwrap_or_else(|_| format!("{}/risk_state-{}.json", log_dir, broker)),
+            max_drawdown_pct: env::var("MAX_DRAWDOWN_PCT")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(Decimal::ZERO),
+            // Per broker, like the state store: a simulated account's equity must
+            // never become the high-water mark of a real one
+            breaker_state_file: env::var("BREAKER_STATE_FILE")
+                .unwrap_or_else(|_| format!("{}/circuit_breaker-{}.json", log_dir, broker)),
+            reconcile_policy: env::var("RECONCILE_POLICY")
+                .map(|s| s.to_lowercase())
+                .unwrap_or_else(|_| "alert".to_string()),
+            stop_loss_pct: env::var("STOP_LOSS_PCT")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(0.0),
+            trailing_stop_pct: env::var("TRAILING_STOP_PCT")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(0.0),
+            take_profit_pct: env::var("TAKE_PROFIT_PCT")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(0.0),
+            protection_check_interval_secs: env::var("PROTECTION_CHECK_INTERVAL_SECS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(60),
+            signal_sources,
+            signal_weights,
+            momentum_lookback_days: env::var("MOMENTUM_LOOKBACK_DAYS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(20),
+            momentum_full_scale_pct: env::var("MOMENTUM_FULL_SCALE_PCT")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(20.0),
+            missing_data_policy: env::var("MISSING_DATA_POLICY")
+                .map(|s| s.to_lowercase())
+                .unwrap_or_else(|_| "renormalize".to_string()),
+            required_components: env::var("REQUIRED_COMPONENTS")
+                .unwrap_or_else(|_| "temperature,inventory".to_string())
+                .split(',')
+                .map(|s| s.trim().to_lowercase())
+                .filter(|s| !s.is_empty())
+                .collect(),
             weather_api_url: "https://api.open-meteo.com/v1/forecast".to_string(),
             weather_regions: vec![
                 "40.7128,-74.0060".to_string(), // New York
@@ -79,9 +347,124 @@ impl Default for TradingConfig {
             ],
             eia_api_key: env::var("EIA_API_KEY").unwrap_or_default(),
             eia_api_url: "https://api.eia.gov/v2/natural-gas/stor/wkly/data/".to_string(),
+            eia_max_age_days: env::var("EIA_MAX_AGE_DAYS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(10),
             noaa_api_url: "https://api.weather.gov/alerts".to_string(),
+            // A simulated account has no exchange behind it, so it trades around the clock
+            market_closed_policy: env::var("MARKET_CLOSED_POLICY")
+                .map(|s| s.to_lowercase())
+                .unwrap_or_else(|_| if broker == "simulated" { "ignore" } else { "skip" }.to_string()),
+            trade_open_offset_minutes: env::var("TRADE_OPEN_OFFSET_MINUTES")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(30),
+            schedule: env::var("SCHEDULE")
+                .unwrap_or_else(|_| "eia;0 9 * * MON-FRI".to_string())
+                .split(';')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect(),
+            eia_release_delay_minutes: env::var("EIA_RELEASE_DELAY_MINUTES")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(5),
+            eia_release_overrides: env::var("EIA_RELEASE_OVERRIDES").unwrap_or_default(),
+            order_fill_timeout_secs: env::var("ORDER_FILL_TIMEOUT_SECS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(30),
+            order_poll_interval_ms: env::var("ORDER_POLL_INTERVAL_MS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(500),
+            cancel_orders_on_shutdown: env::var("CANCEL_ORDERS_ON_SHUTDOWN")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(false),
+            flip_failure_policy: env::var("FLIP_FAILURE_POLICY")
+                .map(|s| s.to_lowercase())
+                .unwrap_or_else(|_| "rollback".to_string()),
+            flip_max_retries: env::var("FLIP_MAX_RETRIES")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(2),
+            execution,
+            symbol_execution,
+            http_timeout_secs: env::var("HTTP_TIMEOUT_SECS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(30),
+            http_max_retries: env::var("HTTP_MAX_RETRIES")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(3),
+            http_backoff_base_ms: env::var("HTTP_BACKOFF_BASE_MS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(500),
+            http_backoff_max_ms: env::var("HTTP_BACKOFF_MAX_MS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(30000),
             log_level: env::var("LOG_LEVEL").unwrap_or_else(|_| "INFO".to_string()),
             log_file: env::var("LOG_FILE").unwrap_or_else(|_| "trading_bot.log".to_string()),
+            // One database per broker so simulated orders never mix with a real account's
+            state_db_path: env::var("STATE_DB_PATH").unwrap_or_else(|_| format!("{}/state-{}.db", log_dir, broker)),
+            log_dir,
+        }
+    }
+}
+
+/// How orders for one symbol are priced and escalated
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExecutionPolicy {
+    /// "market", "limit", "marketable_limit" or "stop"
+    pub order_type: String,
+    /// Distance from the quote of marketable-limit and stop prices
+    pub max_slippage_bps: Decimal,
+    /// Seconds an unfilled limit or stop order works before it is repriced
+    pub reprice_after_secs: u64,
+    pub max_reprices: u32,
+    /// Send whatever is still unfilled after the last reprice as a market order
+    pub fallback_to_market: bool,
+}
+
+impl Default for ExecutionPolicy {
+    fn default() -> Self {
+        Self {
+            order_type: "market".to_string(),
+            max_slippage_bps: dec!(20),
+            reprice_after_secs: 15,
+            max_reprices: 2,
+            fallback_to_market: true,
+        }
+    }
+}
+
+impl ExecutionPolicy {
+    /// Read `ORDER_TYPE`, `MAX_SLIPPAGE_BPS`, `REPRICE_AFTER_SECS`, `MAX_REPRICES`
+    /// and `FALLBACK_TO_MARKET` with `suffix` appended (e.g. `ORDER_TYPE_KOLD`),
+    /// keeping `defaults` for unset variables
+    fn from_env(suffix: &str, defaults: &ExecutionPolicy) -> Self {
+        let var = |name: &str| env::var(format!("{}{}", name, suffix)).ok();
+        Self {
+            order_type: var("ORDER_TYPE")
+                .map(|s| s.to_lowercase())
+                .unwrap_or_else(|| defaults.order_type.clone()),
+            max_slippage_bps: var("MAX_SLIPPAGE_BPS")
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(defaults.max_slippage_bps),
+            reprice_after_secs: var("REPRICE_AFTER_SECS")
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(defaults.reprice_after_secs),
+            max_reprices: var("MAX_REPRICES")
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(defaults.max_reprices),
+            fallback_to_market: var("FALLBACK_TO_MARKET")
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(defaults.fallback_to_market),
         }
     }
 }
@@ -94,11 +477,176 @@ impl TradingConfig {
         Self::default()
     }
     
+    /// Load settings from an env-style file (e.g. an optimizer's `best_params.env`)
+    /// before the usual `config.env`/`.env` lookup. Values in `path` take precedence
+    /// over those files but not over variables already set in the environment.
+    pub fn from_file(path: &std::path::Path) -> Result<Self, String> {
+        dotenv::from_path(path)
+            .map_err(|e| format!("Failed to load config file {}: {}", path.display(), e))?;
+        Ok(Self::from_env())
+    }
+    
+    /// Weight of signal source `name`; 0 for sources that are not enabled
+    pub fn signal_weight(&self, name: &str) -> f64 {
+        self.signal_weights.get(name).copied().unwrap_or(0.0)
+    }
+    
+    /// Execution policy for orders in `symbol`
+    pub fn execution_for(&self, symbol: &str) -> &ExecutionPolicy {
+        self.symbol_execution.get(symbol).unwrap_or(&self.execution)
+    }
+    
     pub fn validate(&self) -> Result<(), String> {
-        if self.alpaca_api_key.is_empty() || self.alpaca_secret_key.is_empty() {
-            return Err("Alpaca API credentials not found! Please set ALPACA_API_KEY and ALPACA_SECRET_KEY environment variables".to_string());
+        match self.broker.as_str() {
+            "alpaca" => {
+                if self.alpaca_api_key.is_empty() || self.alpaca_secret_key.is_empty() {
+                    return Err("Alpaca API credentials not found! Please set ALPACA_API_KEY and ALPACA_SECRET_KEY environment variables".to_string());
+                }
+            }
+            "simulated" => {
+                for symbol in [&self.symbol, &self.inverse_symbol] {
+                    if !self.sim_prices.contains_key(symbol) && !self.sim_bars.contains_key(symbol) {
+                        return Err(format!("No simulated price for {}! Set SIM_PRICES, e.g. SIM_PRICES={}:25.0,{}:40.0, or SIM_BARS", symbol, self.symbol, self.inverse_symbol));
+                    }
+                }
+            }
+            other => {
+                return Err(format!("Unknown BROKER '{}'. Expected 'alpaca' or 'simulated'", other));
+            }
+        }
+        if !matches!(self.missing_data_policy.as_str(), "renormalize" | "refuse") {
+            return Err(format!("Unknown MISSING_DATA_POLICY '{}'. Expected 'renormalize' or 'refuse'", self.missing_data_policy));
+        }
+        if self.signal_sources.is_empty() {
+            return Err("SIGNAL_SOURCES must list at least one source".to_string());
+        }
+        for name in &self.signal_sources {
+            if registry::registration(name).is_none() {
+                let known: Vec<&str> = SOURCES.iter().map(|source| source.name).collect();
+                return Err(format!("Unknown signal source '{}' in SIGNAL_SOURCES. Expected one of: {}", name, known.join(", ")));
+            }
+            match self.signal_weights.get(name) {
+                None => return Err(format!("No weight for signal source '{}'. Set {}_WEIGHT", name, name.to_uppercase())),
+                Some(weight) if !weight.is_finite() || *weight < 0.0 => {
+                    return Err(format!("{}_WEIGHT must be a non-negative number", name.to_uppercase()));
+                }
+                Some(_) => {}
+            }
+        }
+        if self.missing_data_policy == "refuse" {
+            if let Some(name) = self.required_components.iter().find(|r| !self.signal_sources.contains(r)) {
+                return Err(format!("REQUIRED_COMPONENTS lists '{}', which is not in SIGNAL_SOURCES", name));
+            }
+        }
+        if self.momentum_lookback_days == 0 || self.momentum_full_scale_pct <= 0.0 {
+            return Err("MOMENTUM_LOOKBACK_DAYS and MOMENTUM_FULL_SCALE_PCT must be greater than 0".to_string());
+        }
+        if !matches!(self.market_closed_policy.as_str(), "skip" | "defer" | "ignore") {
+            return Err(format!("Unknown MARKET_CLOSED_POLICY '{}'. Expected 'skip', 'defer' or 'ignore'", self.market_closed_policy));
+        }
+        if self.trade_open_offset_minutes < 0 {
+            return Err("TRADE_OPEN_OFFSET_MINUTES must not be negative".to_string());
+        }
+        crate::scheduler::Scheduler::from_specs(&self.schedule, self)
+            .map_err(|e| format!("Invalid SCHEDULE: {}", e))?;
+        if self.order_poll_interval_ms == 0 {
+            return Err("ORDER_POLL_INTERVAL_MS must be greater than 0".to_string());
+        }
+        match self.position_sizing.as_str() {
+            "fixed" | "confidence" | "percent_equity" => {}
+            "vol_target" => {
+                if self.vol_target_pct <= Decimal::ZERO || self.vol_max_scale <= Decimal::ZERO || self.vol_lookback_days < 3 {
+                    return Err("vol_target sizing needs VOL_TARGET_PCT > 0, VOL_MAX_SCALE > 0 and VOL_LOOKBACK_DAYS >= 3".to_string());
+                }
+            }
+            "kelly" => {
+                if !(Decimal::ZERO..=Decimal::ONE).contains(&self.kelly_win_rate) || self.kelly_payoff_ratio <= Decimal::ZERO || !(Decimal::ZERO..=Decimal::ONE).contains(&self.kelly_fraction) {
+                    return Err("kelly sizing needs KELLY_WIN_RATE and KELLY_FRACTION in 0-1 and KELLY_PAYOFF_RATIO > 0".to_string());
+                }
+            }
+            other => {
+                return Err(format!("Unknown POSITION_SIZING '{}'. Expected 'fixed', 'confidence', 'vol_target', 'kelly' or 'percent_equity'", other));
+            }
+        }
+        if !matches!(self.order_qty_mode.as_str(), "whole" | "fractional" | "notional") {
+            return Err(format!("Unknown ORDER_QTY_MODE '{}'. Expected 'whole', 'fractional' or 'notional'", self.order_qty_mode));
+        }
+        if self.rebalance_band_pct < 0.0 {
+            return Err("REBALANCE_BAND_PCT must not be negative".to_string());
+        }
+        for (name, limit) in [
+            ("MAX_ORDER_NOTIONAL", self.max_order_notional),
+            ("MAX_GROSS_EXPOSURE", self.max_gross_exposure),
+            ("MAX_EXPOSURE_PCT_EQUITY", self.max_exposure_pct_equity),
+            ("DAILY_LOSS_LIMIT_PCT", self.daily_loss_limit_pct),
+            ("WEEKLY_LOSS_LIMIT_PCT", self.weekly_loss_limit_pct),
+        ] {
+            if limit < Decimal::ZERO {
+                return Err(format!("{} must not be negative", name));
+            }
+        }
+        if self.max_drawdown_pct < Decimal::ZERO || self.max_drawdown_pct >= Decimal::ONE_HUNDRED {
+            return Err("MAX_DRAWDOWN_PCT must be between 0 and 100".to_string());
+        }
+        if !matches!(self.reconcile_policy.as_str(), "alert" | "adopt" | "flatten") {
+            return Err(format!("Unknown RECONCILE_POLICY '{}'. Expected 'alert', 'adopt' or 'flatten'", self.reconcile_policy));
+        }
+        if self.kill_switch_file.is_empty() {
+            return Err("KILL_SWITCH_FILE must not be empty".to_string());
+        }
+        for (name, pct) in [("STOP_LOSS_PCT", self.stop_loss_pct), ("TRAILING_STOP_PCT", self.trailing_stop_pct)] {
+            if !(0.0..100.0).contains(&pct) {
+                return Err(format!("{} must be between 0 and 100", name));
+            }
+        }
+        if self.take_profit_pct < 0.0 {
+            return Err("TAKE_PROFIT_PCT must not be negative".to_string());
+        }
+        if self.protection_check_interval_secs == 0 {
+            return Err("PROTECTION_CHECK_INTERVAL_SECS must be greater than 0".to_string());
+        }
+        if !matches!(self.flip_failure_policy.as_str(), "retry" | "rollback" | "halt") {
+            return Err(format!("Unknown FLIP_FAILURE_POLICY '{}'. Expected 'retry', 'rollback' or 'halt'", self.flip_failure_policy));
+        }
+        for (symbol, policy) in std::iter::once(("default", &self.execution))
+            .chain(self.symbol_execution.iter().map(|(s, p)| (s.as_str(), p)))
+        {
+            if !matches!(policy.order_type.as_str(), "market" | "limit" | "marketable_limit" | "stop") {
+                return Err(format!("Unknown ORDER_TYPE '{}' for {}. Expected 'market', 'limit', 'marketable_limit' or 'stop'", policy.order_type, symbol));
+            }
+            if policy.max_slippage_bps < Decimal::ZERO {
+                return Err(format!("MAX_SLIPPAGE_BPS for {} must not be negative", symbol));
+            }
+            if policy.order_type != "market" && policy.reprice_after_secs == 0 {
+                return Err(format!("REPRICE_AFTER_SECS for {} must be greater than 0", symbol));
+            }
+        }
+        if self.http_timeout_secs == 0 {
+            return Err("HTTP_TIMEOUT_SECS must be greater than 0".to_string());
         }
         Ok(())
     }
 }
 
+/// Parse "SYMBOL:PRICE" pairs separated by commas, e.g. "BOIL:25.0,KOLD:40.0"
+fn parse_symbol_prices(value: &str) -> HashMap<String, Decimal> {
+    value
+        .split(',')
+        .filter_map(|pair| {
+            let (symbol, price) = pair.split_once(':')?;
+            Some((symbol.trim().to_uppercase(), price.trim().parse().ok()?))
+        })
+        .collect()
+}
+
+/// Parse "SYMBOL:PATH" pairs separated by commas, e.g. "BOIL:bars/BOIL.csv,KOLD:bars/KOLD.csv"
+fn parse_symbol_paths(value: &str) -> HashMap<String, String> {
+    value
+        .split(',')
+        .filter_map(|pair| {
+            let (symbol, path) = pair.split_once(':')?;
+            Some((symbol.trim().to_uppercase(), path.trim().to_string()))
+        })
+        .collect()
+}
+
@@ -1,5 +1,9 @@
 use crate::config::TradingConfig;
-use anyhow::Result;
+use crate::data_sources::error::DataSourceError;
+use crate::signals::source::{SignalSource, SourceMetadata};
+use crate::signals::ComponentSignal;
+use crate::utils::HttpClient;
+use async_trait::async_trait;
 use chrono::{DateTime, Utc, Duration, Datelike};
 use log::{info, warn, error};
 use serde::{Deserialize, Serialize};
@@ -22,22 +26,23 @@ struct StorageDataPoint {
 
 pub struct EIADataFetcher {
     config: TradingConfig,
+    client: HttpClient,
 }
 
 impl EIADataFetcher {
     pub fn new(config: TradingConfig) -> Self {
-        Self { config }
+        let client = HttpClient::new(&config);
+        Self { config, client }
     }
     
-    pub async fn fetch_storage_data(&self) -> Result<Vec<(DateTime<Utc>, f64)>> {
+    pub async fn fetch_storage_data(&self) -> Result<Vec<(DateTime<Utc>, f64)>, DataSourceError> {
         if self.config.eia_api_key.is_empty() {
-            return Err(anyhow::anyhow!("EIA API key not provided"));
+            return Err(DataSourceError::MissingApiKey("EIA"));
         }
         
         let end_date = Utc::now();
         let start_date = end_date - Duration::days(365);
         
-        let client = reqwest::Client::new();
         let url = &self.config.eia_api_url;
         
         let params = [
@@ -55,9 +60,12 @@ impl EIADataFetcher {
         );
         
         info!("  Sending request to EIA API...");
-        match client.get(url).query(&params).send().await {
+        match self.client.send(self.client.get(url).query(&params)).await {
             Ok(response) => {
                 info!("  Response status: {}", response.status());
+                if !response.status().is_success() {
+                    return Err(DataSourceError::from_response(response).await);
+                }
                 match response.json::<EIAResponse>().await {
                     Ok(data) => {
                         info!("  Successfully parsed EIA response");
@@ -85,40 +93,61 @@ impl EIADataFetcher {
                         Ok(storage_data)
                     }
                     Err(e) => {
-                        Err(anyhow::anyhow!("Error parsing EIA API response: {}", e))
+                        Err(DataSourceError::Parse(format!("Error parsing EIA API response: {}", e)))
                     }
                 }
             }
             Err(e) => {
-                Err(anyhow::anyhow!("EIA API failed: {}", e))
+                Err(e.into())
             }
         }
     }
     
-    pub async fn calculate_inventory_signal(&self) -> f64 {
+    /// Compare the latest storage reading against the average of `storage_data`.
+    ///
+    /// Expects the series sorted by date; returns `None` with fewer than two points.
+    pub fn inventory_signal_from_series(storage_data: &[(DateTime<Utc>, f64)]) -> Option<f64> {
+        if storage_data.len() < 2 {
+            return None;
+        }
+        
+        let current_storage = storage_data.last()?.1;
+        let historical_avg: f64 = storage_data.iter().map(|(_, v)| v).sum::<f64>() / storage_data.len() as f64;
+        
+        // Calculate signal: positive if below average (bullish for prices)
+        let inventory_signal = (historical_avg - current_storage) / historical_avg;
+        
+        info!("Current storage: {:.0} Bcf", current_storage);
+        info!("Historical avg: {:.0} Bcf", historical_avg);
+        info!("Inventory signal: {:.3}", inventory_signal);
+        
+        Some(inventory_signal)
+    }
+    
+    pub async fn calculate_inventory_signal(&self) -> ComponentSignal {
         match self.fetch_storage_data().await {
             Ok(storage_data) => {
-                if storage_data.len() < 2 {
+                let Some(inventory_signal) = Self::inventory_signal_from_series(&storage_data) else {
                     warn!("Insufficient storage data");
-                    return 0.0;
-                }
+                    return ComponentSignal::unavailable("insufficient storage data");
+                };
                 
-                let current_storage = storage_data.last().unwrap().1;
-                let historical_avg: f64 = storage_data.iter().map(|(_, v)| v).sum::<f64>() / storage_data.len() as f64;
-                
-                // Calculate signal: positive if below average (bullish for prices)
-                let inventory_signal = (historical_avg - current_storage) / historical_avg;
-                
-                info!("Current storage: {:.0} Bcf", current_storage);
-                info!("Historical avg: {:.0} Bcf", historical_avg);
-                info!("Inventory signal: {:.3}", inventory_signal);
+                // Weekly reports mean data is normally up to ~a week old; anything
+                // much older means the feed has stalled
+                let as_of = storage_data.last().map(|(date, _)| *date);
+                if let Some(date) = as_of {
+                    let age = Utc::now() - date;
+                    if age > Duration::days(self.config.eia_max_age_days) {
+                        warn!("Latest storage report is {} days old, treating as unavailable", age.num_days());
+                        return ComponentSignal::unavailable(format!("storage data is {} days old", age.num_days()));
+                    }
+                }
                 
-                inventory_signal
+                ComponentSignal::available(inventory_signal, as_of)
             }
             Err(e) => {
                 error!("Error calculating inventory signal: {}", e);
-                // Return 0.0 instead of mock data when API fails
-                0.0
+                ComponentSignal::unavailable(e.to_string())
             }
         }
     }
@@ -156,3 +185,25 @@ impl EIADataFetcher {
     }
 }
 
+#[async_trait]
+impl SignalSource for EIADataFetcher {
+    fn name(&self) -> &'static str {
+        "inventory"
+    }
+    
+    fn weight(&self) -> f64 {
+        self.config.signal_weight(self.name())
+    }
+    
+    fn metadata(&self) -> SourceMetadata {
+        SourceMetadata {
+            description: "working gas in storage against its one-year average",
+            provider: "EIA",
+        }
+    }
+    
+    async fn compute(&self) -> ComponentSignal {
+        self.calculate_inventory_signal().await
+    }
+}
+
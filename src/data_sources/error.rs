@@ -0,0 +1,77 @@
+use thiserror::Error;
+
+/// Failures raised by the weather, EIA and NOAA fetchers
+#[derive(Debug, Error)]
+pub enum DataSourceError {
+    #[error("{0} API key not provided")]
+    MissingApiKey(&'static str),
+    
+    #[error("request timed out: {0}")]
+    Timeout(#[source] reqwest::Error),
+    
+    #[error("network error: {0}")]
+    Network(#[source] reqwest::Error),
+    
+    #[error("rate limited (retry after {retry_after:?}s)")]
+    RateLimited { retry_after: Option<u64> },
+    
+    #[error("authentication failed ({status}): {body}")]
+    Auth { status: u16, body: String },
+    
+    #[error("API returned status {status}: {body}")]
+    HttpStatus { status: u16, body: String },
+    
+    #[error("failed to parse response: {0}")]
+    Parse(String),
+    
+    #[error("invalid request: {0}")]
+    InvalidRequest(String),
+}
+
+impl DataSourceError {
+    /// Consume an unsuccessful response and classify it
+    pub async fn from_response(response: reqwest::Response) -> Self {
+        let status = response.status();
+        let retry_after = response
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.trim().parse().ok());
+        let body = response.text().await.unwrap_or_default();
+        Self::from_status(status, retry_after, body)
+    }
+    
+    /// Map a non-success HTTP status and its body to the matching variant
+    pub fn from_status(status: reqwest::StatusCode, retry_after: Option<u64>, body: String) -> Self {
+        let body = truncate(&body);
+        match status.as_u16() {
+            401 | 403 => DataSourceError::Auth { status: status.as_u16(), body },
+            429 => DataSourceError::RateLimited { retry_after },
+            code => DataSourceError::HttpStatus { status: code, body },
+        }
+    }
+}
+
+impl From<reqwest::Error> for DataSourceError {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_timeout() {
+            DataSourceError::Timeout(e)
+        } else if e.is_decode() {
+            DataSourceError::Parse(e.to_string())
+        } else {
+            DataSourceError::Network(e)
+        }
+    }
+}
+
+impl From<serde_json::Error> for DataSourceError {
+    fn from(e: serde_json::Error) -> Self {
+        DataSourceError::Parse(e.to_string())
+    }
+}
+
+/// Keep error bodies short enough to log
+pub(crate) fn truncate(body: &str) -> String {
+    body.chars().take(200).collect()
+}
+
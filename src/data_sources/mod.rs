@@ -1,3 +1,4 @@
+pub mod error;
 pub mod weather_data;
 pub mod eia_data;
 pub mod noaa_data;
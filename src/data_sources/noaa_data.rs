@@ -1,5 +1,10 @@
 use crate::config::TradingConfig;
-use anyhow::Result;
+use crate::data_sources::error::DataSourceError;
+use crate::signals::source::{SignalSource, SourceMetadata};
+use crate::signals::ComponentSignal;
+use crate::utils::HttpClient;
+use async_trait::async_trait;
+use chrono::Utc;
 use log::{info, warn, error};
 use serde::{Deserialize, Serialize};
 
@@ -28,20 +33,19 @@ pub struct Properties {
 
 pub struct NOAADataFetcher {
     config: TradingConfig,
+    client: HttpClient,
 }
 
 impl NOAADataFetcher {
     pub fn new(config: TradingConfig) -> Self {
-        Self { config }
+        let client = HttpClient::with_user_agent(&config, "algotrade/1.0 (contact: your-email@example.com)");
+        Self { config, client }
     }
     
-    pub async fn fetch_weather_alerts(&self) -> Result<Vec<Properties>> {
+    pub async fn fetch_weather_alerts(&self) -> Result<Vec<Properties>, DataSourceError> {
         info!("Fetching weather alerts from NOAA API...");
         info!("  URL: {}", self.config.noaa_api_url);
         
-        let client = reqwest::Client::builder()
-            .user_agent("algotrade/1.0 (contact: your-email@example.com)")
-            .build()?;
         let url = &self.config.noaa_api_url;
         
         let params = [
@@ -52,13 +56,13 @@ impl NOAADataFetcher {
         
         info!("  Parameters: active=true, status=actual, message_type=alert");
         
-        match client.get(url).query(&params).send().await {
+        match self.client.send(self.client.get(url).query(&params)).await {
             Ok(response) => {
                 let status = response.status();
                 info!("  Response status: {}", status);
                 if !status.is_success() {
                     error!("  NOAA API returned error status: {}", status);
-                    return Err(anyhow::anyhow!("NOAA API returned status: {}", status));
+                    return Err(DataSourceError::from_response(response).await);
                 }
                 let text = response.text().await?;
                 info!("  Response received, size: {} bytes", text.len());
@@ -96,23 +100,23 @@ impl NOAADataFetcher {
                     }
                     Err(e) => {
                         error!("  Error parsing NOAA response: {}", e);
-                        Err(anyhow::anyhow!("Error parsing NOAA response: {}", e))
+                        Err(DataSourceError::Parse(format!("Error parsing NOAA response: {}", e)))
                     }
                 }
             }
             Err(e) => {
-                Err(anyhow::anyhow!("Error fetching NOAA alerts: {}", e))
+                Err(e.into())
             }
         }
     }
     
-    pub async fn calculate_storm_signal(&self) -> f64 {
+    pub async fn calculate_storm_signal(&self) -> ComponentSignal {
         info!("Calculating storm signal from NOAA alerts...");
         match self.fetch_weather_alerts().await {
             Ok(alerts) => {
                 if alerts.is_empty() {
                     info!("No relevant weather alerts found - storm signal: 0.0");
-                    return 0.0;
+                    return ComponentSignal::available(0.0, Some(Utc::now()));
                 }
                 
                 info!("Processing {} weather alerts...", alerts.len());
@@ -163,14 +167,35 @@ impl NOAADataFetcher {
                 
                 info!("Total storm signal: {:.3}", storm_signal);
                 
-                storm_signal
+                ComponentSignal::available(storm_signal, Some(Utc::now()))
             }
             Err(e) => {
                 error!("Error calculating storm signal: {}", e);
-                // Return 0.0 instead of mock data when API fails
-                0.0
+                ComponentSignal::unavailable(e.to_string())
             }
         }
     }
 }
 
+#[async_trait]
+impl SignalSource for NOAADataFetcher {
+    fn name(&self) -> &'static str {
+        "storm"
+    }
+    
+    fn weight(&self) -> f64 {
+        self.config.signal_weight(self.name())
+    }
+    
+    fn metadata(&self) -> SourceMetadata {
+        SourceMetadata {
+            description: "active severe weather alerts",
+            provider: "NOAA National Weather Service",
+        }
+    }
+    
+    async fn compute(&self) -> ComponentSignal {
+        self.calculate_storm_signal().await
+    }
+}
+
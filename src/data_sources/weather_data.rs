@@ -1,5 +1,10 @@
 use crate::config::TradingConfig;
-use anyhow::Result;
+use crate::data_sources::error::DataSourceError;
+use crate::signals::source::{SignalSource, SourceMetadata};
+use crate::signals::ComponentSignal;
+use crate::utils::HttpClient;
+use async_trait::async_trait;
+use chrono::Utc;
 use log::{info, warn, error};
 use serde::{Deserialize, Serialize};
 
@@ -18,24 +23,25 @@ struct DailyData {
 
 pub struct WeatherDataFetcher {
     config: TradingConfig,
+    client: HttpClient,
 }
 
 impl WeatherDataFetcher {
     pub fn new(config: TradingConfig) -> Self {
-        Self { config }
+        let client = HttpClient::new(&config);
+        Self { config, client }
     }
     
-    pub async fn fetch_weather_forecast(&self, region: &str, days: i32) -> Result<WeatherResponse> {
+    pub async fn fetch_weather_forecast(&self, region: &str, days: i32) -> Result<WeatherResponse, DataSourceError> {
         info!("  Fetching weather forecast for region: {} ({} days)", region, days);
         let parts: Vec<&str> = region.split(',').collect();
         if parts.len() != 2 {
-            return Err(anyhow::anyhow!("Invalid region format"));
+            return Err(DataSourceError::InvalidRequest(format!("Invalid region format: {}", region)));
         }
         
         let lat = parts[0];
         let lon = parts[1];
         
-        let client = reqwest::Client::new();
         let url = &self.config.weather_api_url;
         
         let params = [
@@ -49,13 +55,14 @@ impl WeatherDataFetcher {
         info!("    URL: {}", url);
         info!("    Coordinates: lat={}, lon={}", lat, lon);
         
-        let response = client
-            .get(url)
-            .query(&params)
-            .send()
+        let response = self.client
+            .send(self.client.get(url).query(&params))
             .await?;
         
         info!("    Response status: {}", response.status());
+        if !response.status().is_success() {
+            return Err(DataSourceError::from_response(response).await);
+        }
         let weather_data: WeatherResponse = response.json().await?;
         info!("    Successfully fetched weather data");
         Ok(weather_data)
@@ -66,7 +73,16 @@ impl WeatherDataFetcher {
         (base_temp - avg_temp).max(0.0)
     }
     
-    pub async fn get_regional_hdd_signal(&self) -> f64 {
+    /// Convert a 7-day regional HDD total into a signal relative to the historical average
+    pub fn hdd_signal(avg_hdd: f64) -> f64 {
+        // Historical average HDD for comparison
+        let historical_avg_hdd = 25.0;
+        
+        // Calculate signal: positive if colder than average
+        (avg_hdd - historical_avg_hdd) / historical_avg_hdd
+    }
+    
+    pub async fn get_regional_hdd_signal(&self) -> ComponentSignal {
         info!("Calculating regional HDD signal from {} regions...", self.config.weather_regions.len());
         let mut total_hdd = 0.0;
         let mut valid_regions = 0;
@@ -97,20 +113,37 @@ impl WeatherDataFetcher {
         
         if valid_regions == 0 {
             warn!("No valid weather data received");
-            return 0.0;
+            return ComponentSignal::unavailable("no valid weather data received");
         }
         
         let avg_hdd = total_hdd / valid_regions as f64;
-        
-        // Historical average HDD for comparison
-        let historical_avg_hdd = 25.0;
-        
-        // Calculate signal: positive if colder than average
-        let hdd_signal = (avg_hdd - historical_avg_hdd) / historical_avg_hdd;
+        let hdd_signal = Self::hdd_signal(avg_hdd);
         
         info!("Average HDD: {:.2}, Signal: {:.3}", avg_hdd, hdd_signal);
         
-        hdd_signal
+        ComponentSignal::available(hdd_signal, Some(Utc::now()))
+    }
+}
+
+#[async_trait]
+impl SignalSource for WeatherDataFetcher {
+    fn name(&self) -> &'static str {
+        "temperature"
+    }
+    
+    fn weight(&self) -> f64 {
+        self.config.signal_weight(self.name())
+    }
+    
+    fn metadata(&self) -> SourceMetadata {
+        SourceMetadata {
+            description: "7-day heating degree days across consuming regions",
+            provider: "Open-Meteo",
+        }
+    }
+    
+    async fn compute(&self) -> ComponentSignal {
+        self.get_regional_hdd_signal().await
     }
 }
 
@@ -1,6 +1,9 @@
+pub mod backtest;
 pub mod config;
 pub mod data_sources;
+pub mod scheduler;
 pub mod signals;
+pub mod storage;
 pub mod trading;
 pub mod utils;
 
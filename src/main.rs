@@ -1,23 +1,39 @@
+mod backtest;
 mod config;
 mod data_sources;
+mod scheduler;
 mod signals;
+mod storage;
 mod trading;
 mod utils;
 
+use backtest::{load_bars_csv, load_inputs_csv, Backtester, Objective, Optimizer, ParameterSpace};
 use clap::{Parser, Subcommand};
 use config::TradingConfig;
-use data_sources::{WeatherDataFetcher, EIADataFetcher, NOAADataFetcher};
-use signals::SignalProcessor;
-use trading::AlpacaTrader;
-use utils::TradingLogger;
-use log::{info, error};
+use scheduler::Scheduler;
+use signals::{ComponentSignal, SignalProcessor, SignalRegistry};
+use storage::StateStore;
+use trading::market_clock::{self, MarketClock};
+use trading::circuit_breaker::CircuitBreaker;
+use trading::protection::PositionMonitor;
+use trading::reconcile::Reconciler;
+use trading::risk::{KillSwitchBroker, RiskEngine};
+use trading::{AlpacaTrader, Broker, SimulatedBroker, TradeExecutor};
+use utils::{Shutdown, TradingLogger};
+use log::{info, error, warn};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::time::Duration;
-use tokio::time::sleep;
 
 #[derive(Parser)]
 #[command(name = "algotrade")]
 #[command(about = "Natural gas trading bot for BOIL/KOLD ETFs")]
 struct Cli {
+    /// Extra env-style config file loaded before config.env (e.g. best_params.env)
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+    
     #[command(subcommand)]
     command: Option<Commands>,
 }
@@ -30,126 +46,366 @@ enum Commands {
     Continuous {
         #[arg(default_value = "24")]
         interval_hours: u64,
+        /// Trade once per session, TRADE_OPEN_OFFSET_MINUTES after the open, instead of every N hours
+        #[arg(long)]
+        at_open: bool,
+    },
+    /// Run a cycle at every time in SCHEDULE (cron expressions in US/Eastern, or `eia`)
+    Schedule {
+        /// Override SCHEDULE; repeat for several jobs, e.g. --job eia --job "0 9 * * MON-FRI"
+        #[arg(long = "job")]
+        jobs: Vec<String>,
+    },
+    /// Replay historical inputs and daily bars through the strategy
+    Backtest {
+        /// CSV with date,hdd,storage_bcf,storm_signal columns
+        #[arg(long)]
+        inputs: PathBuf,
+        /// Daily bars CSV for the bullish symbol (BOIL)
+        #[arg(long)]
+        bars: PathBuf,
+        /// Daily bars CSV for the inverse symbol (KOLD)
+        #[arg(long)]
+        inverse_bars: PathBuf,
+        /// Directory for equity_curve.csv, trades.csv and summary.json
+        #[arg(long, default_value = "backtest_results")]
+        output: PathBuf,
+    },
+    /// Walk-forward search over signal weights and thresholds
+    Optimize {
+        /// CSV with date,hdd,storage_bcf,storm_signal columns
+        #[arg(long)]
+        inputs: PathBuf,
+        /// Daily bars CSV for the bullish symbol (BOIL)
+        #[arg(long)]
+        bars: PathBuf,
+        /// Daily bars CSV for the inverse symbol (KOLD)
+        #[arg(long)]
+        inverse_bars: PathBuf,
+        /// Candidate values as "a,b,c" or "start:end:step" (default: current config)
+        #[arg(long, default_value = "")]
+        temperature_weights: String,
+        #[arg(long, default_value = "")]
+        inventory_weights: String,
+        #[arg(long, default_value = "")]
+        storm_weights: String,
+        #[arg(long, default_value = "")]
+        buy_thresholds: String,
+        #[arg(long, default_value = "", allow_hyphen_values = true)]
+        sell_thresholds: String,
+        /// Draw this many random samples within the value ranges instead of a full grid
+        #[arg(long)]
+        random: Option<usize>,
+        #[arg(long, default_value = "42")]
+        seed: u64,
+        /// Training window length in trading days
+        #[arg(long, default_value = "252")]
+        train_days: usize,
+        /// Test window length (and roll step) in trading days
+        #[arg(long, default_value = "63")]
+        test_days: usize,
+        #[arg(long, value_enum, default_value = "sharpe")]
+        objective: Objective,
+        /// Directory for ranked.csv, ranked.json and best_params.env
+        #[arg(long, default_value = "optimization_results")]
+        output: PathBuf,
+    },
+    /// Block every order, including protective exits and flattening, by creating KILL_SWITCH_FILE
+    Kill {
+        /// Recorded in the kill switch file and in risk.log for every blocked trade
+        #[arg(long, default_value = "manual kill switch")]
+        reason: String,
+    },
+    /// Remove the kill switch so trading can resume
+    Resume,
+    /// Clear a tripped circuit breaker so trading can resume
+    Rearm,
+    /// Show the latest cycles recorded in the state store
+    History {
+        /// Number of cycles to show
+        #[arg(long, default_value = "20")]
+        limit: usize,
     },
 }
 
 struct NatGasTraderBot {
-    _config: TradingConfig,
+    config: TradingConfig,
     logger: TradingLogger,
-    weather_fetcher: WeatherDataFetcher,
-    eia_fetcher: EIADataFetcher,
-    noaa_fetcher: NOAADataFetcher,
+    signal_sources: SignalRegistry,
     signal_processor: SignalProcessor,
-    trader: AlpacaTrader,
+    broker: Arc<dyn Broker>,
+    executor: TradeExecutor,
+    breaker: Arc<CircuitBreaker>,
+    monitor: PositionMonitor,
+    reconciler: Reconciler,
+    store: Option<Arc<StateStore>>,
+    market_clock: MarketClock,
+    shutdown: Shutdown,
 }
 
 impl NatGasTraderBot {
-    async fn new(config: TradingConfig) -> anyhow::Result<Self> {
+    async fn new(config: TradingConfig, shutdown: Shutdown) -> anyhow::Result<Self> {
         let logger = TradingLogger::new(config.clone());
-        let weather_fetcher = WeatherDataFetcher::new(config.clone());
-        let eia_fetcher = EIADataFetcher::new(config.clone());
-        let noaa_fetcher = NOAADataFetcher::new(config.clone());
         let signal_processor = SignalProcessor::new(config.clone());
-        let trader = AlpacaTrader::new(config.clone())?;
+        let broker: Arc<dyn Broker> = match config.broker.as_str() {
+            "simulated" => {
+                info!("Using simulated broker (starting cash: ${:.2})", config.sim_starting_cash);
+                Arc::new(SimulatedBroker::from_config(&config)?)
+            }
+            _ => Arc::new(AlpacaTrader::new(config.clone())?),
+        };
+        // Every order path, not only the risk-checked trades, honours the kill switch
+        let broker: Arc<dyn Broker> = Arc::new(KillSwitchBroker::new(&config, broker));
+        let signal_sources = SignalRegistry::from_config(&config, broker.clone());
+        let risk = RiskEngine::new(config.clone(), broker.clone()).with_state_file(Path::new(&config.risk_state_file));
+        let store = if config.state_db_path.is_empty() {
+            None
+        } else {
+            let store = StateStore::open(Path::new(&config.state_db_path))
+                .map_err(|e| anyhow::anyhow!("Failed to open state store {}: {}", config.state_db_path, e))?
+                .with_ledger(&broker.ledger_id());
+            info!("Recording history in {}", config.state_db_path);
+            Some(Arc::new(store))
+        };
+        let mut breaker = CircuitBreaker::new(config.clone(), broker.clone()).with_state_file(Path::new(&config.breaker_state_file));
+        let mut executor = TradeExecutor::new(config.clone(), broker.clone()).with_risk(risk);
+        let mut reconciler = Reconciler::new(config.clone(), broker.clone());
+        if let Some(store) = &store {
+            breaker = breaker.with_store(store.clone());
+            executor = executor.with_store(store.clone());
+            reconciler = reconciler.with_store(store.clone());
+        }
+        let breaker = Arc::new(breaker);
+        let executor = executor.with_breaker(breaker.clone());
+        let monitor = PositionMonitor::new(config.clone(), broker.clone());
+        let market_clock = MarketClock::new(config.clone());
         
         // Verify connection
-        match trader.get_account_info().await {
+        match broker.get_account_info().await {
             Ok(account) => {
-                info!("Connected to Alpaca. Account status: {}", account.equity);
+                info!("Connected to {}. Account status: {}", broker.name(), account.equity);
                 info!("Buying power: ${:.2}", account.buying_power);
             }
             Err(e) => {
-                error!("Failed to connect to Alpaca API: {}", e);
-                return Err(e);
+                error!("Failed to connect to {} API: {}", broker.name(), e);
+                return Err(e.into());
             }
         }
         
+        info!("Reconciling positions and orders with {}...", broker.name());
+        let can_trade = config.market_closed_policy == "ignore" || market_clock.is_open().await;
+        let startup_id = format!("{}-startup", chrono::Utc::now().format("%Y%m%dT%H%M%SZ"));
+        reconciler.reconcile(&startup_id, can_trade).await;
+        
         info!("NATGAS TRADER Bot initialized");
         
         Ok(Self {
-            _config: config,
+            config,
             logger,
-            weather_fetcher,
-            eia_fetcher,
-            noaa_fetcher,
+            signal_sources,
             signal_processor,
-            trader,
+            broker,
+            executor,
+            breaker,
+            monitor,
+            reconciler,
+            store,
+            market_clock,
+            shutdown,
         })
     }
     
-    async fn fetch_all_signals(&self) -> (f64, f64, f64) {
+    async fn fetch_all_signals(&self) -> BTreeMap<String, ComponentSignal> {
         info!("");
         info!(">>> Starting signal fetch process <<<");
         info!("");
         
-        info!("[1/3] Fetching temperature signal from weather data...");
-        let temp_signal = self.weather_fetcher.get_regional_hdd_signal().await;
-        info!("[1/3] Temperature signal: {:.4}", temp_signal);
-        
-        info!("[2/3] Fetching inventory signal from EIA data...");
-        let inventory_signal = self.eia_fetcher.calculate_inventory_signal().await;
-        info!("[2/3] Inventory signal: {:.4}", inventory_signal);
-        
-        info!("[3/3] Fetching storm signal from NOAA data...");
-        let storm_signal = self.noaa_fetcher.calculate_storm_signal().await;
-        info!("[3/3] Storm signal: {:.4}", storm_signal);
+        let components = self.signal_sources.compute_all().await;
         
         info!("");
         info!(">>> Signal fetch complete <<<");
-        info!("  Temperature: {:.4}", temp_signal);
-        info!("  Inventory: {:.4}", inventory_signal);
-        info!("  Storm: {:.4}", storm_signal);
+        for (name, component) in &components {
+            info!("  {}: {}", name, component.describe());
+        }
         info!("");
         
-        (temp_signal, inventory_signal, storm_signal)
+        components
     }
     
     async fn run_trading_cycle(&self) -> bool {
+        let cycle_start = chrono::Utc::now();
+        let cycle_id = cycle_start.format("%Y%m%dT%H%M%SZ").to_string();
         info!("");
         info!("{}", "=".repeat(60));
-        info!("STARTING TRADING CYCLE");
-        info!("Time: {}", chrono::Utc::now().format("%Y-%m-%d %H:%M:%S UTC"));
+        info!("STARTING TRADING CYCLE {}", cycle_id);
+        info!("Time: {}", cycle_start.format("%Y-%m-%d %H:%M:%S UTC"));
         info!("{}", "=".repeat(60));
+        if let Some(store) = &self.store {
+            store.begin_cycle(&cycle_id, cycle_start);
+        }
         
-        match self.fetch_all_signals().await {
-            (temp_signal, inventory_signal, storm_signal) => {
-                info!("");
-                info!(">>> Processing signals and generating trading signal <<<");
-                let trading_signal = self.signal_processor.create_trading_signal(
-                    temp_signal,
-                    inventory_signal,
-                    storm_signal,
-                );
-                
-                info!("");
-                info!(">>> Trading signal generated <<<");
-                self.logger.log_signal(&trading_signal);
-                
-                info!("");
-                info!(">>> Executing trade based on signal <<<");
-                info!("  Action: {}", trading_signal.action);
-                info!("  Symbol: {}", trading_signal.symbol);
-                info!("  Confidence: {:.2}", trading_signal.confidence);
-                let trade_result = self.trader.execute_trade(&trading_signal).await;
-                self.logger.log_trade(trade_result.as_ref());
-                
-                info!("");
-                info!(">>> Fetching portfolio summary <<<");
-                match self.trader.get_portfolio_summary().await {
-                    Ok(portfolio) => {
-                        self.logger.log_portfolio(&portfolio);
+        info!("");
+        info!(">>> Checking market clock <<<");
+        let clock = match self.market_clock.status().await {
+            Ok(clock) => {
+                market_clock::log_status(&clock);
+                Some(clock)
+            }
+            Err(e) => {
+                error!("  Market clock unavailable ({}), treating the market as closed", e);
+                None
+            }
+        };
+        let mut market_closed = false;
+        if !clock.as_ref().is_some_and(|clock| clock.is_open) {
+            let resume_at = match self.config.market_closed_policy.as_str() {
+                "defer" => self.market_clock
+                    .next_trade_time(chrono::Utc::now(), self.config.trade_open_offset_minutes)
+                    .await
+                    .or(clock.as_ref().map(|clock| clock.next_open)),
+                _ => None,
+            };
+            match (self.config.market_closed_policy.as_str(), resume_at) {
+                ("defer", Some(resume_at)) => {
+                    info!("  Market closed, deferring cycle until {}", resume_at.format("%Y-%m-%d %H:%M UTC"));
+                    if self.sleep_until(resume_at).await {
+                        info!("  Shutdown requested while waiting for the open, ending cycle before trading");
+                        if let Some(store) = &self.store {
+                            store.finish_cycle(&cycle_id, "interrupted");
+                        }
+                        return true;
                     }
-                    Err(e) => {
-                        error!("Error getting portfolio summary: {}", e);
+                }
+                ("ignore", _) => warn!("  Market closed, trading anyway (MARKET_CLOSED_POLICY=ignore)"),
+                _ => {
+                    info!("  Market closed, trade execution will be skipped this cycle");
+                    market_closed = true;
+                }
+            }
+        }
+        
+        // `once` reconciles at startup moments before its only cycle
+        if !self.reconciler.checked_recently(!market_closed) {
+            info!("");
+            info!(">>> Reconciling positions with {} <<<", self.broker.name());
+            self.reconciler.reconcile(&cycle_id, !market_closed).await;
+        }
+        
+        if self.monitor.is_enabled() && !market_closed {
+            info!("");
+            info!(">>> Checking protective exits <<<");
+            self.check_protection().await;
+        }
+        
+        let components = self.fetch_all_signals().await;
+        
+        info!("");
+        info!(">>> Processing signals and generating trading signal <<<");
+        let trading_signal = self.signal_processor.create_trading_signal(components);
+        if let Some(reason) = &trading_signal.blocked_reason {
+            warn!("  Trading blocked by missing data: {}", reason);
+        }
+        
+        info!("");
+        info!(">>> Trading signal generated <<<");
+        self.logger.log_signal(&trading_signal);
+        if let Some(store) = &self.store {
+            store.record_signal(&cycle_id, &trading_signal);
+        }
+        
+        info!("");
+        info!(">>> Executing trade based on signal <<<");
+        info!("  Action: {}", trading_signal.action);
+        info!("  Symbol: {}", trading_signal.symbol);
+        info!("  Confidence: {:.2}", trading_signal.confidence);
+        // Last safe point before orders go out; once execution starts every leg runs to completion
+        let (trade_result, outcome) = if let Some(signal) = self.shutdown.requested() {
+            warn!("  Skipping execution: shutdown requested ({})", signal);
+            (None, "interrupted")
+        } else if market_closed {
+            info!("  Skipping execution: market closed");
+            (None, "market_closed")
+        } else {
+            let result = self.executor.execute_trade(&trading_signal, &cycle_id).await;
+            let outcome = if result.is_some() { "traded" } else { "no_trade" };
+            (result, outcome)
+        };
+        self.logger.log_trade(trade_result.as_ref());
+        
+        info!("");
+        info!(">>> Fetching portfolio summary <<<");
+        match self.broker.get_portfolio_summary().await {
+            Ok(portfolio) => {
+                self.logger.log_portfolio(&portfolio);
+                if let Some(store) = &self.store {
+                    store.record_portfolio(&cycle_id, &portfolio);
+                }
+            }
+            Err(e) => {
+                error!("Error getting portfolio summary: {}", e);
+            }
+        }
+        
+        info!("");
+        info!("{}", "=".repeat(60));
+        info!("TRADING CYCLE COMPLETED SUCCESSFULLY");
+        info!("{}", "=".repeat(60));
+        info!("");
+        if let Some(store) = &self.store {
+            store.finish_cycle(&cycle_id, outcome);
+        }
+        true
+    }
+    
+    /// Run one cycle per trading session at the configured offset from the open
+    async fn run_at_open(&self) {
+        info!("Starting session trading {} minutes after each open", self.config.trade_open_offset_minutes);
+        
+        while self.shutdown.requested().is_none() {
+            match self.market_clock.next_trade_time(chrono::Utc::now(), self.config.trade_open_offset_minutes).await {
+                Some(next_run) => {
+                    info!("Next cycle at {}", next_run.format("%Y-%m-%d %H:%M UTC"));
+                    if self.sleep_until(next_run).await {
+                        break;
                     }
+                    self.run_trading_cycle().await;
+                }
+                None => {
+                    warn!("No upcoming trading session found, checking again in 1 hour");
+                    self.shutdown.sleep(Duration::from_secs(3600)).await;
+                }
+            }
+        }
+    }
+    
+    /// Run one cycle whenever a scheduled job fires
+    async fn run_scheduled(&self, scheduler: &Scheduler) {
+        info!("Scheduled jobs (US/Eastern):");
+        for job in scheduler.jobs() {
+            info!("  {}", job.name);
+        }
+        
+        while self.shutdown.requested().is_none() {
+            let now = chrono::Utc::now();
+            let Some((next_run, jobs)) = scheduler.next_run(now) else {
+                error!("Schedule never fires again, stopping");
+                return;
+            };
+            info!(
+                "Next cycle at {} ({})",
+                next_run.with_timezone(&trading::market_calendar::MARKET_TZ).format("%Y-%m-%d %H:%M %Z"),
+                jobs.join(", ")
+            );
+            if self.sleep_until(next_run).await {
+                break;
+            }
+            self.run_trading_cycle().await;
+            
+            // A long cycle may run past other firing times; those are skipped rather than run late
+            if let Some((missed, _)) = scheduler.next_run(next_run) {
+                if missed < chrono::Utc::now() {
+                    warn!("Cycle overran scheduled time {}, skipping it", missed.format("%Y-%m-%d %H:%M UTC"));
                 }
-                
-                info!("");
-                info!("{}", "=".repeat(60));
-                info!("TRADING CYCLE COMPLETED SUCCESSFULLY");
-                info!("{}", "=".repeat(60));
-                info!("");
-                true
             }
         }
     }
@@ -157,79 +413,297 @@ impl NatGasTraderBot {
     async fn run_continuous(&self, interval_hours: u64) {
         info!("Starting continuous trading with {}h intervals", interval_hours);
         
-        loop {
-            match self.run_trading_cycle().await {
+        while self.shutdown.requested().is_none() {
+            let interrupted = match self.run_trading_cycle().await {
                 true => {
                     let sleep_seconds = interval_hours * 3600;
                     info!("Waiting {} hours until next cycle", interval_hours);
-                    sleep(Duration::from_secs(sleep_seconds)).await;
+                    self.wait(Duration::from_secs(sleep_seconds)).await
                 }
                 false => {
                     info!("Trading cycle failed, waiting 5 minutes before retry");
-                    sleep(Duration::from_secs(300)).await;
+                    self.wait(Duration::from_secs(300)).await
+                }
+            };
+            if interrupted {
+                break;
+            }
+        }
+    }
+    
+    /// Sleep until `time`; returns true if shutdown was requested first
+    async fn sleep_until(&self, time: chrono::DateTime<chrono::Utc>) -> bool {
+        let wait = (time - chrono::Utc::now()).to_std().unwrap_or_default();
+        self.wait(wait).await
+    }
+    
+    /// Sleep for `duration`, checking protective exits and the drawdown circuit
+    /// breaker every PROTECTION_CHECK_INTERVAL_SECS; returns true if shutdown
+    /// was requested first
+    async fn wait(&self, duration: Duration) -> bool {
+        if !self.monitor.is_enabled() && self.config.max_drawdown_pct.is_zero() {
+            return self.shutdown.sleep(duration).await;
+        }
+        let deadline = tokio::time::Instant::now() + duration;
+        let interval = Duration::from_secs(self.config.protection_check_interval_secs);
+        loop {
+            let remaining = deadline.saturating_duration_since(tokio::time::Instant::now());
+            if remaining.is_zero() {
+                return false;
+            }
+            if self.shutdown.sleep(remaining.min(interval)).await {
+                return true;
+            }
+            // The cycle that follows the wait checks positions itself
+            if tokio::time::Instant::now() < deadline {
+                self.check_protection().await;
+            }
+        }
+    }
+    
+    /// Trip the circuit breaker on a deep drawdown, then close positions whose
+    /// stop-loss, trailing-stop or take-profit triggered. Outside market hours
+    /// orders would only queue, so checks wait for the open unless
+    /// MARKET_CLOSED_POLICY=ignore.
+    async fn check_protection(&self) {
+        if self.config.market_closed_policy != "ignore" && !self.market_clock.is_open().await {
+            return;
+        }
+        let check_id = chrono::Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+        if self.breaker.check(&check_id).await.is_some() {
+            return;
+        }
+        for exit in self.monitor.check(&check_id).await {
+            self.logger.log_exit(&exit);
+            if let Some(store) = &self.store {
+                store.record_order(&check_id, &exit.trade);
+            }
+        }
+    }
+    
+    /// Final cleanup once the run loop has stopped at a safe point
+    async fn shutdown(&self) -> anyhow::Result<()> {
+        info!("");
+        info!(">>> SHUTTING DOWN <<<");
+        let reason = self.shutdown.requested().unwrap_or("run completed");
+        info!("  Reason: {}", reason);
+        
+        let mut uncancelled = 0;
+        if self.config.cancel_orders_on_shutdown {
+            info!("  Cancelling open orders...");
+            match self.broker.get_open_orders().await {
+                Ok(orders) => {
+                    info!("  Found {} open order(s)", orders.len());
+                    for order in orders {
+                        match self.broker.cancel_order(&order.order_id).await {
+                            Ok(()) => info!("  Cancelled {} {} {} (ID: {})", order.side, order.qty, order.symbol, order.order_id),
+                            Err(e) => {
+                                error!("  Failed to cancel order {}: {}", order.order_id, e);
+                                uncancelled += 1;
+                            }
+                        }
+                    }
+                }
+                Err(e) => {
+                    error!("  Could not list open orders: {}", e);
+                    uncancelled += 1;
                 }
             }
         }
+        
+        info!("  Flushing logs...");
+        log::logger().flush();
+        
+        if uncancelled > 0 {
+            anyhow::bail!("shutdown ({}) left {} open order(s) uncancelled", reason, uncancelled);
+        }
+        info!(">>> SHUTDOWN COMPLETE ({}) <<<", reason);
+        Ok(())
     }
 }
 
-#[tokio::main]
-async fn main() -> anyhow::Result<()> {
-    // Initialize logger with more verbose output
-    env_logger::Builder::from_default_env()
-        .filter_level(log::LevelFilter::Info)
-        .format_timestamp_secs()
-        .init();
+async fn run_backtest(
+    config: TradingConfig,
+    inputs: &Path,
+    bars: &Path,
+    inverse_bars: &Path,
+    output: &Path,
+) -> anyhow::Result<()> {
+    info!("Running BACKTEST mode");
+    info!("  Inputs: {}", inputs.display());
+    info!("  {} bars: {}", config.symbol, bars.display());
+    info!("  {} bars: {}", config.inverse_symbol, inverse_bars.display());
     
-    info!("{}", "=".repeat(60));
-    info!("NATGAS TRADER BOT - Starting up");
-    info!("{}", "=".repeat(60));
+    let inputs = load_inputs_csv(inputs)?;
+    let bars = load_bars_csv(bars)?;
+    let inverse_bars = load_bars_csv(inverse_bars)?;
     
-    // Load configuration
-    info!("Loading configuration from environment...");
-    let config = TradingConfig::from_env();
-    info!("Configuration loaded successfully");
-    info!("  Symbol: {}", config.symbol);
-    info!("  Inverse Symbol: {}", config.inverse_symbol);
-    info!("  Buy Threshold: {}", config.buy_threshold);
-    info!("  Sell Threshold: {}", config.sell_threshold);
+    let report = Backtester::new(config).run(&inputs, &bars, &inverse_bars).await?;
+    report.log_summary();
+    report.write_to_dir(output)?;
+    
+    info!("Program completed");
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn run_optimize(
+    config: TradingConfig,
+    inputs: &Path,
+    bars: &Path,
+    inverse_bars: &Path,
+    space: ParameterSpace,
+    random: Option<usize>,
+    seed: u64,
+    train_days: usize,
+    test_days: usize,
+    objective: Objective,
+    output: &Path,
+) -> anyhow::Result<()> {
+    info!("Running OPTIMIZE mode");
+    
+    let inputs = load_inputs_csv(inputs)?;
+    let bars = load_bars_csv(bars)?;
+    let inverse_bars = load_bars_csv(inverse_bars)?;
+    
+    let candidates = match random {
+        Some(samples) => {
+            info!("  Random search: {} samples (seed {})", samples, seed);
+            space.random(samples, seed)
+        }
+        None => {
+            let grid = space.grid();
+            info!("  Grid search: {} combinations", grid.len());
+            grid
+        }
+    };
+    let folds = Optimizer::build_folds(&bars, train_days, test_days);
+    info!("  Walk-forward: {} folds of {} train / {} test days", folds.len(), train_days, test_days);
     
+    let report = Optimizer::new(config, objective)
+        .run(&inputs, &bars, &inverse_bars, &candidates, &folds)
+        .await?;
+    report.log_summary();
+    report.write_to_dir(output)?;
+    
+    info!("Program completed");
+    Ok(())
+}
+
+/// Create or remove the kill switch file checked before every order
+fn set_kill_switch(config: &TradingConfig, reason: Option<&str>) -> anyhow::Result<()> {
+    let path = Path::new(&config.kill_switch_file);
+    match reason {
+        Some(reason) => {
+            std::fs::write(path, format!("{} ({})\n", reason, chrono::Utc::now().to_rfc3339()))?;
+            warn!("Kill switch engaged: {} created, no orders will be placed", path.display());
+            println!("Kill switch engaged ({}). Run `resume` to trade again.", path.display());
+        }
+        None if path.exists() => {
+            std::fs::remove_file(path)?;
+            info!("Kill switch released: {} removed", path.display());
+            println!("Kill switch released, trading can resume.");
+        }
+        None => println!("Kill switch is not engaged ({} does not exist).", path.display()),
+    }
+    Ok(())
+}
+
+/// Clear a tripped circuit breaker; the high-water mark restarts at the next check
+fn rearm_circuit_breaker(config: &TradingConfig) -> anyhow::Result<()> {
+    match CircuitBreaker::rearm(Path::new(&config.breaker_state_file))? {
+        Some(previous) if previous.tripped => {
+            info!("Circuit breaker re-armed (was tripped: {})", previous.reason.as_deref().unwrap_or("unknown"));
+            println!("Circuit breaker re-armed. It had tripped at {}: {}",
+                     previous.tripped_at.map(|t| t.to_rfc3339()).unwrap_or_default(),
+                     previous.reason.unwrap_or_default());
+        }
+        Some(_) => println!("Circuit breaker was not tripped; high-water mark reset."),
+        None => println!("No circuit breaker state at {}, nothing to re-arm.", config.breaker_state_file),
+    }
+    Ok(())
+}
+
+/// Print the latest cycles from the state store
+fn show_history(config: &TradingConfig, limit: usize) -> anyhow::Result<()> {
+    let path = Path::new(&config.state_db_path);
+    if config.state_db_path.is_empty() || !path.exists() {
+        println!("No state store at '{}' (STATE_DB_PATH).", config.state_db_path);
+        return Ok(());
+    }
+    let store = StateStore::open(path)?;
+    let cycles = store.recent_cycles(limit)?;
+    println!("{:<18} {:<14} {:<5} {:<5} {:>8} {:>6} {:>12}", "cycle", "outcome", "act", "sym", "signal", "orders", "value");
+    for cycle in cycles {
+        println!(
+            "{:<18} {:<14} {:<5} {:<5} {:>8} {:>6} {:>12}",
+            cycle.cycle_id,
+            cycle.outcome.as_deref().unwrap_or("unfinished"),
+            cycle.action.as_deref().unwrap_or("-"),
+            cycle.symbol.as_deref().unwrap_or("-"),
+            cycle.total_signal.map(|s| format!("{:.4}", s)).unwrap_or_else(|| "-".to_string()),
+            cycle.orders,
+            cycle.total_value.as_deref().unwrap_or("-"),
+        );
+    }
+    Ok(())
+}
+
+async fn run_bot(config: TradingConfig, command: Option<Commands>) -> anyhow::Result<()> {
     // Validate configuration
     info!("Validating configuration...");
     if let Err(e) = config.validate() {
         error!("Configuration validation failed: {}", e);
         eprintln!("ERROR: {}", e);
-        eprintln!("Please set ALPACA_API_KEY and ALPACA_SECRET_KEY environment variables");
-        eprintln!("Or check your .env file");
+        eprintln!("Please check your environment variables or .env file");
         std::process::exit(1);
     }
     info!("Configuration validated successfully");
     
     // Create logs directory
     info!("Creating logs directory...");
-    std::fs::create_dir_all("logs")?;
+    std::fs::create_dir_all(&config.log_dir)?;
     info!("Logs directory ready");
     
     // Create and run bot
     info!("Initializing trading bot...");
-    let bot = NatGasTraderBot::new(config).await?;
+    let shutdown = Shutdown::install();
+    let bot = NatGasTraderBot::new(config, shutdown).await?;
     info!("Trading bot initialized successfully");
     
-    let cli = Cli::parse();
-    
-    match cli.command {
+    match command {
         Some(Commands::Once) => {
             info!("Running in ONCE mode - single trading cycle");
             bot.run_trading_cycle().await;
             info!("Program completed");
         }
-        Some(Commands::Continuous { interval_hours }) => {
+        Some(Commands::Continuous { at_open: true, .. }) => {
+            info!("Starting continuous trading mode (once per session at open + {} min)", bot.config.trade_open_offset_minutes);
+            info!("Press Ctrl+C to stop the bot");
+            println!("Starting continuous trading mode (once per session at open + {} min)", bot.config.trade_open_offset_minutes);
+            println!("Press Ctrl+C to stop the bot");
+            bot.run_at_open().await;
+        }
+        Some(Commands::Continuous { interval_hours, .. }) => {
             info!("Starting continuous trading mode (every {} hours)", interval_hours);
             info!("Press Ctrl+C to stop the bot");
             println!("Starting continuous trading mode (every {} hours)", interval_hours);
             println!("Press Ctrl+C to stop the bot");
             bot.run_continuous(interval_hours).await;
         }
+        Some(Commands::Schedule { jobs }) => {
+            let specs = if jobs.is_empty() { bot.config.schedule.clone() } else { jobs };
+            let scheduler = Scheduler::from_specs(&specs, &bot.config).map_err(anyhow::Error::msg)?;
+            info!("Starting scheduled trading mode");
+            info!("Press Ctrl+C to stop the bot");
+            println!("Starting scheduled trading mode ({})", specs.join("; "));
+            println!("Press Ctrl+C to stop the bot");
+            bot.run_scheduled(&scheduler).await;
+        }
+        Some(Commands::Backtest { .. }) | Some(Commands::Optimize { .. }) | Some(Commands::Kill { .. }) | Some(Commands::Resume)
+        | Some(Commands::Rearm) | Some(Commands::History { .. }) => {
+            unreachable!("handled without starting the bot")
+        }
         None => {
             // Default: run continuously (once per day)
             info!("Starting continuous trading mode (once per day)");
@@ -240,6 +714,87 @@ async fn main() -> anyhow::Result<()> {
         }
     }
     
-    Ok(())
+    bot.shutdown().await
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    // Initialize logger with more verbose output
+    env_logger::Builder::from_default_env()
+        .filter_level(log::LevelFilter::Info)
+        .format_timestamp_secs()
+        .init();
+    
+    let cli = Cli::parse();
+    
+    info!("{}", "=".repeat(60));
+    info!("NATGAS TRADER BOT - Starting up");
+    info!("{}", "=".repeat(60));
+    
+    // Load configuration
+    let config = match &cli.config {
+        Some(path) => {
+            info!("Loading configuration from {} and environment...", path.display());
+            match TradingConfig::from_file(path) {
+                Ok(config) => config,
+                Err(e) => {
+                    error!("{}", e);
+                    eprintln!("ERROR: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        None => {
+            info!("Loading configuration from environment...");
+            TradingConfig::from_env()
+        }
+    };
+    info!("Configuration loaded successfully");
+    info!("  Broker: {}", config.broker);
+    info!("  Symbol: {}", config.symbol);
+    info!("  Inverse Symbol: {}", config.inverse_symbol);
+    info!("  Buy Threshold: {}", config.buy_threshold);
+    info!("  Sell Threshold: {}", config.sell_threshold);
+    
+    match cli.command {
+        // Backtests replay history offline and need no broker credentials
+        Some(Commands::Backtest { inputs, bars, inverse_bars, output }) => {
+            run_backtest(config, &inputs, &bars, &inverse_bars, &output).await
+        }
+        Some(Commands::Optimize {
+            inputs,
+            bars,
+            inverse_bars,
+            temperature_weights,
+            inventory_weights,
+            storm_weights,
+            buy_thresholds,
+            sell_thresholds,
+            random,
+            seed,
+            train_days,
+            test_days,
+            objective,
+            output,
+        }) => {
+            let space = ParameterSpace {
+                temperature_weights: ParameterSpace::parse_values(&temperature_weights, config.signal_weight("temperature"))?,
+                inventory_weights: ParameterSpace::parse_values(&inventory_weights, config.signal_weight("inventory"))?,
+                storm_weights: ParameterSpace::parse_values(&storm_weights, config.signal_weight("storm"))?,
+                buy_thresholds: ParameterSpace::parse_values(&buy_thresholds, config.buy_threshold)?,
+                sell_thresholds: ParameterSpace::parse_values(&sell_thresholds, config.sell_threshold)?,
+            };
+            run_optimize(
+                config, &inputs, &bars, &inverse_bars, space, random, seed,
+                train_days, test_days, objective, &output,
+            ).await
+        }
+        // The kill switch is a plain file, so a running bot picks it up before its next trade
+        Some(Commands::Kill { reason }) => set_kill_switch(&config, Some(&reason)),
+        Some(Commands::Resume) => set_kill_switch(&config, None),
+        Some(Commands::Rearm) => rearm_circuit_breaker(&config),
+        Some(Commands::History { limit }) => show_history(&config, limit),
+        command => run_bot(config, command).await,
+    }
 }
 
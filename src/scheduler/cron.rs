@@ -0,0 +1,218 @@
+use crate::trading::market_calendar::MARKET_TZ;
+use chrono::{DateTime, Datelike, Duration, LocalResult, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Timelike, Utc};
+
+/// Five-field cron expression (`minute hour day-of-month month day-of-week`)
+/// evaluated in US/Eastern time.
+///
+/// Fields accept `*`, numbers, names (`JAN`, `MON`), ranges (`1-5`), lists
+/// (`1,15`) and steps (`*/15`, `9-17/2`). As in Vixie cron, when both
+/// day-of-month and day-of-week are restricted a day matching either fires.
+///
+/// DST: a local time skipped by the spring-forward gap fires at the first
+/// instant after the gap; a time repeated by the fall-back fires once, on its
+/// first occurrence.
+#[derive(Debug, Clone)]
+pub struct CronExpr {
+    source: String,
+    minutes: Vec<bool>,
+    hours: Vec<bool>,
+    days_of_month: Vec<bool>,
+    months: Vec<bool>,
+    days_of_week: Vec<bool>,
+    dom_restricted: bool,
+    dow_restricted: bool,
+}
+
+const MONTH_NAMES: [&str; 12] = ["JAN", "FEB", "MAR", "APR", "MAY", "JUN", "JUL", "AUG", "SEP", "OCT", "NOV", "DEC"];
+const DAY_NAMES: [&str; 7] = ["SUN", "MON", "TUE", "WED", "THU", "FRI", "SAT"];
+
+impl CronExpr {
+    pub fn parse(expr: &str) -> Result<Self, String> {
+        let fields: Vec<&str> = expr.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err(format!("cron expression '{}' must have 5 fields, found {}", expr, fields.len()));
+        }
+        
+        let mut days_of_week = parse_field(fields[4], 0, 7, &DAY_NAMES, 0)
+            .map_err(|e| format!("day-of-week in '{}': {}", expr, e))?;
+        // 7 is an alias for Sunday
+        if days_of_week[7] {
+            days_of_week[0] = true;
+        }
+        days_of_week.truncate(7);
+        
+        Ok(Self {
+            source: expr.to_string(),
+            minutes: parse_field(fields[0], 0, 59, &[], 0).map_err(|e| format!("minute in '{}': {}", expr, e))?,
+            hours: parse_field(fields[1], 0, 23, &[], 0).map_err(|e| format!("hour in '{}': {}", expr, e))?,
+            days_of_month: parse_field(fields[2], 1, 31, &[], 0).map_err(|e| format!("day-of-month in '{}': {}", expr, e))?,
+            months: parse_field(fields[3], 1, 12, &MONTH_NAMES, 1).map_err(|e| format!("month in '{}': {}", expr, e))?,
+            days_of_week,
+            dom_restricted: !fields[2].starts_with('*'),
+            dow_restricted: !fields[4].starts_with('*'),
+        })
+    }
+    
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+    
+    fn matches_date(&self, date: NaiveDate) -> bool {
+        if !self.months[date.month() as usize] {
+            return false;
+        }
+        let dom = self.days_of_month[date.day() as usize];
+        let dow = self.days_of_week[date.weekday().num_days_from_sunday() as usize];
+        match (self.dom_restricted, self.dow_restricted) {
+            (true, true) => dom || dow,
+            (true, false) => dom,
+            (false, true) => dow,
+            (false, false) => true,
+        }
+    }
+    
+    /// First firing time strictly after `after`
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let start = after.with_timezone(&MARKET_TZ).date_naive();
+        // Any satisfiable expression fires within a leap cycle (e.g. "0 0 29 2 *")
+        for date in start.iter_days().take(366 * 4 + 1) {
+            if !self.matches_date(date) {
+                continue;
+            }
+            for hour in (0..24).filter(|h| self.hours[*h]) {
+                for minute in (0..60).filter(|m| self.minutes[*m]) {
+                    let time = NaiveTime::from_hms_opt(hour as u32, minute as u32, 0)?;
+                    let fire = local_to_utc(date.and_time(time))?;
+                    if fire > after {
+                        return Some(fire);
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Resolve an Eastern wall clock time, moving times inside the DST gap forward
+pub(crate) fn local_to_utc(local: NaiveDateTime) -> Option<DateTime<Utc>> {
+    match MARKET_TZ.from_local_datetime(&local) {
+        LocalResult::Single(t) => Some(t.with_timezone(&Utc)),
+        LocalResult::Ambiguous(first, _) => Some(first.with_timezone(&Utc)),
+        LocalResult::None => {
+            // The gap is one hour long, so the top of the following hour is the first valid instant
+            let shifted = local + Duration::hours(1);
+            let after_gap = shifted.date().and_hms_opt(shifted.hour(), 0, 0)?;
+            MARKET_TZ.from_local_datetime(&after_gap).earliest().map(|t| t.with_timezone(&Utc))
+        }
+    }
+}
+
+/// Parse one cron field into a membership table indexed by value.
+/// `name_base` is the value of the first entry in `names`.
+fn parse_field(field: &str, min: usize, max: usize, names: &[&str], name_base: usize) -> Result<Vec<bool>, String> {
+    let mut set = vec![false; max + 1];
+    let value = |s: &str| -> Result<usize, String> {
+        let upper = s.to_uppercase();
+        if let Some(pos) = names.iter().position(|n| *n == upper) {
+            return Ok(pos + name_base);
+        }
+        let v: usize = s.parse().map_err(|_| format!("invalid value '{}'", s))?;
+        if v < min || v > max {
+            return Err(format!("{} out of range {}-{}", v, min, max));
+        }
+        Ok(v)
+    };
+    
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step: usize = step.parse().map_err(|_| format!("invalid step '{}'", step))?;
+                if step == 0 {
+                    return Err("step must be positive".to_string());
+                }
+                (range, step)
+            }
+            None => (part, 1),
+        };
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((a, b)) = range.split_once('-') {
+            (value(a)?, value(b)?)
+        } else {
+            let start = value(range)?;
+            // "5/15" means every 15 starting at 5
+            (start, if step > 1 { max } else { start })
+        };
+        if start > end {
+            return Err(format!("range {}-{} is reversed", start, end));
+        }
+        for v in (start..=end).step_by(step) {
+            set[v] = true;
+        }
+    }
+    Ok(set)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn utc(text: &str) -> DateTime<Utc> {
+        text.parse().unwrap()
+    }
+    
+    fn next(expr: &str, after: &str) -> DateTime<Utc> {
+        CronExpr::parse(expr).unwrap().next_after(utc(after)).unwrap()
+    }
+    
+    #[test]
+    fn rejects_malformed_expressions() {
+        assert!(CronExpr::parse("0 9 * *").is_err());
+        assert!(CronExpr::parse("60 9 * * *").is_err());
+        assert!(CronExpr::parse("0 9 * * FRI-MON").is_err());
+        assert!(CronExpr::parse("*/0 9 * * *").is_err());
+        assert!(CronExpr::parse("0 9 * FOO *").is_err());
+    }
+    
+    #[test]
+    fn weekday_names_skip_the_weekend() {
+        // Friday 10:00 EDT -> Monday 09:00 EDT
+        assert_eq!(next("0 9 * * MON-FRI", "2024-10-25T14:00:00Z"), utc("2024-10-28T13:00:00Z"));
+        // Sunday may be written as 7
+        assert_eq!(next("0 0 * * 7", "2024-10-24T14:00:00Z"), utc("2024-10-27T04:00:00Z"));
+    }
+    
+    #[test]
+    fn steps_and_lists() {
+        assert_eq!(next("*/15 * * * *", "2024-10-24T14:07:00Z"), utc("2024-10-24T14:15:00Z"));
+        assert_eq!(next("5/20 * * * *", "2024-10-24T14:26:00Z"), utc("2024-10-24T14:45:00Z"));
+        assert_eq!(next("0 9,15 * * *", "2024-10-24T14:00:00Z"), utc("2024-10-24T19:00:00Z"));
+    }
+    
+    #[test]
+    fn restricted_day_of_month_and_week_match_either() {
+        // Tuesday 1 October -> Monday 7 October
+        assert_eq!(next("0 12 1 * MON", "2024-10-01T17:00:00Z"), utc("2024-10-07T16:00:00Z"));
+        // Monday 28 October -> Friday 1 November
+        assert_eq!(next("0 12 1 * MON", "2024-10-28T17:00:00Z"), utc("2024-11-01T16:00:00Z"));
+    }
+    
+    #[test]
+    fn leap_day_only_expression() {
+        assert_eq!(next("0 0 29 2 *", "2024-03-01T00:00:00Z"), utc("2028-02-29T05:00:00Z"));
+    }
+    
+    #[test]
+    fn dst_gap_fires_after_the_gap() {
+        // 02:30 does not exist on 10 March 2024; the first instant after is 03:00 EDT
+        assert_eq!(next("30 2 * * *", "2024-03-10T05:00:00Z"), utc("2024-03-10T07:00:00Z"));
+    }
+    
+    #[test]
+    fn repeated_hour_fires_once() {
+        // 01:30 happens twice on 3 November 2024; only the EDT one fires
+        assert_eq!(next("30 1 * * *", "2024-11-03T04:00:00Z"), utc("2024-11-03T05:30:00Z"));
+        assert_eq!(next("30 1 * * *", "2024-11-03T05:30:00Z"), utc("2024-11-04T06:30:00Z"));
+    }
+}
+
@@ -0,0 +1,121 @@
+use crate::scheduler::cron::local_to_utc;
+use crate::trading::market_calendar::{is_federal_holiday, MARKET_TZ};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+
+/// Release times of the EIA Weekly Natural Gas Storage Report.
+///
+/// The report normally comes out Thursday at 10:30 ET. Following EIA's holiday
+/// practice, a federal holiday on the Thursday moves it to Wednesday 12:00 ET
+/// and a holiday earlier in the week delays it to Thursday 12:00 ET. Weeks
+/// EIA schedules differently can be pinned with explicit overrides.
+#[derive(Debug, Clone)]
+pub struct EiaReleaseRule {
+    /// Minutes to wait after the release before running, so the API has the new data
+    delay: Duration,
+    overrides: Vec<NaiveDateTime>,
+}
+
+impl EiaReleaseRule {
+    pub fn new(delay_minutes: i64, overrides: Vec<NaiveDateTime>) -> Self {
+        Self {
+            delay: Duration::minutes(delay_minutes),
+            overrides,
+        }
+    }
+    
+    /// Parse "YYYY-MM-DD HH:MM" Eastern Time overrides separated by commas
+    pub fn parse_overrides(value: &str) -> Result<Vec<NaiveDateTime>, String> {
+        value
+            .split(',')
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty())
+            .map(|s| {
+                NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M")
+                    .map_err(|e| format!("invalid EIA release override '{}': {}", s, e))
+            })
+            .collect()
+    }
+    
+    /// Release time (Eastern wall clock) for the week starting on `monday`
+    pub fn release_in_week(&self, monday: NaiveDate) -> NaiveDateTime {
+        let sunday = monday + Duration::days(6);
+        if let Some(pinned) = self.overrides.iter().find(|o| o.date() >= monday && o.date() <= sunday) {
+            return *pinned;
+        }
+        
+        let wednesday = monday + Duration::days(2);
+        let thursday = monday + Duration::days(3);
+        let noon = NaiveTime::from_hms_opt(12, 0, 0).expect("valid time");
+        let regular = NaiveTime::from_hms_opt(10, 30, 0).expect("valid time");
+        
+        if is_federal_holiday(thursday) {
+            wednesday.and_time(noon)
+        } else if monday.iter_days().take(3).any(is_federal_holiday) {
+            thursday.and_time(noon)
+        } else {
+            thursday.and_time(regular)
+        }
+    }
+    
+    /// First run time (release plus delay) strictly after `after`
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let today = after.with_timezone(&MARKET_TZ).date_naive();
+        let monday = today - Duration::days(today.weekday().num_days_from_monday() as i64);
+        (0..54)
+            .map(|week| monday + Duration::weeks(week))
+            .filter_map(|week| local_to_utc(self.release_in_week(week)))
+            .map(|release| release + self.delay)
+            .find(|run| *run > after)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn local(text: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M").unwrap()
+    }
+    
+    fn monday(text: &str) -> NaiveDate {
+        text.parse().unwrap()
+    }
+    
+    #[test]
+    fn regular_week_releases_thursday_morning() {
+        let rule = EiaReleaseRule::new(0, Vec::new());
+        assert_eq!(rule.release_in_week(monday("2024-10-21")), local("2024-10-24 10:30"));
+    }
+    
+    #[test]
+    fn holidays_move_the_release() {
+        let rule = EiaReleaseRule::new(0, Vec::new());
+        // Thanksgiving and Independence Day on the Thursday: Wednesday noon
+        assert_eq!(rule.release_in_week(monday("2024-11-25")), local("2024-11-27 12:00"));
+        assert_eq!(rule.release_in_week(monday("2024-07-01")), local("2024-07-03 12:00"));
+        // Labor Day, Columbus Day and Christmas earlier in the week: Thursday noon
+        assert_eq!(rule.release_in_week(monday("2024-09-02")), local("2024-09-05 12:00"));
+        assert_eq!(rule.release_in_week(monday("2024-10-14")), local("2024-10-17 12:00"));
+        assert_eq!(rule.release_in_week(monday("2024-12-23")), local("2024-12-26 12:00"));
+    }
+    
+    #[test]
+    fn overrides_pin_their_week() {
+        let overrides = EiaReleaseRule::parse_overrides(" 2024-10-23 09:00 , ").unwrap();
+        let rule = EiaReleaseRule::new(0, overrides);
+        assert_eq!(rule.release_in_week(monday("2024-10-21")), local("2024-10-23 09:00"));
+        assert_eq!(rule.release_in_week(monday("2024-10-28")), local("2024-10-31 10:30"));
+        assert!(EiaReleaseRule::parse_overrides("2024-10-23").is_err());
+    }
+    
+    #[test]
+    fn next_run_adds_the_delay() {
+        let rule = EiaReleaseRule::new(5, Vec::new());
+        let utc = |text: &str| text.parse::<DateTime<Utc>>().unwrap();
+        assert_eq!(rule.next_after(utc("2024-10-24T14:00:00Z")), Some(utc("2024-10-24T14:35:00Z")));
+        assert_eq!(rule.next_after(utc("2024-10-24T14:35:00Z")), Some(utc("2024-10-31T14:35:00Z")));
+        // After the switch to EST the same local time is an hour later in UTC
+        assert_eq!(rule.next_after(utc("2024-11-01T00:00:00Z")), Some(utc("2024-11-07T15:35:00Z")));
+    }
+}
+
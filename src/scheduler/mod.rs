@@ -0,0 +1,108 @@
+pub mod cron;
+pub mod eia;
+
+use crate::config::TradingConfig;
+use chrono::{DateTime, Utc};
+use cron::CronExpr;
+use eia::EiaReleaseRule;
+
+/// When a scheduled job fires
+#[derive(Debug, Clone)]
+pub enum ScheduleRule {
+    Cron(CronExpr),
+    EiaRelease(EiaReleaseRule),
+}
+
+#[derive(Debug, Clone)]
+pub struct Job {
+    pub name: String,
+    pub rule: ScheduleRule,
+}
+
+impl Job {
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match &self.rule {
+            ScheduleRule::Cron(expr) => expr.next_after(after),
+            ScheduleRule::EiaRelease(rule) => rule.next_after(after),
+        }
+    }
+}
+
+/// Set of jobs that each trigger a trading cycle.
+///
+/// Specs are either a five-field cron expression in US/Eastern time or `eia`
+/// for the weekly storage report release. Jobs firing at the same minute
+/// trigger a single cycle.
+#[derive(Debug, Clone)]
+pub struct Scheduler {
+    jobs: Vec<Job>,
+}
+
+impl Scheduler {
+    pub fn from_specs(specs: &[String], config: &TradingConfig) -> Result<Self, String> {
+        let mut jobs = Vec::new();
+        for spec in specs.iter().map(|s| s.trim()).filter(|s| !s.is_empty()) {
+            let job = if spec.eq_ignore_ascii_case("eia") {
+                let overrides = EiaReleaseRule::parse_overrides(&config.eia_release_overrides)?;
+                Job {
+                    name: format!("EIA storage report (+{} min)", config.eia_release_delay_minutes),
+                    rule: ScheduleRule::EiaRelease(EiaReleaseRule::new(config.eia_release_delay_minutes, overrides)),
+                }
+            } else {
+                let expr = CronExpr::parse(spec)?;
+                Job {
+                    name: format!("cron '{}'", expr.as_str()),
+                    rule: ScheduleRule::Cron(expr),
+                }
+            };
+            jobs.push(job);
+        }
+        if jobs.is_empty() {
+            return Err("schedule has no jobs".to_string());
+        }
+        Ok(Self { jobs })
+    }
+    
+    pub fn jobs(&self) -> &[Job] {
+        &self.jobs
+    }
+    
+    /// Earliest firing after `after`, with the names of every job due at that time
+    pub fn next_run(&self, after: DateTime<Utc>) -> Option<(DateTime<Utc>, Vec<&str>)> {
+        let upcoming: Vec<(DateTime<Utc>, &str)> = self
+            .jobs
+            .iter()
+            .filter_map(|job| job.next_after(after).map(|t| (t, job.name.as_str())))
+            .collect();
+        let next = upcoming.iter().map(|(t, _)| *t).min()?;
+        let names = upcoming.iter().filter(|(t, _)| *t == next).map(|(_, n)| *n).collect();
+        Some((next, names))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn jobs_due_together_run_once() {
+        let config = TradingConfig {
+            eia_release_delay_minutes: 5,
+            eia_release_overrides: String::new(),
+            ..TradingConfig::default()
+        };
+        let specs = ["eia".to_string(), "35 10 * * THU".to_string(), "0 9 * * MON-FRI".to_string()];
+        let scheduler = Scheduler::from_specs(&specs, &config).unwrap();
+        assert_eq!(scheduler.jobs().len(), 3);
+        
+        let (at, names) = scheduler.next_run("2024-10-24T13:30:00Z".parse().unwrap()).unwrap();
+        assert_eq!(at, "2024-10-24T14:35:00Z".parse::<DateTime<Utc>>().unwrap());
+        assert_eq!(names.len(), 2);
+    }
+    
+    #[test]
+    fn empty_schedule_is_rejected() {
+        assert!(Scheduler::from_specs(&[" ".to_string()], &TradingConfig::default()).is_err());
+    }
+}
+
@@ -0,0 +1,60 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// One signal component as reported by a data source.
+///
+/// `available == false` means the source could not produce a reading (API
+/// outage, missing key, stale data), which is different from a reading of 0.0.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComponentSignal {
+    pub value: f64,
+    pub available: bool,
+    /// Timestamp of the newest data point behind the value
+    pub as_of: Option<DateTime<Utc>>,
+    /// Why the component is unavailable
+    pub reason: Option<String>,
+    /// Weight the component carries in the total signal, stamped from
+    /// `SignalSource::weight` by `SignalRegistry`
+    #[serde(default)]
+    pub weight: f64,
+}
+
+impl ComponentSignal {
+    pub fn available(value: f64, as_of: Option<DateTime<Utc>>) -> Self {
+        Self {
+            value,
+            available: true,
+            as_of,
+            reason: None,
+            weight: 0.0,
+        }
+    }
+    
+    pub fn unavailable(reason: impl Into<String>) -> Self {
+        Self {
+            value: 0.0,
+            available: false,
+            as_of: None,
+            reason: Some(reason.into()),
+            weight: 0.0,
+        }
+    }
+    
+    pub fn with_weight(mut self, weight: f64) -> Self {
+        self.weight = weight;
+        self
+    }
+    
+    /// One-line summary for logs
+    pub fn describe(&self) -> String {
+        if self.available {
+            match self.as_of {
+                Some(as_of) => format!("{:.4} (as of {})", self.value, as_of.format("%Y-%m-%d %H:%M UTC")),
+                None => format!("{:.4}", self.value),
+            }
+        } else {
+            format!("UNAVAILABLE ({})", self.reason.as_deref().unwrap_or("unknown"))
+        }
+    }
+}
+
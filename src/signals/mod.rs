@@ -1,4 +1,11 @@
+pub mod component;
+pub mod momentum;
+pub mod registry;
 pub mod signal_processor;
+pub mod source;
 
+pub use component::ComponentSignal;
+pub use registry::SignalRegistry;
 pub use signal_processor::{SignalProcessor, TradingSignal};
+pub use source::SignalSource;
 
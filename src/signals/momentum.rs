@@ -0,0 +1,80 @@
+use crate::config::TradingConfig;
+use crate::signals::source::{SignalSource, SourceMetadata};
+use crate::signals::ComponentSignal;
+use crate::trading::Broker;
+use async_trait::async_trait;
+use log::info;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use std::sync::Arc;
+
+/// Trend of `SYMBOL`'s own price.
+///
+/// The return over `MOMENTUM_LOOKBACK_DAYS` daily closes is scaled so a move
+/// of `MOMENTUM_FULL_SCALE_PCT` reads as +/-1. `SYMBOL` tracks natural gas,
+/// so a rising price gives a positive (bullish) component like a cold
+/// forecast does.
+pub struct MomentumSource {
+    config: TradingConfig,
+    broker: Arc<dyn Broker>,
+}
+
+impl MomentumSource {
+    pub fn new(config: TradingConfig, broker: Arc<dyn Broker>) -> Self {
+        Self { config, broker }
+    }
+    
+    /// Percent change from the first to the last of `closes`, oldest first
+    fn change_pct(closes: &[Decimal]) -> Option<f64> {
+        let (first, last) = (*closes.first()?, *closes.last()?);
+        if closes.len() < 2 || first <= Decimal::ZERO {
+            return None;
+        }
+        ((last / first - Decimal::ONE) * Decimal::ONE_HUNDRED).to_f64()
+    }
+    
+    /// Signal for `closes`, oldest first
+    pub fn momentum_signal(closes: &[Decimal], full_scale_pct: f64) -> Option<f64> {
+        let change_pct = Self::change_pct(closes)?;
+        Some((change_pct / full_scale_pct).clamp(-1.0, 1.0))
+    }
+}
+
+#[async_trait]
+impl SignalSource for MomentumSource {
+    fn name(&self) -> &'static str {
+        "momentum"
+    }
+    
+    fn weight(&self) -> f64 {
+        self.config.signal_weight(self.name())
+    }
+    
+    fn metadata(&self) -> SourceMetadata {
+        SourceMetadata {
+            description: "price trend of the bullish symbol",
+            provider: "broker daily bars",
+        }
+    }
+    
+    async fn compute(&self) -> ComponentSignal {
+        let days = self.config.momentum_lookback_days;
+        let closes = match self.broker.get_daily_closes(&self.config.symbol, days + 1).await {
+            Ok(closes) => closes,
+            Err(e) => return ComponentSignal::unavailable(format!("no {} price history: {}", self.config.symbol, e)),
+        };
+        if closes.len() < days + 1 {
+            return ComponentSignal::unavailable(format!(
+                "only {} of {} {} closes available", closes.len(), days + 1, self.config.symbol
+            ));
+        }
+        match Self::momentum_signal(&closes, self.config.momentum_full_scale_pct) {
+            Some(value) => {
+                info!("  {} moved {:.2}% over {} days", self.config.symbol, Self::change_pct(&closes).unwrap_or_default(), days);
+                ComponentSignal::available(value, None)
+            }
+            None => ComponentSignal::unavailable(format!("invalid {} closes", self.config.symbol)),
+        }
+    }
+}
+
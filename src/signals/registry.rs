@@ -0,0 +1,159 @@
+use crate::config::TradingConfig;
+use crate::data_sources::{EIADataFetcher, NOAADataFetcher, WeatherDataFetcher};
+use crate::signals::momentum::MomentumSource;
+use crate::signals::{ComponentSignal, SignalSource};
+use crate::trading::Broker;
+use log::info;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// One source `SIGNAL_SOURCES` may list
+pub struct SourceRegistration {
+    pub name: &'static str,
+    /// Weight used when `<NAME>_WEIGHT` is not set; `None` makes it required
+    pub default_weight: Option<f64>,
+    build: fn(&TradingConfig, &Arc<dyn Broker>) -> Box<dyn SignalSource>,
+}
+
+/// Every known signal source. A new driver implements `SignalSource` and
+/// adds a row here; from then on it is switched on and weighted purely by
+/// configuration.
+pub const SOURCES: &[SourceRegistration] = &[
+    SourceRegistration {
+        name: "temperature",
+        default_weight: Some(0.5),
+        build: |config, _| Box::new(WeatherDataFetcher::new(config.clone())),
+    },
+    SourceRegistration {
+        name: "inventory",
+        default_weight: Some(0.4),
+        build: |config, _| Box::new(EIADataFetcher::new(config.clone())),
+    },
+    SourceRegistration {
+        name: "storm",
+        default_weight: Some(0.1),
+        build: |config, _| Box::new(NOAADataFetcher::new(config.clone())),
+    },
+    SourceRegistration {
+        name: "momentum",
+        default_weight: None,
+        build: |config, broker| Box::new(MomentumSource::new(config.clone(), broker.clone())),
+    },
+];
+
+/// The registration for `name`, if there is one
+pub fn registration(name: &str) -> Option<&'static SourceRegistration> {
+    SOURCES.iter().find(|source| source.name == name)
+}
+
+/// The signal sources enabled by `SIGNAL_SOURCES`, in the order listed.
+pub struct SignalRegistry {
+    sources: Vec<Box<dyn SignalSource>>,
+}
+
+impl SignalRegistry {
+    pub fn from_config(config: &TradingConfig, broker: Arc<dyn Broker>) -> Self {
+        let sources = config
+            .signal_sources
+            .iter()
+            // Unknown names are rejected by `TradingConfig::validate`
+            .filter_map(|name| registration(name))
+            .map(|source| (source.build)(config, &broker))
+            .collect();
+        Self { sources }
+    }
+    
+    pub fn sources(&self) -> &[Box<dyn SignalSource>] {
+        &self.sources
+    }
+    
+    /// Read every source in turn
+    pub async fn compute_all(&self) -> BTreeMap<String, ComponentSignal> {
+        let mut components = BTreeMap::new();
+        let count = self.sources.len();
+        for (idx, source) in self.sources.iter().enumerate() {
+            let metadata = source.metadata();
+            info!("[{}/{}] Fetching {} signal: {} from {} (weight {})...",
+                  idx + 1, count, source.name(), metadata.description, metadata.provider, source.weight());
+            let component = source.compute().await.with_weight(source.weight());
+            info!("[{}/{}] {} signal: {}", idx + 1, count, source.name(), component.describe());
+            components.insert(source.name().to_string(), component);
+        }
+        components
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trading::SimulatedBroker;
+    use rust_decimal::Decimal;
+    
+    fn config(sources: &[&str], weights: &[(&str, f64)]) -> TradingConfig {
+        TradingConfig {
+            broker: "simulated".to_string(),
+            symbol: "BOIL".to_string(),
+            inverse_symbol: "KOLD".to_string(),
+            sim_prices: [("BOIL".to_string(), Decimal::from(20)), ("KOLD".to_string(), Decimal::from(50))].into(),
+            signal_sources: sources.iter().map(|s| s.to_string()).collect(),
+            signal_weights: weights.iter().map(|(name, weight)| (name.to_string(), *weight)).collect(),
+            ..TradingConfig::default()
+        }
+    }
+    
+    fn names(config: &TradingConfig) -> Vec<&'static str> {
+        let broker: Arc<dyn Broker> = Arc::new(SimulatedBroker::new(Decimal::ZERO, Decimal::ZERO, Decimal::ZERO));
+        SignalRegistry::from_config(config, broker).sources().iter().map(|s| s.name()).collect()
+    }
+    
+    #[test]
+    fn only_listed_sources_are_enabled_in_order() {
+        let config = config(&["momentum", "temperature"], &[("momentum", 0.3), ("temperature", 0.7)]);
+        assert_eq!(names(&config), ["momentum", "temperature"]);
+        
+        let config = self::config(&["storm"], &[("storm", 1.0)]);
+        assert_eq!(names(&config), ["storm"]);
+    }
+    
+    #[test]
+    fn sources_take_their_configured_weight() {
+        let config = config(&["temperature", "momentum"], &[("temperature", 0.25), ("momentum", 0.75)]);
+        let broker: Arc<dyn Broker> = Arc::new(SimulatedBroker::new(Decimal::ZERO, Decimal::ZERO, Decimal::ZERO));
+        let registry = SignalRegistry::from_config(&config, broker);
+        let weights: Vec<f64> = registry.sources().iter().map(|s| s.weight()).collect();
+        assert_eq!(weights, [0.25, 0.75]);
+    }
+    
+    #[tokio::test]
+    async fn momentum_reads_the_simulated_bars() {
+        let path = std::env::temp_dir().join(format!("algotrade-sim-bars-{}.csv", std::process::id()));
+        let rows: Vec<String> = (1..=25).map(|day| format!("2024-01-{:02},{}", day, 20 + day)).collect();
+        std::fs::write(&path, format!("date,close\n{}\n", rows.join("\n"))).unwrap();
+        let config = TradingConfig {
+            sim_bars: [("BOIL".to_string(), path.to_string_lossy().into_owned())].into(),
+            momentum_lookback_days: 20,
+            momentum_full_scale_pct: 20.0,
+            ..config(&["momentum"], &[("momentum", 1.0)])
+        };
+        assert!(config.validate().is_ok());
+        
+        let broker: Arc<dyn Broker> = Arc::new(SimulatedBroker::from_config(&config).unwrap());
+        std::fs::remove_file(&path).unwrap();
+        let components = SignalRegistry::from_config(&config, broker).compute_all().await;
+        let momentum = &components["momentum"];
+        assert!(momentum.available, "{}", momentum.describe());
+        // The bars win over SIM_PRICES: 25 -> 45 over the lookback, a full-scale rise
+        assert_eq!(momentum.value, 1.0);
+    }
+    
+    #[test]
+    fn unknown_or_unweighted_sources_are_rejected() {
+        let config = config(&["temperature", "sunspots"], &[("temperature", 1.0), ("sunspots", 1.0)]);
+        assert!(config.validate().unwrap_err().contains("Unknown signal source 'sunspots'"));
+        assert_eq!(names(&config), ["temperature"]);
+        
+        let config = self::config(&["momentum"], &[]);
+        assert!(config.validate().unwrap_err().contains("MOMENTUM_WEIGHT"));
+    }
+}
@@ -1,18 +1,21 @@
 use crate::config::TradingConfig;
+use crate::signals::component::ComponentSignal;
 use chrono::{DateTime, Utc};
-use log::info;
+use log::{info, warn};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TradingSignal {
     pub timestamp: DateTime<Utc>,
-    pub temperature_signal: f64,
-    pub inventory_signal: f64,
-    pub storm_signal: f64,
+    /// Reading of each enabled `SignalSource`, by name
+    pub components: BTreeMap<String, ComponentSignal>,
     pub total_signal: f64,
     pub action: String, // "BUY", "SELL", "HOLD"
     pub symbol: String, // "BOIL" or "KOLD"
     pub confidence: f64,
+    /// Set when missing data forced a HOLD regardless of the signal
+    pub blocked_reason: Option<String>,
 }
 
 pub struct SignalProcessor {
@@ -24,32 +27,70 @@ impl SignalProcessor {
         Self { config }
     }
     
-    pub fn calculate_total_signal(
-        &self,
-        temp_signal: f64,
-        inventory_signal: f64,
-        storm_signal: f64,
-    ) -> f64 {
-        let total_signal = temp_signal * self.config.temperature_weight
-            + inventory_signal * self.config.inventory_weight
-            + storm_signal * self.config.storm_weight;
+    /// Weighted sum of the available components, each weighted by the
+    /// `SignalSource::weight` its registry stamped on it.
+    ///
+    /// Weights of unavailable components are redistributed pro rata over the
+    /// available ones so the total keeps the same scale. Returns `None` when
+    /// no component is available.
+    pub fn calculate_total_signal(&self, components: &BTreeMap<String, ComponentSignal>) -> Option<f64> {
+        let components: Vec<(&str, &ComponentSignal, f64)> = components
+            .iter()
+            .map(|(name, component)| (name.as_str(), component, component.weight))
+            .collect();
+        
+        let total_weight: f64 = components.iter().map(|(_, _, w)| w).sum();
+        let available_weight: f64 = components
+            .iter()
+            .filter(|(_, c, _)| c.available)
+            .map(|(_, _, w)| w)
+            .sum();
         
         info!("Signal components:");
-        info!(
-            "  Temperature: {:.3} (weight: {})",
-            temp_signal, self.config.temperature_weight
-        );
-        info!(
-            "  Inventory: {:.3} (weight: {})",
-            inventory_signal, self.config.inventory_weight
-        );
-        info!(
-            "  Storm: {:.3} (weight: {})",
-            storm_signal, self.config.storm_weight
-        );
+        for (name, component, weight) in &components {
+            if component.available {
+                info!("  {}: {:.3} (weight: {})", name, component.value, weight);
+            } else {
+                warn!(
+                    "  {}: unavailable ({}) (weight: {})",
+                    name,
+                    component.reason.as_deref().unwrap_or("unknown"),
+                    weight
+                );
+            }
+        }
+        
+        if available_weight <= 0.0 {
+            warn!("  No signal components available");
+            return None;
+        }
+        
+        let scale = total_weight / available_weight;
+        if (scale - 1.0).abs() > f64::EPSILON {
+            info!("  Renormalizing weights over available components (x{:.3})", scale);
+        }
+        
+        let total_signal: f64 = components
+            .iter()
+            .filter(|(_, c, _)| c.available)
+            .map(|(_, c, w)| c.value * w * scale)
+            .sum();
         info!("  Total signal: {:.3}", total_signal);
         
-        total_signal
+        Some(total_signal)
+    }
+    
+    /// Names of required components that are unavailable under the `refuse` policy
+    fn missing_required<'a>(&self, components: &'a BTreeMap<String, ComponentSignal>) -> Vec<&'a str> {
+        if self.config.missing_data_policy != "refuse" {
+            return Vec::new();
+        }
+        
+        components
+            .iter()
+            .filter(|(name, c)| !c.available && self.config.required_components.contains(name))
+            .map(|(name, _)| name.as_str())
+            .collect()
     }
     
     pub fn determine_action(&self, total_signal: f64) -> (String, String, f64) {
@@ -79,25 +120,120 @@ impl SignalProcessor {
         }
     }
     
-    pub fn create_trading_signal(
-        &self,
-        temp_signal: f64,
-        inventory_signal: f64,
-        storm_signal: f64,
-    ) -> TradingSignal {
-        let total_signal = self.calculate_total_signal(temp_signal, inventory_signal, storm_signal);
-        let (action, symbol, confidence) = self.determine_action(total_signal);
+    pub fn create_trading_signal(&self, components: BTreeMap<String, ComponentSignal>) -> TradingSignal {
+        let total_signal = self.calculate_total_signal(&components);
+        let missing = self.missing_required(&components);
+        
+        let blocked_reason = if !missing.is_empty() {
+            Some(format!("required components unavailable: {}", missing.join(", ")))
+        } else if total_signal.is_none() {
+            Some("no signal components available".to_string())
+        } else {
+            None
+        };
+        
+        let (action, symbol, confidence) = match &blocked_reason {
+            Some(reason) => {
+                warn!("  Decision: HOLD ({})", reason);
+                ("HOLD".to_string(), String::new(), 0.0)
+            }
+            None => self.determine_action(total_signal.unwrap_or(0.0)),
+        };
         
         TradingSignal {
             timestamp: Utc::now(),
-            temperature_signal: temp_signal,
-            inventory_signal,
-            storm_signal,
-            total_signal,
+            components,
+            total_signal: total_signal.unwrap_or(0.0),
             action,
             symbol,
             confidence,
+            blocked_reason,
         }
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn processor(policy: &str) -> SignalProcessor {
+        SignalProcessor::new(TradingConfig {
+            symbol: "BOIL".to_string(),
+            inverse_symbol: "KOLD".to_string(),
+            buy_threshold: 0.3,
+            sell_threshold: -0.3,
+            missing_data_policy: policy.to_string(),
+            required_components: vec!["inventory".to_string()],
+            ..TradingConfig::default()
+        })
+    }
+    
+    fn components(readings: &[(&str, f64, Option<f64>)]) -> BTreeMap<String, ComponentSignal> {
+        readings
+            .iter()
+            .map(|(name, weight, value)| {
+                let component = match value {
+                    Some(value) => ComponentSignal::available(*value, None),
+                    None => ComponentSignal::unavailable("stale"),
+                };
+                (name.to_string(), component.with_weight(*weight))
+            })
+            .collect()
+    }
+    
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-9, "{} != {}", actual, expected);
+    }
+    
+    #[test]
+    fn total_is_the_weighted_sum_when_everything_is_available() {
+        let all = components(&[("temperature", 0.5, Some(0.6)), ("inventory", 0.4, Some(-0.5)), ("storm", 0.1, Some(1.0))]);
+        assert_close(processor("renormalize").calculate_total_signal(&all).unwrap(), 0.3 - 0.2 + 0.1);
+    }
+    
+    #[test]
+    fn unavailable_weight_is_spread_over_the_available_components() {
+        let partial = components(&[("temperature", 0.5, Some(0.6)), ("inventory", 0.4, None), ("storm", 0.1, Some(0.2))]);
+        // (0.6 * 0.5 + 0.2 * 0.1) scaled by 1.0 / 0.6
+        assert_close(processor("renormalize").calculate_total_signal(&partial).unwrap(), 0.32 / 0.6);
+    }
+    
+    #[test]
+    fn no_total_without_any_available_component() {
+        let none = components(&[("temperature", 0.5, None), ("inventory", 0.4, None)]);
+        assert_eq!(processor("renormalize").calculate_total_signal(&none), None);
+        assert_eq!(processor("renormalize").calculate_total_signal(&BTreeMap::new()), None);
+        
+        for policy in ["renormalize", "refuse"] {
+            let signal = processor(policy).create_trading_signal(components(&[("temperature", 0.5, None)]));
+            assert_eq!(signal.action, "HOLD");
+            assert_eq!(signal.blocked_reason.as_deref(), Some("no signal components available"));
+        }
+    }
+    
+    #[test]
+    fn renormalize_trades_on_the_remaining_components() {
+        let signal = processor("renormalize")
+            .create_trading_signal(components(&[("temperature", 0.6, Some(0.8)), ("inventory", 0.4, None)]));
+        assert_eq!(signal.blocked_reason, None);
+        assert_eq!((signal.action.as_str(), signal.symbol.as_str()), ("BUY", "BOIL"));
+        assert_close(signal.total_signal, 0.8);
+    }
+    
+    #[test]
+    fn refuse_holds_when_a_required_component_is_missing() {
+        let signal = processor("refuse")
+            .create_trading_signal(components(&[("temperature", 0.5, Some(0.8)), ("inventory", 0.4, None)]));
+        assert_eq!(signal.action, "HOLD");
+        assert_eq!(signal.symbol, "");
+        assert_eq!(signal.confidence, 0.0);
+        assert_eq!(signal.blocked_reason.as_deref(), Some("required components unavailable: inventory"));
+        
+        // Optional components may still be missing
+        let signal = processor("refuse")
+            .create_trading_signal(components(&[("inventory", 0.4, Some(-0.9)), ("storm", 0.1, None)]));
+        assert_eq!(signal.blocked_reason, None);
+        assert_eq!((signal.action.as_str(), signal.symbol.as_str()), ("BUY", "KOLD"));
+    }
+}
@@ -0,0 +1,31 @@
+use crate::signals::ComponentSignal;
+use async_trait::async_trait;
+use serde::Serialize;
+
+/// Descriptive details of a signal source
+#[derive(Debug, Clone, Serialize)]
+pub struct SourceMetadata {
+    /// What the component measures, e.g. "7-day heating degree days"
+    pub description: &'static str,
+    /// Where its data comes from, e.g. "Open-Meteo"
+    pub provider: &'static str,
+}
+
+/// One driver of the trading signal.
+///
+/// `SignalRegistry` builds the sources listed in `SIGNAL_SOURCES`; each
+/// reading is weighted by `weight` in the total signal. A source that cannot
+/// produce a reading returns `ComponentSignal::unavailable` rather than 0.0.
+#[async_trait]
+pub trait SignalSource: Send + Sync {
+    /// Key in `SIGNAL_SOURCES`, `REQUIRED_COMPONENTS` and `TradingSignal::components`
+    fn name(&self) -> &'static str;
+    
+    /// Weight of the reading in the total signal, normally `<NAME>_WEIGHT`
+    fn weight(&self) -> f64;
+    
+    fn metadata(&self) -> SourceMetadata;
+    
+    async fn compute(&self) -> ComponentSignal;
+}
+
@@ -0,0 +1,4 @@
+pub mod state_store;
+
+pub use state_store::StateStore;
+
@@ -0,0 +1,546 @@
+use crate::signals::{ComponentSignal, TradingSignal};
+use crate::trading::broker::TradeResult;
+use chrono::{DateTime, Utc};
+use log::{info, error};
+use rusqlite::{params, Connection, OptionalExtension};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Mutex;
+
+/// Schema changes, applied in order. `PRAGMA user_version` holds how many
+/// have run; append new steps, never edit shipped ones.
+const MIGRATIONS: &[&str] = &[
+    // 1: initial schema. Money and quantities are stored as decimal text.
+    "CREATE TABLE cycles (
+        cycle_id TEXT PRIMARY KEY,
+        started_at TEXT NOT NULL,
+        finished_at TEXT,
+        outcome TEXT
+    );
+    CREATE TABLE component_signals (
+        cycle_id TEXT NOT NULL,
+        name TEXT NOT NULL,
+        value REAL NOT NULL,
+        available INTEGER NOT NULL,
+        as_of TEXT,
+        reason TEXT,
+        PRIMARY KEY (cycle_id, name)
+    );
+    CREATE TABLE trading_signals (
+        cycle_id TEXT PRIMARY KEY,
+        timestamp TEXT NOT NULL,
+        total_signal REAL NOT NULL,
+        action TEXT NOT NULL,
+        symbol TEXT NOT NULL,
+        confidence REAL NOT NULL,
+        blocked_reason TEXT
+    );
+    CREATE TABLE orders (
+        order_id TEXT PRIMARY KEY,
+        client_order_id TEXT NOT NULL,
+        cycle_id TEXT NOT NULL,
+        symbol TEXT NOT NULL,
+        side TEXT NOT NULL,
+        order_type TEXT NOT NULL,
+        qty TEXT NOT NULL,
+        notional TEXT,
+        status TEXT NOT NULL,
+        filled_qty TEXT,
+        filled_avg_price TEXT,
+        submitted_at TEXT NOT NULL,
+        updated_at TEXT NOT NULL
+    );
+    CREATE INDEX orders_cycle ON orders (cycle_id);
+    CREATE TABLE fills (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        order_id TEXT NOT NULL,
+        symbol TEXT NOT NULL,
+        side TEXT NOT NULL,
+        qty TEXT NOT NULL,
+        price TEXT NOT NULL,
+        recorded_at TEXT NOT NULL
+    );
+    CREATE INDEX fills_order ON fills (order_id);
+    CREATE TABLE portfolio_snapshots (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        cycle_id TEXT NOT NULL,
+        taken_at TEXT NOT NULL,
+        total_value TEXT,
+        cash TEXT,
+        buying_power TEXT,
+        positions TEXT NOT NULL
+    );",
+    // 2: corrections that line expected positions up with the broker
+    "CREATE TABLE position_adjustments (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        symbol TEXT NOT NULL,
+        qty TEXT NOT NULL,
+        reason TEXT NOT NULL,
+        recorded_at TEXT NOT NULL
+    );",
+    // 3: the broker account (`Broker::ledger_id`) each order, fill and
+    // adjustment belongs to. Older rows keep an empty ledger.
+    "ALTER TABLE orders ADD COLUMN ledger TEXT NOT NULL DEFAULT '';
+    ALTER TABLE fills ADD COLUMN ledger TEXT NOT NULL DEFAULT '';
+    ALTER TABLE position_adjustments ADD COLUMN ledger TEXT NOT NULL DEFAULT '';",
+    // 4: order IDs are only unique within a ledger (the simulated broker
+    // numbers its orders from sim-1 in every run), so key orders on both
+    "CREATE TABLE orders_by_ledger (
+        ledger TEXT NOT NULL DEFAULT '',
+        order_id TEXT NOT NULL,
+        client_order_id TEXT NOT NULL,
+        cycle_id TEXT NOT NULL,
+        symbol TEXT NOT NULL,
+        side TEXT NOT NULL,
+        order_type TEXT NOT NULL,
+        qty TEXT NOT NULL,
+        notional TEXT,
+        status TEXT NOT NULL,
+        filled_qty TEXT,
+        filled_avg_price TEXT,
+        submitted_at TEXT NOT NULL,
+        updated_at TEXT NOT NULL,
+        PRIMARY KEY (ledger, order_id)
+    );
+    INSERT INTO orders_by_ledger
+        (ledger, order_id, client_order_id, cycle_id, symbol, side, order_type, qty, notional,
+         status, filled_qty, filled_avg_price, submitted_at, updated_at)
+        SELECT ledger, order_id, client_order_id, cycle_id, symbol, side, order_type, qty, notional,
+               status, filled_qty, filled_avg_price, submitted_at, updated_at
+        FROM orders;
+    DROP TABLE orders;
+    ALTER TABLE orders_by_ledger RENAME TO orders;
+    CREATE INDEX orders_cycle ON orders (cycle_id);
+    DROP INDEX fills_order;
+    CREATE INDEX fills_order ON fills (ledger, order_id);",
+];
+
+/// One row of `StateStore::recent_cycles`
+#[derive(Debug, Clone, Serialize)]
+pub struct CycleSummary {
+    pub cycle_id: String,
+    pub started_at: String,
+    pub finished_at: Option<String>,
+    pub outcome: Option<String>,
+    pub action: Option<String>,
+    pub symbol: Option<String>,
+    pub total_signal: Option<f64>,
+    pub orders: u32,
+    pub total_value: Option<String>,
+}
+
+/// SQLite history of every cycle, its signals, orders, fills and portfolio.
+///
+/// Recording is best effort: a failed write is logged and never interrupts
+/// trading. Orders are keyed by the ledger and the broker's order ID, so
+/// recording the same order again updates its status; each increase in its filled quantity is
+/// stored as a fill. Expected positions are the sum of all fills plus any
+/// adjustments made when the broker's positions were adopted, counting only
+/// those of the store's ledger so a different account's history never shows
+/// up as expected state.
+pub struct StateStore {
+    conn: Mutex<Connection>,
+    ledger: String,
+}
+
+impl StateStore {
+    /// Open (or create) the database at `path` and bring its schema up to date
+    pub fn open(path: &Path) -> rusqlite::Result<Self> {
+        Self::migrate(Connection::open(path)?)
+    }
+    
+    fn migrate(mut conn: Connection) -> rusqlite::Result<Self> {
+        let version: usize = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        for (idx, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+            info!("Applying state store migration {}", idx + 1);
+            let tx = conn.transaction()?;
+            tx.execute_batch(migration)?;
+            tx.pragma_update(None, "user_version", idx + 1)?;
+            tx.commit()?;
+        }
+        Ok(Self { conn: Mutex::new(conn), ledger: String::new() })
+    }
+    
+    /// Record orders and positions under `ledger`, the broker's `ledger_id`
+    pub fn with_ledger(mut self, ledger: &str) -> Self {
+        self.ledger = ledger.to_string();
+        self
+    }
+    
+    pub fn begin_cycle(&self, cycle_id: &str, started_at: DateTime<Utc>) {
+        self.write("cycle", |conn| {
+            conn.execute(
+                "INSERT OR REPLACE INTO cycles (cycle_id, started_at) VALUES (?1, ?2)",
+                params![cycle_id, started_at.to_rfc3339()],
+            )
+        });
+    }
+    
+    /// Close a cycle with a short outcome such as "traded", "no_trade" or "interrupted"
+    pub fn finish_cycle(&self, cycle_id: &str, outcome: &str) {
+        self.write("cycle", |conn| {
+            conn.execute(
+                "UPDATE cycles SET finished_at = ?2, outcome = ?3 WHERE cycle_id = ?1",
+                params![cycle_id, Utc::now().to_rfc3339(), outcome],
+            )
+        });
+    }
+    
+    /// Record the trading signal and each of its components
+    pub fn record_signal(&self, cycle_id: &str, signal: &TradingSignal) {
+        self.write("signal", |conn| {
+            let tx = conn.transaction()?;
+            for (name, component) in &signal.components {
+                Self::insert_component(&tx, cycle_id, name, component)?;
+            }
+            tx.execute(
+                "INSERT OR REPLACE INTO trading_signals
+                 (cycle_id, timestamp, total_signal, action, symbol, confidence, blocked_reason)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    cycle_id,
+                    signal.timestamp.to_rfc3339(),
+                    signal.total_signal,
+                    signal.action,
+                    signal.symbol,
+                    signal.confidence,
+                    signal.blocked_reason,
+                ],
+            )?;
+            tx.commit()
+        });
+    }
+    
+    fn insert_component(conn: &Connection, cycle_id: &str, name: &str, component: &ComponentSignal) -> rusqlite::Result<usize> {
+        conn.execute(
+            "INSERT OR REPLACE INTO component_signals (cycle_id, name, value, available, as_of, reason)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                cycle_id,
+                name,
+                component.value,
+                component.available,
+                component.as_of.map(|t| t.to_rfc3339()),
+                component.reason,
+            ],
+        )
+    }
+    
+    /// Insert or update an order, adding a fill for any quantity filled since
+    /// it was last recorded
+    pub fn record_order(&self, cycle_id: &str, order: &TradeResult) {
+        self.write("order", |conn| {
+            let tx = conn.transaction()?;
+            let previous: Option<(Option<String>, Option<String>)> = tx
+                .query_row(
+                    "SELECT filled_qty, filled_avg_price FROM orders WHERE ledger = ?1 AND order_id = ?2",
+                    params![self.ledger, order.order_id],
+                    |row| Ok((row.get(0)?, row.get(1)?)),
+                )
+                .optional()?;
+            let (prev_qty, prev_price) = previous
+                .map(|(qty, price)| (parse_decimal(qty), parse_decimal(price)))
+                .unwrap_or_default();
+            
+            tx.execute(
+                "INSERT INTO orders
+                 (order_id, client_order_id, cycle_id, symbol, side, order_type, qty, notional,
+                  status, filled_qty, filled_avg_price, submitted_at, updated_at, ledger)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)
+                 ON CONFLICT (ledger, order_id) DO UPDATE SET
+                  status = excluded.status,
+                  filled_qty = excluded.filled_qty,
+                  filled_avg_price = excluded.filled_avg_price,
+                  updated_at = excluded.updated_at",
+                params![
+                    order.order_id,
+                    order.client_order_id,
+                    cycle_id,
+                    order.symbol,
+                    order.side,
+                    order.order_type.to_string(),
+                    order.qty.to_string(),
+                    order.notional.map(|n| n.to_string()),
+                    order.status.to_string(),
+                    order.filled_qty.map(|q| q.to_string()),
+                    order.filled_avg_price.map(|p| p.to_string()),
+                    order.submitted_at,
+                    Utc::now().to_rfc3339(),
+                    self.ledger,
+                ],
+            )?;
+            
+            // The broker reports a running average, so the price of the new
+            // shares is what moved the average
+            let filled = order.filled_qty.unwrap_or_default();
+            let new_qty = filled - prev_qty.unwrap_or_default();
+            if new_qty > Decimal::ZERO {
+                let cost = filled * order.filled_avg_price.unwrap_or_default()
+                    - prev_qty.unwrap_or_default() * prev_price.unwrap_or_default();
+                tx.execute(
+                    "INSERT INTO fills (order_id, symbol, side, qty, price, recorded_at, ledger)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                    params![
+                        order.order_id,
+                        order.symbol,
+                        order.side,
+                        new_qty.to_string(),
+                        (cost / new_qty).round_dp(6).normalize().to_string(),
+                        Utc::now().to_rfc3339(),
+                        self.ledger,
+                    ],
+                )?;
+            }
+            tx.commit()
+        });
+    }
+    
+    /// Record the output of `Broker::get_portfolio_summary`
+    pub fn record_portfolio(&self, cycle_id: &str, portfolio: &serde_json::Value) {
+        let field = |name: &str| portfolio.get(name).and_then(|v| v.as_str()).map(str::to_string);
+        self.write("portfolio", |conn| {
+            conn.execute(
+                "INSERT INTO portfolio_snapshots (cycle_id, taken_at, total_value, cash, buying_power, positions)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    cycle_id,
+                    Utc::now().to_rfc3339(),
+                    field("total_value"),
+                    field("cash"),
+                    field("buying_power"),
+                    portfolio.get("positions").cloned().unwrap_or_default().to_string(),
+                ],
+            )
+        });
+    }
+    
+    /// Record a correction of `qty` shares to the expected `symbol` position
+    pub fn record_adjustment(&self, symbol: &str, qty: Decimal, reason: &str) {
+        self.write("position adjustment", |conn| {
+            conn.execute(
+                "INSERT INTO position_adjustments (symbol, qty, reason, recorded_at, ledger) VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![symbol, qty.to_string(), reason, Utc::now().to_rfc3339(), self.ledger],
+            )
+        });
+    }
+    
+    /// Shares per symbol this ledger's fills and adjustments add up to, or
+    /// `None` if nothing has been recorded for it yet
+    pub fn expected_positions(&self) -> rusqlite::Result<Option<HashMap<String, Decimal>>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT symbol, CASE side WHEN 'sell' THEN '-' || qty ELSE qty END FROM fills WHERE ledger = ?1
+             UNION ALL SELECT symbol, qty FROM position_adjustments WHERE ledger = ?1",
+        )?;
+        let rows = stmt.query_map(params![self.ledger], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?;
+        let mut positions = HashMap::new();
+        let mut any = false;
+        for row in rows {
+            let (symbol, qty) = row?;
+            any = true;
+            *positions.entry(symbol).or_insert(Decimal::ZERO) += parse_decimal(Some(qty)).unwrap_or_default();
+        }
+        Ok(any.then_some(positions))
+    }
+    
+    /// IDs of this ledger's orders last recorded in a non-terminal state
+    pub fn working_orders(&self) -> rusqlite::Result<Vec<String>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT order_id FROM orders WHERE ledger = ?1 AND status IN ('new', 'accepted', 'partially_filled')",
+        )?;
+        let rows = stmt.query_map(params![self.ledger], |row| row.get(0))?;
+        rows.collect()
+    }
+    
+    /// The latest `limit` cycles, newest first, with their signal, order count
+    /// and closing portfolio value
+    pub fn recent_cycles(&self, limit: usize) -> rusqlite::Result<Vec<CycleSummary>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT c.cycle_id, c.started_at, c.finished_at, c.outcome, s.action, s.symbol, s.total_signal,
+                    (SELECT COUNT(*) FROM orders o WHERE o.cycle_id = c.cycle_id),
+                    (SELECT p.total_value FROM portfolio_snapshots p WHERE p.cycle_id = c.cycle_id ORDER BY p.id DESC LIMIT 1)
+             FROM cycles c LEFT JOIN trading_signals s ON s.cycle_id = c.cycle_id
+             ORDER BY c.started_at DESC LIMIT ?1",
+        )?;
+        let rows = stmt.query_map(params![limit as i64], |row| {
+            Ok(CycleSummary {
+                cycle_id: row.get(0)?,
+                started_at: row.get(1)?,
+                finished_at: row.get(2)?,
+                outcome: row.get(3)?,
+                action: row.get(4)?,
+                symbol: row.get(5)?,
+                total_signal: row.get(6)?,
+                orders: row.get(7)?,
+                total_value: row.get(8)?,
+            })
+        })?;
+        rows.collect()
+    }
+    
+    fn write<T>(&self, what: &str, op: impl FnOnce(&mut Connection) -> rusqlite::Result<T>) {
+        let mut conn = self.conn.lock().unwrap();
+        if let Err(e) = op(&mut conn) {
+            error!("Failed to record {} in state store: {}", what, e);
+        }
+    }
+}
+
+fn parse_decimal(value: Option<String>) -> Option<Decimal> {
+    value.and_then(|v| v.parse().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trading::order::{OrderStatus, OrderType};
+    use rust_decimal_macros::dec;
+    
+    fn temp_db(name: &str) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("algotrade-state-{}-{}.db", std::process::id(), name));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+    
+    fn user_version(store: &StateStore) -> usize {
+        store.conn.lock().unwrap().query_row("PRAGMA user_version", [], |row| row.get(0)).unwrap()
+    }
+    
+    fn order(id: &str, side: &str, status: OrderStatus, filled: Decimal, avg_price: Decimal) -> TradeResult {
+        TradeResult {
+            order_id: id.to_string(),
+            client_order_id: format!("client-{}", id),
+            symbol: "BOIL".to_string(),
+            qty: dec!(30),
+            notional: None,
+            side: side.to_string(),
+            order_type: OrderType::Market,
+            status,
+            filled_qty: Some(filled),
+            filled_avg_price: Some(avg_price),
+            submitted_at: "2024-10-24T14:00:00Z".to_string(),
+        }
+    }
+    
+    fn fills(store: &StateStore) -> Vec<(String, String)> {
+        let conn = store.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT qty, price FROM fills ORDER BY id").unwrap();
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?))).unwrap();
+        rows.collect::<rusqlite::Result<_>>().unwrap()
+    }
+    
+    #[test]
+    fn new_database_runs_every_migration_once() {
+        let path = temp_db("fresh");
+        let store = StateStore::open(&path).unwrap();
+        assert_eq!(user_version(&store), MIGRATIONS.len());
+        assert_eq!(store.expected_positions().unwrap(), None);
+        store.record_adjustment("BOIL", dec!(5), "adopted");
+        drop(store);
+        
+        let reopened = StateStore::open(&path).unwrap();
+        assert_eq!(user_version(&reopened), MIGRATIONS.len());
+        assert_eq!(reopened.expected_positions().unwrap().unwrap()["BOIL"], dec!(5));
+        std::fs::remove_file(&path).unwrap();
+    }
+    
+    #[test]
+    fn upgrades_a_version_1_database() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(MIGRATIONS[0]).unwrap();
+        conn.pragma_update(None, "user_version", 1).unwrap();
+        conn.execute(
+            "INSERT INTO fills (order_id, symbol, side, qty, price, recorded_at)
+             VALUES ('old', 'BOIL', 'buy', '7', '20', '2024-10-01T14:00:00Z')",
+            [],
+        ).unwrap();
+        
+        let store = StateStore::migrate(conn).unwrap();
+        assert_eq!(user_version(&store), MIGRATIONS.len());
+        // Rows from before the ledger column belong to no broker account
+        assert_eq!(store.expected_positions().unwrap().unwrap()["BOIL"], dec!(7));
+        let store = store.with_ledger("simulated:test");
+        assert_eq!(store.expected_positions().unwrap(), None);
+        store.record_adjustment("KOLD", dec!(2), "adopted");
+        assert_eq!(store.expected_positions().unwrap().unwrap()["KOLD"], dec!(2));
+    }
+    
+    #[test]
+    fn record_order_stores_each_new_fill() {
+        let store = StateStore::migrate(Connection::open_in_memory().unwrap()).unwrap();
+        store.record_order("c1", &order("o1", "buy", OrderStatus::Accepted, dec!(0), dec!(0)));
+        assert!(fills(&store).is_empty());
+        assert_eq!(store.working_orders().unwrap(), vec!["o1".to_string()]);
+        
+        store.record_order("c1", &order("o1", "buy", OrderStatus::PartiallyFilled, dec!(10), dec!(20)));
+        // 30 shares averaging $21 after 10 at $20: the other 20 cost $21.50
+        store.record_order("c1", &order("o1", "buy", OrderStatus::Filled, dec!(30), dec!(21)));
+        store.record_order("c1", &order("o1", "buy", OrderStatus::Filled, dec!(30), dec!(21)));
+        assert_eq!(fills(&store), vec![
+            ("10".to_string(), "20".to_string()),
+            ("20".to_string(), "21.5".to_string()),
+        ]);
+        assert!(store.working_orders().unwrap().is_empty());
+        
+        store.record_order("c2", &order("o2", "sell", OrderStatus::Filled, dec!(12), dec!(22)));
+        assert_eq!(store.expected_positions().unwrap().unwrap()["BOIL"], dec!(18));
+    }
+    
+    #[test]
+    fn ledgers_sharing_a_database_stay_separate() {
+        let path = temp_db("ledgers");
+        let paper = StateStore::open(&path).unwrap().with_ledger("alpaca:https://paper-api.alpaca.markets");
+        let sim = StateStore::open(&path).unwrap().with_ledger("simulated:run-1");
+        
+        paper.record_order("c1", &order("p1", "buy", OrderStatus::Filled, dec!(30), dec!(20)));
+        sim.record_order("c1", &order("s1", "buy", OrderStatus::Accepted, dec!(0), dec!(0)));
+        sim.record_adjustment("KOLD", dec!(4), "adopted");
+        
+        assert_eq!(paper.expected_positions().unwrap(), Some(HashMap::from([("BOIL".to_string(), dec!(30))])));
+        assert_eq!(sim.expected_positions().unwrap(), Some(HashMap::from([("KOLD".to_string(), dec!(4))])));
+        assert!(paper.working_orders().unwrap().is_empty());
+        assert_eq!(sim.working_orders().unwrap(), vec!["s1".to_string()]);
+        
+        drop((paper, sim));
+        std::fs::remove_file(&path).unwrap();
+    }
+    
+    #[test]
+    fn runs_reusing_an_order_id_keep_their_own_orders_and_fills() {
+        let path = temp_db("reused-ids");
+        let first = StateStore::open(&path).unwrap().with_ledger("simulated:run-1");
+        first.record_order("c1", &order("sim-1", "buy", OrderStatus::Filled, dec!(30), dec!(20)));
+        drop(first);
+        
+        // A restarted simulated broker numbers its orders from sim-1 again
+        let second = StateStore::open(&path).unwrap().with_ledger("simulated:run-2");
+        second.record_order("c2", &order("sim-1", "buy", OrderStatus::Accepted, dec!(0), dec!(0)));
+        second.record_order("c2", &order("sim-1", "buy", OrderStatus::Filled, dec!(10), dec!(22)));
+        
+        assert_eq!(fills(&second), vec![
+            ("30".to_string(), "20".to_string()),
+            ("10".to_string(), "22".to_string()),
+        ]);
+        assert_eq!(second.expected_positions().unwrap(), Some(HashMap::from([("BOIL".to_string(), dec!(10))])));
+        let orders: Vec<(String, String)> = {
+            let conn = second.conn.lock().unwrap();
+            let mut stmt = conn.prepare("SELECT ledger, cycle_id FROM orders ORDER BY ledger").unwrap();
+            let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?))).unwrap();
+            rows.collect::<rusqlite::Result<_>>().unwrap()
+        };
+        assert_eq!(orders, vec![
+            ("simulated:run-1".to_string(), "c1".to_string()),
+            ("simulated:run-2".to_string(), "c2".to_string()),
+        ]);
+        
+        let reopened = StateStore::open(&path).unwrap().with_ledger("simulated:run-1");
+        assert_eq!(reopened.expected_positions().unwrap(), Some(HashMap::from([("BOIL".to_string(), dec!(30))])));
+        drop((second, reopened));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
+
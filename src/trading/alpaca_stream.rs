@@ -0,0 +1,448 @@
+use crate::config::TradingConfig;
+use crate::trading::alpaca_trader::{to_trade_result, AlpacaOrder};
+use crate::trading::broker::TradeResult;
+use futures_util::{SinkExt, StreamExt};
+use log::{info, warn, error, debug};
+use serde::Deserialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::Notify;
+use tokio_tungstenite::tungstenite::Message;
+
+const RECONNECT_BASE: Duration = Duration::from_secs(1);
+const RECONNECT_MAX: Duration = Duration::from_secs(60);
+/// Most events kept for orders nobody watches yet
+const UNWATCHED_CAP: usize = 256;
+
+/// Latest streamed state of each order `OrderTracker` is watching.
+///
+/// The stream reports every order on the account, including ones the bot
+/// never waits for. An order's events can also arrive before the REST call
+/// that placed it returns, so events for unwatched orders are kept in a
+/// small buffer (the latest `UNWATCHED_CAP` orders) that `watch` picks up.
+#[derive(Clone, Default)]
+pub struct OrderUpdates {
+    orders: Arc<Mutex<Orders>>,
+    connected: Arc<AtomicBool>,
+    notify: Arc<Notify>,
+}
+
+#[derive(Default)]
+struct Orders {
+    /// Watched orders and their latest streamed state
+    watched: HashMap<String, Option<TradeResult>>,
+    /// Latest event for orders nobody watches yet, oldest first
+    unwatched: VecDeque<TradeResult>,
+}
+
+/// Events can arrive out of order; never let a late one undo a terminal state
+fn apply(known: &mut Option<TradeResult>, update: TradeResult) {
+    let stale = known
+        .as_ref()
+        .map(|known| known.status.is_terminal() && !update.status.is_terminal())
+        .unwrap_or(false);
+    if !stale {
+        *known = Some(update);
+    }
+}
+
+impl OrderUpdates {
+    fn publish(&self, update: TradeResult) {
+        let mut orders = self.orders.lock().unwrap();
+        if let Some(known) = orders.watched.get_mut(&update.order_id) {
+            apply(known, update);
+        } else if let Some(position) = orders.unwatched.iter().position(|o| o.order_id == update.order_id) {
+            let mut known = orders.unwatched.remove(position);
+            apply(&mut known, update);
+            orders.unwatched.extend(known);
+        } else {
+            orders.unwatched.push_back(update);
+            if orders.unwatched.len() > UNWATCHED_CAP {
+                orders.unwatched.pop_front();
+            }
+        }
+        drop(orders);
+        self.notify.notify_waiters();
+    }
+    
+    fn set_connected(&self, connected: bool) {
+        self.connected.store(connected, Ordering::SeqCst);
+        self.notify.notify_waiters();
+    }
+    
+    /// Whether the stream is subscribed and delivering updates right now
+    pub fn is_connected(&self) -> bool {
+        self.connected.load(Ordering::SeqCst)
+    }
+    
+    /// Start keeping updates for an order, including any that already arrived
+    pub fn watch(&self, order_id: &str) {
+        let mut orders = self.orders.lock().unwrap();
+        if orders.watched.contains_key(order_id) {
+            return;
+        }
+        let buffered = orders
+            .unwatched
+            .iter()
+            .position(|o| o.order_id == order_id)
+            .and_then(|position| orders.unwatched.remove(position));
+        orders.watched.insert(order_id.to_string(), buffered);
+    }
+    
+    pub fn latest(&self, order_id: &str) -> Option<TradeResult> {
+        self.orders.lock().unwrap().watched.get(order_id).cloned().flatten()
+    }
+    
+    /// Drop an order once its tracking is finished
+    pub fn forget(&self, order_id: &str) {
+        self.orders.lock().unwrap().watched.remove(order_id);
+    }
+    
+    /// Resolves the next time any order update arrives or the connection
+    /// comes up or drops
+    pub async fn changed(&self) {
+        self.notify.notified().await;
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct StreamMessage {
+    stream: String,
+    #[serde(default)]
+    data: serde_json::Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct TradeUpdateEvent {
+    event: String,
+    order: AlpacaOrder,
+}
+
+/// Client for Alpaca's `trade_updates` WebSocket stream.
+///
+/// Authenticates, subscribes and publishes every order event into
+/// `OrderUpdates`, reconnecting with backoff whenever the connection drops.
+/// `ALPACA_STREAM_URL` can point it at a local stand-in server.
+pub struct TradeUpdatesStream {
+    url: String,
+    api_key: String,
+    secret_key: String,
+    updates: OrderUpdates,
+}
+
+impl TradeUpdatesStream {
+    pub fn new(config: &TradingConfig, updates: OrderUpdates) -> Self {
+        Self {
+            url: config.alpaca_stream_url.clone(),
+            api_key: config.alpaca_api_key.clone(),
+            secret_key: config.alpaca_secret_key.clone(),
+            updates,
+        }
+    }
+    
+    /// Run the stream on a background task for the life of the process
+    pub fn spawn(self) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move { self.run().await })
+    }
+    
+    async fn run(self) {
+        let mut delay = RECONNECT_BASE;
+        loop {
+            info!("Connecting to trade updates stream at {}...", self.url);
+            let result = self.connect_and_listen(&mut delay).await;
+            self.updates.set_connected(false);
+            match result {
+                Ok(()) => warn!("Trade updates stream closed by server"),
+                Err(e) => error!("Trade updates stream error: {}", e),
+            }
+            info!("Reconnecting to trade updates stream in {}s", delay.as_secs());
+            tokio::time::sleep(delay).await;
+            delay = (delay * 2).min(RECONNECT_MAX);
+        }
+    }
+    
+    /// One connection's lifetime. `delay` is reset once the subscription is confirmed.
+    async fn connect_and_listen(&self, delay: &mut Duration) -> anyhow::Result<()> {
+        let (mut socket, _) = tokio_tungstenite::connect_async(self.url.as_str()).await?;
+        
+        let auth = serde_json::json!({
+            "action": "auth",
+            "key": self.api_key,
+            "secret": self.secret_key,
+        });
+        socket.send(Message::Text(auth.to_string())).await?;
+        
+        while let Some(frame) = socket.next().await {
+            let text = match frame? {
+                Message::Text(text) => text,
+                // Alpaca's paper endpoint sends JSON in binary frames
+                Message::Binary(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+                Message::Ping(payload) => {
+                    socket.send(Message::Pong(payload)).await?;
+                    continue;
+                }
+                Message::Close(_) => return Ok(()),
+                _ => continue,
+            };
+            
+            let message: StreamMessage = match serde_json::from_str(&text) {
+                Ok(message) => message,
+                Err(e) => {
+                    warn!("  Ignoring unparseable stream message ({}): {}", e, text);
+                    continue;
+                }
+            };
+            
+            match message.stream.as_str() {
+                "authorization" => {
+                    let status = message.data.get("status").and_then(|s| s.as_str()).unwrap_or("");
+                    if status != "authorized" {
+                        anyhow::bail!("trade updates stream authorization failed: {}", message.data);
+                    }
+                    info!("  Trade updates stream authorized, subscribing...");
+                    let listen = serde_json::json!({
+                        "action": "listen",
+                        "data": { "streams": ["trade_updates"] },
+                    });
+                    socket.send(Message::Text(listen.to_string())).await?;
+                }
+                "listening" => {
+                    info!("  Listening for trade updates");
+                    *delay = RECONNECT_BASE;
+                    self.updates.set_connected(true);
+                }
+                "trade_updates" => match serde_json::from_value::<TradeUpdateEvent>(message.data) {
+                    Ok(update) => {
+                        let result = to_trade_result(&update.order);
+                        debug!("  Trade update: {} {} -> {}", update.event, result.order_id, result.status);
+                        self.updates.publish(result);
+                    }
+                    Err(e) => warn!("  Ignoring malformed trade update: {}", e),
+                },
+                other => debug!("  Ignoring stream message on {}", other),
+            }
+        }
+        
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trading::order::{OrderStatus, OrderTracker};
+    use crate::trading::SimulatedBroker;
+    use rust_decimal::Decimal;
+    use tokio::net::TcpListener;
+    use tokio::sync::oneshot;
+    use tokio_tungstenite::WebSocketStream;
+    
+    fn order_json(id: &str, status: &str) -> serde_json::Value {
+        serde_json::json!({
+            "id": id,
+            "client_order_id": format!("hoc-{}", id),
+            "symbol": "BOIL",
+            "qty": "10",
+            "side": "buy",
+            "type": "market",
+            "status": status,
+            "filled_qty": if status == "filled" { "10" } else { "0" },
+            "filled_avg_price": if status == "filled" { Some("25.5") } else { None },
+        })
+    }
+    
+    fn trade_update(id: &str, status: &str) -> Message {
+        let data = serde_json::json!({ "event": status, "order": order_json(id, status) });
+        Message::Text(serde_json::json!({ "stream": "trade_updates", "data": data }).to_string())
+    }
+    
+    async fn next_json(socket: &mut WebSocketStream<tokio::net::TcpStream>) -> serde_json::Value {
+        match socket.next().await {
+            Some(Ok(Message::Text(text))) => serde_json::from_str(&text).unwrap(),
+            other => panic!("expected a text frame, got {:?}", other),
+        }
+    }
+    
+    /// Accept one connection and walk it through auth and listen the way Alpaca does
+    async fn accept_and_subscribe(listener: &TcpListener) -> WebSocketStream<tokio::net::TcpStream> {
+        let (tcp, _) = listener.accept().await.unwrap();
+        let mut socket = tokio_tungstenite::accept_async(tcp).await.unwrap();
+        
+        let auth = next_json(&mut socket).await;
+        assert_eq!(auth["action"], "auth");
+        assert_eq!(auth["key"], "test-key");
+        assert_eq!(auth["secret"], "test-secret");
+        let authorized = serde_json::json!({ "stream": "authorization", "data": { "status": "authorized", "action": "authenticate" } });
+        socket.send(Message::Text(authorized.to_string())).await.unwrap();
+        
+        let listen = next_json(&mut socket).await;
+        assert_eq!(listen["action"], "listen");
+        assert_eq!(listen["data"]["streams"], serde_json::json!(["trade_updates"]));
+        let listening = serde_json::json!({ "stream": "listening", "data": { "streams": ["trade_updates"] } });
+        socket.send(Message::Text(listening.to_string())).await.unwrap();
+        socket
+    }
+    
+    async fn wait_until_buffered(updates: &OrderUpdates, order_id: &str) {
+        let buffered = || updates.orders.lock().unwrap().unwatched.iter().any(|o| o.order_id == order_id);
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while !buffered() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("update was never buffered");
+    }
+    
+    #[tokio::test]
+    async fn streamed_updates_reach_tracker_and_survive_reconnect() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let config = TradingConfig {
+            alpaca_stream_url: format!("ws://{}", listener.local_addr().unwrap()),
+            alpaca_api_key: "test-key".to_string(),
+            alpaca_secret_key: "test-secret".to_string(),
+            order_poll_interval_ms: 50,
+            ..TradingConfig::default()
+        };
+        
+        let (first_go, first_ready) = oneshot::channel::<()>();
+        let (second_go, second_ready) = oneshot::channel::<()>();
+        let server = tokio::spawn(async move {
+            let mut socket = accept_and_subscribe(&listener).await;
+            first_ready.await.unwrap();
+            socket.send(trade_update("order-1", "filled")).await.unwrap();
+            // Drop the connection; the client has to reconnect and subscribe again
+            socket.close(None).await.unwrap();
+            
+            let mut socket = accept_and_subscribe(&listener).await;
+            second_ready.await.unwrap();
+            socket.send(trade_update("foreign", "filled")).await.unwrap();
+            socket.send(trade_update("order-2", "filled")).await.unwrap();
+            // Keep the connection open until the client is done
+            while socket.next().await.is_some() {}
+        });
+        
+        let updates = OrderUpdates::default();
+        let stream = TradeUpdatesStream::new(&config, updates.clone()).spawn();
+        // The simulated broker knows neither order, so only the stream can complete them
+        let broker = SimulatedBroker::new(Decimal::ZERO, Decimal::ZERO, Decimal::ZERO);
+        let tracker = OrderTracker::new(&config).with_updates(updates.clone());
+        let timeout = Duration::from_secs(10);
+        
+        // The fill arrives before the tracker starts watching, as it can when
+        // an order fills before the REST call that placed it returns
+        let pending: AlpacaOrder = serde_json::from_value(order_json("order-1", "new")).unwrap();
+        first_go.send(()).unwrap();
+        wait_until_buffered(&updates, "order-1").await;
+        let first = tracker.await_terminal_within(&broker, to_trade_result(&pending), timeout).await;
+        assert_eq!(first.status, OrderStatus::Filled);
+        assert_eq!(first.filled_avg_price, Some("25.5".parse().unwrap()));
+        
+        let pending: AlpacaOrder = serde_json::from_value(order_json("order-2", "new")).unwrap();
+        second_go.send(()).unwrap();
+        wait_until_buffered(&updates, "order-2").await;
+        assert!(updates.is_connected());
+        let second = tracker.await_terminal_within(&broker, to_trade_result(&pending), timeout).await;
+        assert_eq!(second.status, OrderStatus::Filled);
+        
+        // Orders nobody watches stay buffered, and finished ones are dropped
+        assert!(updates.latest("foreign").is_none());
+        let orders = updates.orders.lock().unwrap();
+        assert!(orders.watched.is_empty());
+        assert_eq!(orders.unwatched.iter().map(|o| o.order_id.as_str()).collect::<Vec<_>>(), ["foreign"]);
+        drop(orders);
+        
+        stream.abort();
+        server.abort();
+    }
+    
+    fn result(id: &str, status: &str) -> TradeResult {
+        to_trade_result(&serde_json::from_value(order_json(id, status)).unwrap())
+    }
+    
+    fn partial_update(id: &str, filled: &str) -> Message {
+        let mut order = order_json(id, "partially_filled");
+        order["filled_qty"] = filled.into();
+        order["filled_avg_price"] = "25.5".into();
+        let data = serde_json::json!({ "event": "partial_fill", "order": order });
+        Message::Text(serde_json::json!({ "stream": "trade_updates", "data": data }).to_string())
+    }
+    
+    #[tokio::test]
+    async fn every_streamed_partial_fill_reaches_the_tracker() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let config = TradingConfig {
+            alpaca_stream_url: format!("ws://{}", listener.local_addr().unwrap()),
+            alpaca_api_key: "test-key".to_string(),
+            alpaca_secret_key: "test-secret".to_string(),
+            order_poll_interval_ms: 20,
+            ..TradingConfig::default()
+        };
+        
+        let (go, ready) = oneshot::channel::<()>();
+        let server = tokio::spawn(async move {
+            let mut socket = accept_and_subscribe(&listener).await;
+            ready.await.unwrap();
+            socket.send(partial_update("order-1", "3")).await.unwrap();
+            // Let the tracker take the first fill before the second arrives
+            tokio::time::sleep(Duration::from_millis(300)).await;
+            socket.send(partial_update("order-1", "7")).await.unwrap();
+            while socket.next().await.is_some() {}
+        });
+        
+        let updates = OrderUpdates::default();
+        let stream = TradeUpdatesStream::new(&config, updates.clone()).spawn();
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while !updates.is_connected() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("stream never connected");
+        
+        // The simulated broker does not know the order, so only the stream can report fills
+        let broker = SimulatedBroker::new(Decimal::ZERO, Decimal::ZERO, Decimal::ZERO);
+        let tracker = OrderTracker::new(&config).with_updates(updates.clone());
+        let pending: AlpacaOrder = serde_json::from_value(order_json("order-1", "new")).unwrap();
+        go.send(()).unwrap();
+        let result = tracker.await_terminal_within(&broker, to_trade_result(&pending), Duration::from_secs(1)).await;
+        assert_eq!(result.status, OrderStatus::PartiallyFilled);
+        assert_eq!(result.filled_qty, Some(Decimal::from(7)));
+        
+        stream.abort();
+        server.abort();
+    }
+    
+    #[test]
+    fn late_event_does_not_undo_terminal_state() {
+        let updates = OrderUpdates::default();
+        updates.watch("order-1");
+        updates.publish(result("order-1", "filled"));
+        updates.publish(result("order-1", "new"));
+        assert_eq!(updates.latest("order-1").unwrap().status, OrderStatus::Filled);
+    }
+    
+    #[test]
+    fn unwatched_events_are_buffered_until_watched() {
+        let updates = OrderUpdates::default();
+        updates.publish(result("order-1", "filled"));
+        // A late event for a buffered order is stale too
+        updates.publish(result("order-1", "new"));
+        assert!(updates.latest("order-1").is_none());
+        
+        updates.watch("order-1");
+        assert_eq!(updates.latest("order-1").unwrap().status, OrderStatus::Filled);
+        assert!(updates.orders.lock().unwrap().unwatched.is_empty());
+        
+        // Only the most recent orders are kept
+        for n in 0..UNWATCHED_CAP + 10 {
+            updates.publish(result(&format!("other-{}", n), "new"));
+        }
+        let orders = updates.orders.lock().unwrap();
+        assert_eq!(orders.unwatched.len(), UNWATCHED_CAP);
+        assert_eq!(orders.unwatched.front().unwrap().order_id, "other-10");
+    }
+}
+
@@ -0,0 +1,145 @@
+use crate::trading::error::BrokerResult;
+use crate::trading::order::{OrderRequest, OrderStatus, OrderType};
+use async_trait::async_trait;
+use log::info;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+// Money and quantities are fixed-point decimals, as Alpaca reports them, so
+// reconciled numbers match the broker to the last digit.
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Position {
+    pub symbol: String,
+    pub qty: Decimal,
+    pub market_value: Decimal,
+    pub avg_entry_price: Decimal,
+    pub unrealized_pl: Decimal,
+    pub unrealized_plpc: Decimal,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountInfo {
+    pub equity: Decimal,
+    pub buying_power: Decimal,
+    pub cash: Decimal,
+    pub portfolio_value: Decimal,
+    /// Equity at the previous market close, the baseline for daily loss limits
+    #[serde(default)]
+    pub last_equity: Decimal,
+}
+
+/// Best bid and offer
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Quote {
+    pub bid: Decimal,
+    pub ask: Decimal,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TradeResult {
+    pub order_id: String,
+    /// Deterministic ID the order was submitted with (see `TradeExecutor`)
+    #[serde(default)]
+    pub client_order_id: String,
+    pub symbol: String,
+    /// Requested shares; 0 for notional orders until the broker sizes them
+    pub qty: Decimal,
+    /// Requested dollar amount of a notional order
+    #[serde(default)]
+    pub notional: Option<Decimal>,
+    pub side: String,
+    #[serde(default)]
+    pub order_type: OrderType,
+    pub status: OrderStatus,
+    pub filled_qty: Option<Decimal>,
+    pub filled_avg_price: Option<Decimal>,
+    pub submitted_at: String,
+}
+
+/// Brokerage operations the trading cycle depends on.
+///
+/// `AlpacaTrader` is the live implementation; anything else that can report
+/// an account, hold positions and fill orders can be plugged in instead.
+#[async_trait]
+pub trait Broker: Send + Sync {
+    /// Short human readable name used in logs
+    fn name(&self) -> &str;
+    
+    /// Identifies the account whose orders and positions the state store
+    /// tracks. A broker whose account does not outlive the process must
+    /// return a new ID for every instance.
+    fn ledger_id(&self) -> String {
+        self.name().to_string()
+    }
+    
+    async fn get_account_info(&self) -> BrokerResult<AccountInfo>;
+    
+    async fn get_current_position(&self, symbol: &str) -> BrokerResult<Option<Position>>;
+    
+    async fn get_positions(&self) -> BrokerResult<Vec<Position>>;
+    
+    async fn get_current_price(&self, symbol: &str) -> BrokerResult<Decimal>;
+    
+    /// Up to `days` most recent daily closing prices, oldest first
+    async fn get_daily_closes(&self, symbol: &str, days: usize) -> BrokerResult<Vec<Decimal>>;
+    
+    /// Latest bid and ask, used to price limit and stop orders
+    async fn get_latest_quote(&self, symbol: &str) -> BrokerResult<Quote>;
+    
+    /// Submit a market, limit or stop order and follow it until it is done
+    /// or `OrderRequest::fill_timeout` passes
+    async fn place_order(&self, order: &OrderRequest) -> BrokerResult<TradeResult>;
+    
+    /// Orders that are still working (not yet in a terminal state)
+    async fn get_open_orders(&self) -> BrokerResult<Vec<TradeResult>>;
+    
+    /// Latest state of a previously submitted order
+    async fn get_order(&self, order_id: &str) -> BrokerResult<TradeResult>;
+    
+    async fn cancel_order(&self, order_id: &str) -> BrokerResult<()>;
+    
+    async fn get_portfolio_summary(&self) -> BrokerResult<serde_json::Value> {
+        info!("  Fetching portfolio positions from {}...", self.name());
+        let positions = self.get_positions().await?;
+        info!("  Found {} positions", positions.len());
+        
+        info!("  Fetching account information...");
+        let account = self.get_account_info().await?;
+        info!("  Account equity: ${:.2}", account.equity);
+        info!("  Buying power: ${:.2}", account.buying_power);
+        info!("  Cash: ${:.2}", account.cash);
+        
+        let mut portfolio_positions = Vec::new();
+        for position in positions {
+            let current_price = if position.qty.is_zero() {
+                Decimal::ZERO
+            } else {
+                position.market_value / position.qty
+            };
+            
+            info!("  Position: {} - Qty: {:.2}, Value: ${:.2}, Price: ${:.2}",
+                  position.symbol, position.qty, position.market_value, current_price);
+            
+            portfolio_positions.push(serde_json::json!({
+                "symbol": position.symbol,
+                "qty": position.qty,
+                "current_price": current_price,
+                "market_value": position.market_value,
+                "unrealized_pl": position.unrealized_pl,
+                "unrealized_plpc": position.unrealized_plpc,
+            }));
+        }
+        
+        let summary = serde_json::json!({
+            "total_value": account.portfolio_value,
+            "cash": account.cash,
+            "buying_power": account.buying_power,
+            "positions": portfolio_positions,
+        });
+        
+        info!("  Portfolio summary generated");
+        Ok(summary)
+    }
+}
+
@@ -0,0 +1,315 @@
+use crate::config::TradingConfig;
+use crate::storage::StateStore;
+use crate::trading::broker::Broker;
+use crate::trading::order::{OrderAmount, OrderRequest};
+use crate::utils::TradingLogger;
+use chrono::{DateTime, Utc};
+use log::{info, error, warn};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Persisted breaker state
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BreakerState {
+    /// Highest equity seen since the breaker was last armed
+    pub high_water_mark: Decimal,
+    pub tripped: bool,
+    pub reason: Option<String>,
+    pub tripped_at: Option<DateTime<Utc>>,
+}
+
+/// Halts all trading until an operator re-arms it.
+///
+/// It trips when equity falls `MAX_DRAWDOWN_PCT` below its high-water mark,
+/// in which case open orders are cancelled and both symbols are sold, or
+/// when the executor gives up on a failed position flip. With a state file
+/// the high-water mark and the tripped state survive restarts, and the file
+/// is re-read before every check so `rearm` takes effect in a running bot.
+/// With a `StateStore` the orders that flatten positions are recorded.
+pub struct CircuitBreaker {
+    config: TradingConfig,
+    broker: Arc<dyn Broker>,
+    logger: TradingLogger,
+    state: Mutex<BreakerState>,
+    state_file: Option<PathBuf>,
+    store: Option<Arc<StateStore>>,
+}
+
+impl CircuitBreaker {
+    pub fn new(config: TradingConfig, broker: Arc<dyn Broker>) -> Self {
+        let logger = TradingLogger::new(config.clone());
+        Self {
+            config,
+            broker,
+            logger,
+            state: Mutex::new(BreakerState::default()),
+            state_file: None,
+            store: None,
+        }
+    }
+    
+    /// Persist the high-water mark and tripped state in `path`
+    pub fn with_state_file(mut self, path: &Path) -> Self {
+        self.state_file = Some(path.to_path_buf());
+        self.reload();
+        if let Some(reason) = self.tripped() {
+            warn!("Circuit breaker is tripped ({}); run `rearm` to resume trading", reason);
+        }
+        self
+    }
+    
+    /// Record the orders that flatten positions in `store`
+    pub fn with_store(mut self, store: Arc<StateStore>) -> Self {
+        self.store = Some(store);
+        self
+    }
+    
+    /// Reason trading is halted, if it is
+    pub fn tripped(&self) -> Option<String> {
+        let state = self.state.lock().unwrap();
+        state.tripped.then(|| state.reason.clone().unwrap_or_default())
+    }
+    
+    /// Update the high-water mark and trip (flattening both symbols) on a
+    /// deep enough drawdown. Returns the reason trading is halted, if it is.
+    pub async fn check(&self, check_id: &str) -> Option<String> {
+        self.reload();
+        if let Some(reason) = self.tripped() {
+            return Some(reason);
+        }
+        let max_drawdown = self.config.max_drawdown_pct;
+        if max_drawdown <= Decimal::ZERO {
+            return None;
+        }
+        
+        let equity = match self.broker.get_account_info().await {
+            Ok(account) => account.equity,
+            Err(e) => {
+                warn!("  Circuit breaker could not read equity: {}", e);
+                return None;
+            }
+        };
+        let high_water_mark = {
+            let mut state = self.state.lock().unwrap();
+            if equity > state.high_water_mark {
+                state.high_water_mark = equity;
+                self.save(&state);
+            }
+            state.high_water_mark
+        };
+        // No drawdown to measure until the account has held positive equity
+        if high_water_mark <= Decimal::ZERO {
+            return None;
+        }
+        
+        let drawdown = (high_water_mark - equity) / high_water_mark * Decimal::ONE_HUNDRED;
+        if drawdown < max_drawdown {
+            return None;
+        }
+        
+        let reason = format!(
+            "equity ${:.2} is {:.2}% below its high-water mark ${:.2} (limit {}%)",
+            equity, drawdown, high_water_mark, max_drawdown
+        );
+        error!("  CIRCUIT BREAKER TRIPPED: {}", reason);
+        self.trip(&reason);
+        let unclosed = self.flatten(check_id).await;
+        self.logger.log_incident(&serde_json::json!({
+            "cycle_id": check_id,
+            "kind": "circuit_breaker",
+            "reason": reason,
+            "equity": equity,
+            "high_water_mark": high_water_mark,
+            "unclosed_symbols": unclosed,
+        }));
+        Some(reason)
+    }
+    
+    /// Halt trading without touching positions
+    pub fn trip(&self, reason: &str) {
+        let mut state = self.state.lock().unwrap();
+        state.tripped = true;
+        state.reason = Some(reason.to_string());
+        state.tripped_at = Some(Utc::now());
+        self.save(&state);
+    }
+    
+    /// Cancel open orders and sell both symbols. Returns the symbols that
+    /// could not be closed.
+    async fn flatten(&self, check_id: &str) -> Vec<String> {
+        info!("  Circuit breaker: cancelling open orders...");
+        match self.broker.get_open_orders().await {
+            Ok(orders) => {
+                for order in orders {
+                    if let Err(e) = self.broker.cancel_order(&order.order_id).await {
+                        error!("  Failed to cancel order {}: {}", order.order_id, e);
+                    }
+                }
+            }
+            Err(e) => error!("  Could not list open orders: {}", e),
+        }
+        
+        let mut unclosed = Vec::new();
+        for symbol in [&self.config.symbol, &self.config.inverse_symbol] {
+            let qty = match self.broker.get_current_position(symbol).await {
+                Ok(Some(position)) if position.qty > Decimal::ZERO => position.qty,
+                Ok(_) => continue,
+                Err(e) => {
+                    error!("  Could not read {} position: {}", symbol, e);
+                    unclosed.push(symbol.to_string());
+                    continue;
+                }
+            };
+            info!("  Circuit breaker: selling {} {}", qty, symbol);
+            let client_order_id = format!("hoc-{}-breaker-{}", check_id, symbol).to_lowercase();
+            let request = OrderRequest::new("sell", OrderAmount::Qty(qty), symbol, &client_order_id);
+            match self.broker.place_order(&request).await {
+                Ok(result) => {
+                    info!("  Breaker sell {} {} ({})", qty, symbol, result.status);
+                    if let Some(store) = &self.store {
+                        store.record_order(check_id, &result);
+                    }
+                }
+                Err(e) => {
+                    error!("  Failed to sell {}: {}", symbol, e);
+                    unclosed.push(symbol.to_string());
+                }
+            }
+        }
+        unclosed
+    }
+    
+    /// Clear a tripped breaker in the state file at `path`. The high-water
+    /// mark restarts from the equity at the next check.
+    pub fn rearm(path: &Path) -> std::io::Result<Option<BreakerState>> {
+        let Ok(text) = std::fs::read_to_string(path) else {
+            return Ok(None);
+        };
+        let previous: BreakerState = serde_json::from_str(&text).map_err(std::io::Error::other)?;
+        let json = serde_json::to_string_pretty(&BreakerState::default()).map_err(std::io::Error::other)?;
+        std::fs::write(path, json)?;
+        Ok(Some(previous))
+    }
+    
+    fn reload(&self) {
+        let Some(path) = &self.state_file else {
+            return;
+        };
+        let Ok(text) = std::fs::read_to_string(path) else {
+            return;
+        };
+        match serde_json::from_str(&text) {
+            Ok(state) => *self.state.lock().unwrap() = state,
+            Err(e) => warn!("Ignoring unreadable circuit breaker state {}: {}", path.display(), e),
+        }
+    }
+    
+    fn save(&self, state: &BreakerState) {
+        let Some(path) = &self.state_file else {
+            return;
+        };
+        let result = serde_json::to_string_pretty(state)
+            .map_err(std::io::Error::other)
+            .and_then(|json| std::fs::write(path, json));
+        if let Err(e) = result {
+            error!("Failed to save circuit breaker state to {}: {}", path.display(), e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trading::simulated_broker::SimulatedBroker;
+    use rust_decimal_macros::dec;
+    
+    fn config(max_drawdown_pct: Decimal) -> TradingConfig {
+        TradingConfig {
+            symbol: "BOIL".to_string(),
+            inverse_symbol: "KOLD".to_string(),
+            max_drawdown_pct,
+            ..TradingConfig::default()
+        }
+    }
+    
+    /// $5,000 cash and 250 BOIL at $20
+    async fn broker() -> Arc<SimulatedBroker> {
+        let broker = SimulatedBroker::new(dec!(10000), Decimal::ZERO, Decimal::ZERO);
+        broker.set_price("BOIL", dec!(20));
+        broker.set_price("KOLD", dec!(50));
+        let request = OrderRequest::new("buy", OrderAmount::Qty(dec!(250)), "BOIL", "test-buy");
+        broker.place_order(&request).await.unwrap();
+        Arc::new(broker)
+    }
+    
+    fn state_file(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("algotrade-breaker-{}-{}.json", std::process::id(), name));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+    
+    #[tokio::test]
+    async fn disabled_without_a_drawdown_limit() {
+        let broker = broker().await;
+        let breaker = CircuitBreaker::new(config(Decimal::ZERO), broker.clone());
+        assert_eq!(breaker.check("c1").await, None);
+        broker.set_price("BOIL", dec!(1));
+        assert_eq!(breaker.check("c2").await, None);
+    }
+    
+    #[tokio::test]
+    async fn trips_and_flattens_on_drawdown() {
+        let broker = broker().await;
+        let breaker = CircuitBreaker::new(config(dec!(10)), broker.clone());
+        assert_eq!(breaker.check("c1").await, None);
+        
+        // $10,000 -> $9,250 is 7.5%
+        broker.set_price("BOIL", dec!(17));
+        assert_eq!(breaker.check("c2").await, None);
+        // $10,000 -> $8,750 is 12.5%
+        broker.set_price("BOIL", dec!(15));
+        let reason = breaker.check("c3").await.expect("breaker should trip");
+        assert!(reason.contains("12.50%"), "{}", reason);
+        assert_eq!(breaker.tripped(), Some(reason));
+        assert!(broker.get_positions().await.unwrap().is_empty());
+        
+        // Stays tripped after a recovery
+        broker.set_price("BOIL", dec!(30));
+        assert!(breaker.check("c4").await.is_some());
+    }
+    
+    #[tokio::test]
+    async fn zero_high_water_mark_is_not_a_drawdown() {
+        let broker = Arc::new(SimulatedBroker::new(Decimal::ZERO, Decimal::ZERO, Decimal::ZERO));
+        let breaker = CircuitBreaker::new(config(dec!(5)), broker);
+        assert_eq!(breaker.check("c1").await, None);
+        assert_eq!(breaker.check("c2").await, None);
+        assert_eq!(breaker.tripped(), None);
+    }
+    
+    #[tokio::test]
+    async fn tripped_state_survives_restarts_until_rearmed() {
+        let path = state_file("rearm");
+        let broker = broker().await;
+        
+        let breaker = CircuitBreaker::new(config(dec!(10)), broker.clone()).with_state_file(&path);
+        assert_eq!(breaker.check("c1").await, None);
+        breaker.trip("flip failed");
+        
+        let restarted = CircuitBreaker::new(config(dec!(10)), broker.clone()).with_state_file(&path);
+        assert_eq!(restarted.tripped().as_deref(), Some("flip failed"));
+        
+        let previous = CircuitBreaker::rearm(&path).unwrap().unwrap();
+        assert!(previous.tripped);
+        assert_eq!(previous.high_water_mark, dec!(10000));
+        // The running instance re-reads the file before checking
+        assert_eq!(restarted.check("c2").await, None);
+        assert_eq!(restarted.tripped(), None);
+        
+        std::fs::remove_file(&path).unwrap();
+        assert!(CircuitBreaker::rearm(&path).unwrap().is_none());
+    }
+}
+
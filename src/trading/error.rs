@@ -0,0 +1,136 @@
+use thiserror::Error;
+
+pub type BrokerResult<T> = Result<T, BrokerError>;
+
+/// Failures raised by `Broker` implementations
+#[derive(Debug, Error)]
+pub enum BrokerError {
+    #[error("request timed out: {0}")]
+    Timeout(#[source] reqwest::Error),
+    
+    #[error("network error: {0}")]
+    Network(#[source] reqwest::Error),
+    
+    #[error("rate limited (retry after {retry_after:?}s)")]
+    RateLimited { retry_after: Option<u64> },
+    
+    #[error("authentication failed ({status}): {body}")]
+    Auth { status: u16, body: String },
+    
+    #[error("not found: {0}")]
+    NotFound(String),
+    
+    #[error("insufficient qty available for order: {0}")]
+    InsufficientQuantity(String),
+    
+    #[error("insufficient buying power: {0}")]
+    InsufficientBuyingPower(String),
+    
+    #[error("potential wash trade detected: {0}")]
+    WashTrade(String),
+    
+    #[error("market is closed: {0}")]
+    MarketClosed(String),
+    
+    #[error("order rejected: {0}")]
+    Rejected(String),
+    
+    #[error("client_order_id already in use: {0}")]
+    DuplicateClientOrderId(String),
+    
+    #[error("invalid order: {0}")]
+    InvalidOrder(String),
+    
+    #[error("kill switch engaged: {0}")]
+    KillSwitch(String),
+    
+    #[error("no price available for {0}")]
+    PriceUnavailable(String),
+    
+    #[error("API returned status {status}: {body}")]
+    HttpStatus { status: u16, body: String },
+    
+    #[error("failed to parse response: {0}")]
+    Parse(String),
+}
+
+impl BrokerError {
+    /// True when the request may have reached the broker even though it
+    /// failed, so an order's outcome has to be looked up rather than assumed
+    pub fn is_ambiguous(&self) -> bool {
+        match self {
+            BrokerError::Timeout(_) | BrokerError::Network(_) => true,
+            BrokerError::HttpStatus { status, .. } => *status >= 500,
+            _ => false,
+        }
+    }
+    
+    /// Consume an unsuccessful response and classify it
+    pub async fn from_response(response: reqwest::Response) -> Self {
+        let status = response.status();
+        let retry_after = response
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.trim().parse().ok());
+        let body = response.text().await.unwrap_or_default();
+        Self::from_status(status, retry_after, body)
+    }
+    
+    /// Classify an unsuccessful brokerage response.
+    ///
+    /// Alpaca reports most order problems as 403/422 with a JSON `message`,
+    /// so this is the one place that inspects response text.
+    pub fn from_status(status: reqwest::StatusCode, retry_after: Option<u64>, body: String) -> Self {
+        let message = serde_json::from_str::<serde_json::Value>(&body)
+            .ok()
+            .and_then(|v| v.get("message").and_then(|m| m.as_str()).map(|m| m.to_string()))
+            .unwrap_or_else(|| body.chars().take(200).collect());
+        let lower = message.to_lowercase();
+        
+        if lower.contains("wash trade") {
+            BrokerError::WashTrade(message)
+        } else if lower.contains("insufficient qty") || lower.contains("insufficient quantity") {
+            BrokerError::InsufficientQuantity(message)
+        } else if lower.contains("insufficient buying power") {
+            BrokerError::InsufficientBuyingPower(message)
+        } else if lower.contains("client_order_id must be unique") {
+            BrokerError::DuplicateClientOrderId(message)
+        } else if lower.contains("market is closed") || lower.contains("market closed") {
+            BrokerError::MarketClosed(message)
+        } else {
+            match status.as_u16() {
+                401 | 403 => BrokerError::Auth { status: status.as_u16(), body: message },
+                404 => BrokerError::NotFound(message),
+                422 => BrokerError::Rejected(message),
+                429 => BrokerError::RateLimited { retry_after },
+                code => BrokerError::HttpStatus { status: code, body: message },
+            }
+        }
+    }
+}
+
+impl From<reqwest::Error> for BrokerError {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_timeout() {
+            BrokerError::Timeout(e)
+        } else if e.is_decode() {
+            BrokerError::Parse(e.to_string())
+        } else {
+            BrokerError::Network(e)
+        }
+    }
+}
+
+impl From<serde_json::Error> for BrokerError {
+    fn from(e: serde_json::Error) -> Self {
+        BrokerError::Parse(e.to_string())
+    }
+}
+
+impl From<rust_decimal::Error> for BrokerError {
+    fn from(e: rust_decimal::Error) -> Self {
+        BrokerError::Parse(e.to_string())
+    }
+}
+
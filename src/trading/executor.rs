@@ -0,0 +1,938 @@
+use crate::config::{ExecutionPolicy, TradingConfig};
+use crate::signals::TradingSignal;
+use crate::storage::StateStore;
+use crate::trading::broker::{Broker, Quote, TradeResult};
+use crate::trading::circuit_breaker::CircuitBreaker;
+use crate::trading::error::{BrokerError, BrokerResult};
+use crate::trading::order::{OrderAmount, OrderRequest, OrderStatus, OrderType};
+use crate::trading::risk::RiskEngine;
+use crate::trading::sizing::{self, SizingInputs, SizingPolicy};
+use crate::utils::TradingLogger;
+use log::{info, error, warn};
+use rust_decimal::prelude::{FromPrimitive, ToPrimitive};
+use rust_decimal::{Decimal, RoundingStrategy};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Decimal places of fractional share quantities brokers accept
+const QTY_DECIMALS: u32 = 9;
+
+/// Smallest notional order Alpaca accepts
+const MIN_NOTIONAL: Decimal = Decimal::ONE;
+
+/// One order of a position flip
+struct Leg<'a> {
+    name: &'static str,
+    side: &'static str,
+    symbol: &'a str,
+    amount: OrderAmount,
+}
+
+/// A leg that could not be completed, even after any retries
+struct LegFailure {
+    leg: &'static str,
+    side: &'static str,
+    symbol: String,
+    requested: OrderAmount,
+    filled_qty: Decimal,
+    error: String,
+}
+
+/// Turns trading signals into orders against whichever `Broker` is configured.
+///
+/// Only the difference between the held and the target position is traded,
+/// and not at all while an existing position is within `REBALANCE_BAND_PCT`
+/// of the target. A flip (e.g. KOLD -> BOIL) is treated as a transaction: the
+/// closing leg must fill before the opening leg is sent, and a failed leg is handled by
+/// `FLIP_FAILURE_POLICY` (retry, rollback or halt) and recorded in
+/// `logs/incidents.log`, so the bot never ends up holding both symbols.
+/// Every trade must pass the `RiskEngine` before its first order is sent;
+/// retries and rollbacks of an approved trade are not checked again. Halting
+/// trips the `CircuitBreaker`, which also watches for drawdowns. With a
+/// `StateStore` every order sent, including reprices and rollbacks, is recorded.
+pub struct TradeExecutor {
+    config: TradingConfig,
+    broker: Arc<dyn Broker>,
+    logger: TradingLogger,
+    sizer: Box<dyn SizingPolicy>,
+    risk: RiskEngine,
+    breaker: Arc<CircuitBreaker>,
+    store: Option<Arc<StateStore>>,
+}
+
+impl TradeExecutor {
+    pub fn new(config: TradingConfig, broker: Arc<dyn Broker>) -> Self {
+        let logger = TradingLogger::new(config.clone());
+        let sizer = sizing::from_config(&config);
+        let risk = RiskEngine::new(config.clone(), broker.clone());
+        let breaker = Arc::new(CircuitBreaker::new(config.clone(), broker.clone()));
+        Self {
+            config,
+            broker,
+            logger,
+            sizer,
+            risk,
+            breaker,
+            store: None,
+        }
+    }
+    
+    /// Replace the default in-memory risk engine, e.g. with one that persists its counters
+    pub fn with_risk(mut self, risk: RiskEngine) -> Self {
+        self.risk = risk;
+        self
+    }
+    
+    /// Share a circuit breaker (e.g. one persisted to disk) instead of the default in-memory one
+    pub fn with_breaker(mut self, breaker: Arc<CircuitBreaker>) -> Self {
+        self.breaker = breaker;
+        self
+    }
+    
+    /// Record every order in `store`
+    pub fn with_store(mut self, store: Arc<StateStore>) -> Self {
+        self.store = Some(store);
+        self
+    }
+    
+    /// Deterministic `client_order_id` for one leg of a cycle's trade, so a
+    /// resubmission of the same leg can never produce a second order
+    fn client_order_id(cycle_id: &str, signal: &TradingSignal, leg: &str, symbol: &str) -> String {
+        format!("hoc-{}-{}{}-{}-{}", cycle_id, signal.action, signal.symbol, leg, symbol).to_lowercase()
+    }
+    
+    pub async fn execute_trade(&self, signal: &TradingSignal, cycle_id: &str) -> Option<TradeResult> {
+        info!("");
+        info!(">>> EXECUTING TRADE <<<");
+        info!("  Cycle ID: {}", cycle_id);
+        info!("  Signal action: {}", signal.action);
+        info!("  Signal symbol: {}", signal.symbol);
+        info!("  Signal confidence: {:.2}", signal.confidence);
+        info!("  Total signal strength: {:.4}", signal.total_signal);
+        
+        if let Some(reason) = self.breaker.check(cycle_id).await {
+            error!("  Trading halted by circuit breaker: {} (see logs/incidents.log); run `rearm` to resume", reason);
+            info!(">>> TRADE EXECUTION SKIPPED - HALTED <<<");
+            return None;
+        }
+        
+        // Simple strategy: mutual exclusivity
+        // If buying BOIL, sell all KOLD first and vice versa
+        
+        if signal.action != "BUY" {
+            info!("  Signal indicates {}, no trade executed", signal.action);
+            info!(">>> TRADE EXECUTION SKIPPED <<<");
+            return None;
+        }
+        
+        let (target, opposite) = if signal.symbol == self.config.symbol {
+            info!("  Strategy: Buying {} (bullish natural gas)", self.config.symbol);
+            (self.config.symbol.as_str(), self.config.inverse_symbol.as_str())
+        } else if signal.symbol == self.config.inverse_symbol {
+            info!("  Strategy: Buying {} (bearish natural gas)", self.config.inverse_symbol);
+            (self.config.inverse_symbol.as_str(), self.config.symbol.as_str())
+        } else {
+            warn!("  Unsupported symbol: {}", signal.symbol);
+            warn!("  Expected {} or {}", self.config.symbol, self.config.inverse_symbol);
+            info!(">>> TRADE EXECUTION SKIPPED - UNSUPPORTED SYMBOL <<<");
+            return None;
+        };
+        
+        info!("  Checking current positions...");
+        // Without both positions a flip could buy the target while the opposite is still held
+        let (target_qty, opposite_qty) = match (self.held_qty(target).await, self.held_qty(opposite).await) {
+            (Ok(target_qty), Ok(opposite_qty)) => (target_qty, opposite_qty),
+            (Err(e), _) | (_, Err(e)) => {
+                error!("  Could not read positions: {}", e);
+                info!(">>> TRADE EXECUTION SKIPPED - POSITIONS UNAVAILABLE <<<");
+                return None;
+            }
+        };
+        info!("  Current {} position: {} shares", target, target_qty);
+        info!("  Current {} position: {} shares", opposite, opposite_qty);
+        let before = [(target, target_qty), (opposite, opposite_qty)];
+        
+        // Size the target position up front so nothing is traded if the price is unavailable
+        info!("  Fetching current {} price...", target);
+        let price = match self.broker.get_current_price(target).await {
+            Ok(price) => price,
+            Err(e) => {
+                error!("  Could not get current price for {}: {}", target, e);
+                info!(">>> TRADE EXECUTION SKIPPED - NO PRICE <<<");
+                return None;
+            }
+        };
+        info!("  Current {} price: ${:.2}", target, price);
+        let Some(notional) = self.target_notional(target, signal).await else {
+            info!(">>> TRADE EXECUTION SKIPPED - NO POSITION SIZE <<<");
+            return None;
+        };
+        if price <= Decimal::ZERO {
+            error!("  Invalid {} price: {}", target, price);
+            info!(">>> TRADE EXECUTION SKIPPED - NO PRICE <<<");
+            return None;
+        }
+        let desired_qty = self.shares_for(notional, price);
+        if desired_qty.is_zero() {
+            warn!("  ${} buys less than the smallest tradable quantity of {}", notional, target);
+            info!(">>> TRADE EXECUTION SKIPPED - NO POSITION SIZE <<<");
+            return None;
+        }
+        let delta = desired_qty - target_qty;
+        info!("  Position size: ${:.2} ({} sizing)", notional, self.sizer.name());
+        info!("  Target position: {} shares (delta {:+})", desired_qty, delta);
+        
+        // Plan both legs first so the risk checks see the whole trade
+        let flatten = (opposite_qty > Decimal::ZERO).then_some(Leg {
+            name: "flatten",
+            side: "sell",
+            symbol: opposite,
+            amount: OrderAmount::Qty(opposite_qty),
+        });
+        
+        // Trade only the difference to the target, unless an existing
+        // position is already within the rebalance band
+        let drift_pct = (delta.abs() / desired_qty).to_f64().unwrap_or(0.0) * 100.0;
+        let rebalance = if delta.is_zero() || (target_qty > Decimal::ZERO && drift_pct < self.config.rebalance_band_pct) {
+            info!("  {} position within rebalance band ({:.1}% < {:.1}%), keeping {} shares",
+                  target, drift_pct, self.config.rebalance_band_pct, target_qty);
+            None
+        } else if delta > Decimal::ZERO {
+            // Notional mode buys a dollar amount and lets the broker work out the shares
+            let amount = if self.config.order_qty_mode == "notional" {
+                OrderAmount::Notional((delta * price).round_dp_with_strategy(2, RoundingStrategy::ToZero))
+            } else {
+                OrderAmount::Qty(delta)
+            };
+            let name = if target_qty > Decimal::ZERO { "add" } else { "open" };
+            Some(Leg { name, side: "buy", symbol: target, amount })
+        } else {
+            Some(Leg { name: "trim", side: "sell", symbol: target, amount: OrderAmount::Qty(-delta) })
+        };
+        
+        let orders: Vec<OrderRequest> = flatten
+            .iter()
+            .chain(rebalance.iter())
+            .map(|leg| OrderRequest::new(leg.side, leg.amount, leg.symbol, &Self::client_order_id(cycle_id, signal, leg.name, leg.symbol)))
+            .collect();
+        if orders.is_empty() {
+            info!(">>> TRADE EXECUTION COMPLETE - NO REBALANCE NEEDED <<<");
+            return None;
+        }
+        info!("  Running pre-trade risk checks...");
+        if let Err(violation) = self.risk.approve(&orders, signal.timestamp).await {
+            error!("  Trade blocked by risk check: {}", violation);
+            self.logger.log_risk_violation(&serde_json::json!({
+                "cycle_id": cycle_id,
+                "signal_symbol": signal.symbol,
+                "rule": violation.rule,
+                "reason": violation.reason,
+                "orders": orders.iter().map(|o| format!("{} {} {}", o.side, o.amount, o.symbol)).collect::<Vec<_>>(),
+            }));
+            info!(">>> TRADE EXECUTION BLOCKED - RISK LIMIT <<<");
+            return None;
+        }
+        
+        // Leg 1: flatten the opposite side and wait for the fill
+        if let Some(leg) = &flatten {
+            info!("  Mutual exclusivity: Selling all {} before buying {}", opposite, target);
+            if let Err(failure) = self.complete_leg(leg, signal, cycle_id).await {
+                return self.abort_flip(failure, &before, signal, cycle_id).await;
+            }
+            info!("  Successfully sold {} position", opposite);
+        } else {
+            info!("  No existing {} position", opposite);
+        }
+        
+        // Leg 2: move the target position to its sized quantity
+        let Some(leg) = rebalance else {
+            info!(">>> TRADE EXECUTION COMPLETE - NO REBALANCE NEEDED <<<");
+            return None;
+        };
+        info!("  Placing order to {} {} of {}...", leg.side, leg.amount, target);
+        
+        match self.complete_leg(&leg, signal, cycle_id).await {
+            Ok(result) => {
+                info!("  Order placed successfully: {:?}", result);
+                info!(">>> TRADE EXECUTION COMPLETE <<<");
+                Some(result)
+            }
+            Err(failure) => self.abort_flip(failure, &before, signal, cycle_id).await,
+        }
+    }
+    
+    /// Dollars to hold in `symbol` according to the configured sizing policy
+    async fn target_notional(&self, symbol: &str, signal: &TradingSignal) -> Option<Decimal> {
+        let mut inputs = SizingInputs {
+            confidence: Decimal::from_f64(signal.confidence).unwrap_or_default(),
+            ..Default::default()
+        };
+        if self.sizer.needs_equity() {
+            match self.broker.get_account_info().await {
+                Ok(account) => inputs.equity = Some(account.equity),
+                Err(e) => error!("  Could not read account equity for sizing: {}", e),
+            }
+        }
+        if self.sizer.needs_volatility() {
+            match self.broker.get_daily_closes(symbol, self.config.vol_lookback_days + 1).await {
+                Ok(closes) => inputs.volatility = sizing::realized_volatility(&closes),
+                Err(e) => error!("  Could not read {} price history for sizing: {}", symbol, e),
+            }
+            match inputs.volatility {
+                Some(volatility) => info!("  Realized {} volatility: {:.1}% annualized", symbol, volatility * 100.0),
+                None => warn!("  Not enough {} price history to measure volatility", symbol),
+            }
+        }
+        
+        let notional = self.sizer.target_notional(&inputs).map(|n| n.round_dp(2));
+        match notional {
+            Some(notional) if notional > Decimal::ZERO => Some(notional),
+            Some(_) => {
+                warn!("  {} sizing gave no position for {}", self.sizer.name(), symbol);
+                None
+            }
+            None => {
+                warn!("  {} sizing could not size {}: missing inputs", self.sizer.name(), symbol);
+                None
+            }
+        }
+    }
+    
+    /// Shares `notional` buys at `price`: whole shares unless fractional or
+    /// notional orders are enabled. Less than one whole share buys none.
+    fn shares_for(&self, notional: Decimal, price: Decimal) -> Decimal {
+        let shares = notional / price;
+        if self.config.order_qty_mode == "whole" {
+            shares.floor()
+        } else {
+            shares.round_dp_with_strategy(QTY_DECIMALS, RoundingStrategy::ToZero).normalize()
+        }
+    }
+    
+    /// Shares of `symbol` held; a broker that reports no such position holds none
+    async fn held_qty(&self, symbol: &str) -> BrokerResult<Decimal> {
+        match self.broker.get_current_position(symbol).await {
+            Ok(Some(position)) if position.qty > Decimal::ZERO => Ok(position.qty),
+            Ok(_) | Err(BrokerError::NotFound(_)) => Ok(Decimal::ZERO),
+            Err(e) => {
+                warn!("  Could not read {} position: {}", symbol, e);
+                Err(e)
+            }
+        }
+    }
+    
+    /// Place a leg and wait for it to fill completely, retrying the unfilled
+    /// remainder when the policy is `retry`
+    async fn complete_leg(&self, leg: &Leg<'_>, signal: &TradingSignal, cycle_id: &str) -> Result<TradeResult, LegFailure> {
+        let attempts = if self.config.flip_failure_policy == "retry" { 1 + self.config.flip_max_retries } else { 1 };
+        let mut remaining = leg.amount;
+        let mut filled_qty = Decimal::ZERO;
+        let mut last_error = String::new();
+        
+        for attempt in 0..attempts {
+            let leg_id = if attempt == 0 { leg.name.to_string() } else { format!("{}-r{}", leg.name, attempt) };
+            if attempt > 0 {
+                warn!("  Retrying {} leg ({} {} {}), attempt {}/{}", leg.name, leg.side, remaining, leg.symbol, attempt + 1, attempts);
+            }
+            let client_order_id = Self::client_order_id(cycle_id, signal, &leg_id, leg.symbol);
+            
+            let request = OrderRequest::new(leg.side, remaining, leg.symbol, &client_order_id);
+            match self.place_with_policy(request, cycle_id).await {
+                Ok(result) if result.status == OrderStatus::Filled => return Ok(result),
+                Ok(result) => {
+                    // Stop whatever is still working so the position is known before deciding what to do
+                    if !result.status.is_terminal() {
+                        if let Err(e) = self.broker.cancel_order(&result.order_id).await {
+                            warn!("  Failed to cancel unfinished order {}: {}", result.order_id, e);
+                        }
+                    }
+                    let filled = result.filled_qty.unwrap_or_default();
+                    filled_qty += filled;
+                    remaining = Self::remaining_after(remaining, &result);
+                    last_error = format!("order {} ended {} with {} shares filled, {} left", result.order_id, result.status, filled, remaining);
+                    error!("  {} leg incomplete: {}", leg.name, last_error);
+                    if Self::is_complete(remaining) {
+                        return Ok(result);
+                    }
+                }
+                Err(e) => {
+                    last_error = e.to_string();
+                    error!("  {} leg failed ({} {} {}): {}", leg.name, leg.side, remaining, leg.symbol, e);
+                }
+            }
+        }
+        
+        Err(LegFailure {
+            leg: leg.name,
+            side: leg.side,
+            symbol: leg.symbol.to_string(),
+            requested: leg.amount,
+            filled_qty,
+            error: last_error,
+        })
+    }
+    
+    /// Send `request` as the symbol's execution policy says. Limit and stop
+    /// orders are priced off the latest quote; while unfilled after
+    /// `REPRICE_AFTER_SECS` they are cancelled and resent at a fresh price up
+    /// to `MAX_REPRICES` times, then the rest goes out at market if
+    /// `FALLBACK_TO_MARKET` is set. Fills of every attempt are combined.
+    async fn place_with_policy(&self, request: OrderRequest, cycle_id: &str) -> BrokerResult<TradeResult> {
+        let policy = self.config.execution_for(&request.symbol);
+        if policy.order_type == "market" {
+            let result = self.broker.place_order(&request).await?;
+            self.record_order(cycle_id, &result);
+            return Ok(result);
+        }
+        
+        let mut remaining = request.amount;
+        let mut parts = Vec::new();
+        for attempt in 0..=policy.max_reprices {
+            let quote = match self.broker.get_latest_quote(&request.symbol).await {
+                Ok(quote) => quote,
+                Err(e) => {
+                    warn!("  No {} quote to price the order: {}", request.symbol, e);
+                    break;
+                }
+            };
+            let order_type = Self::priced_order_type(policy, &request.side, &quote);
+            let amount = match (remaining, order_type) {
+                // Only market orders take dollar amounts
+                (OrderAmount::Notional(notional), OrderType::Limit { limit_price: price } | OrderType::Stop { stop_price: price }) => {
+                    OrderAmount::Qty((notional / price).round_dp_with_strategy(QTY_DECIMALS, RoundingStrategy::ToZero))
+                }
+                (amount, _) => amount,
+            };
+            let client_order_id = if attempt == 0 {
+                request.client_order_id.clone()
+            } else {
+                format!("{}-p{}", request.client_order_id, attempt)
+            };
+            info!("  {} {} of {} as {} (bid ${}, ask ${}), attempt {}/{}",
+                  request.side, amount, request.symbol, order_type, quote.bid, quote.ask, attempt + 1, policy.max_reprices + 1);
+            
+            let priced = OrderRequest::new(&request.side, amount, &request.symbol, &client_order_id)
+                .with_type(order_type)
+                .with_fill_timeout(Duration::from_secs(policy.reprice_after_secs));
+            let mut result = match self.broker.place_order(&priced).await {
+                Ok(result) => result,
+                Err(e) if !parts.is_empty() => return Ok(Self::partial_after_error(&request, parts, remaining, e)),
+                Err(e) => return Err(e),
+            };
+            if !result.status.is_terminal() {
+                if let Err(e) = self.broker.cancel_order(&result.order_id).await {
+                    warn!("  Failed to cancel unfilled order {}: {}", result.order_id, e);
+                }
+                // A fill can race the cancel, so read back what actually filled
+                match self.broker.get_order(&result.order_id).await {
+                    Ok(latest) => result = latest,
+                    Err(e) => warn!("  Could not re-read order {}: {}", result.order_id, e),
+                }
+            }
+            self.record_order(cycle_id, &result);
+            remaining = Self::remaining_after(remaining, &result);
+            let status = result.status;
+            parts.push(result);
+            if status == OrderStatus::Filled || Self::is_complete(remaining) {
+                return Ok(Self::combine_fills(&request, parts, remaining));
+            }
+            if status == OrderStatus::Rejected {
+                warn!("  {} order rejected, not repricing", order_type);
+                break;
+            }
+            info!("  {} still unfilled after {}s", remaining, policy.reprice_after_secs);
+        }
+        
+        if policy.fallback_to_market {
+            warn!("  Falling back to a market order for the remaining {} of {}", remaining, request.symbol);
+            let market = OrderRequest::new(&request.side, remaining, &request.symbol, &format!("{}-mkt", request.client_order_id));
+            let result = match self.broker.place_order(&market).await {
+                Ok(result) => result,
+                Err(e) if !parts.is_empty() => return Ok(Self::partial_after_error(&request, parts, remaining, e)),
+                Err(e) => return Err(e),
+            };
+            self.record_order(cycle_id, &result);
+            remaining = Self::remaining_after(remaining, &result);
+            parts.push(result);
+        }
+        if parts.is_empty() {
+            return Err(BrokerError::PriceUnavailable(request.symbol.clone()));
+        }
+        Ok(Self::combine_fills(&request, parts, remaining))
+    }
+    
+    fn record_order(&self, cycle_id: &str, order: &TradeResult) {
+        if let Some(store) = &self.store {
+            store.record_order(cycle_id, order);
+        }
+    }
+    
+    /// Limit or stop order for one attempt, priced off `quote`: a `limit` joins
+    /// the near side of the book, a `marketable_limit` or `stop` sits
+    /// `MAX_SLIPPAGE_BPS` beyond the far side
+    fn priced_order_type(policy: &ExecutionPolicy, side: &str, quote: &Quote) -> OrderType {
+        let offset = policy.max_slippage_bps / Decimal::from(10_000);
+        let (near, beyond_far) = if side == "buy" {
+            (quote.bid, quote.ask * (Decimal::ONE + offset))
+        } else {
+            (quote.ask, quote.bid * (Decimal::ONE - offset))
+        };
+        match policy.order_type.as_str() {
+            "limit" => OrderType::Limit { limit_price: OrderType::round_price(near) },
+            "marketable_limit" => OrderType::Limit { limit_price: OrderType::round_price(beyond_far) },
+            "stop" => OrderType::Stop { stop_price: OrderType::round_price(beyond_far) },
+            _ => OrderType::Market,
+        }
+    }
+    
+    /// One result for an order that was sent in several attempts: the last
+    /// attempt's IDs and status with the fills of all of them
+    fn combine_fills(request: &OrderRequest, mut parts: Vec<TradeResult>, remaining: OrderAmount) -> TradeResult {
+        if parts.len() == 1 {
+            return parts.remove(0);
+        }
+        let filled: Decimal = parts.iter().filter_map(|p| p.filled_qty).sum();
+        let cost: Decimal = parts.iter().map(|p| p.filled_qty.unwrap_or_default() * p.filled_avg_price.unwrap_or_default()).sum();
+        let last = parts.pop().unwrap();
+        TradeResult {
+            qty: match request.amount {
+                OrderAmount::Qty(qty) => qty,
+                OrderAmount::Notional(_) => Decimal::ZERO,
+            },
+            notional: match request.amount {
+                OrderAmount::Qty(_) => None,
+                OrderAmount::Notional(notional) => Some(notional),
+            },
+            status: if Self::is_complete(remaining) { OrderStatus::Filled } else { last.status },
+            filled_qty: Some(filled),
+            filled_avg_price: (!filled.is_zero()).then(|| (cost / filled).round_dp(6)),
+            ..last
+        }
+    }
+    
+    /// Earlier attempts already filled part of the order, so a failed later
+    /// attempt must not be reported as an error: the caller would then send
+    /// the whole amount again
+    fn partial_after_error(request: &OrderRequest, parts: Vec<TradeResult>, remaining: OrderAmount, error: BrokerError) -> TradeResult {
+        error!("  Order for the remaining {} of {} failed: {}", remaining, request.symbol, error);
+        Self::combine_fills(request, parts, remaining)
+    }
+    
+    /// What is left of `amount` after `result`'s fills
+    fn remaining_after(amount: OrderAmount, result: &TradeResult) -> OrderAmount {
+        let filled = result.filled_qty.unwrap_or_default();
+        match amount {
+            OrderAmount::Qty(qty) => OrderAmount::Qty(qty - filled),
+            OrderAmount::Notional(notional) => {
+                OrderAmount::Notional((notional - filled * result.filled_avg_price.unwrap_or_default()).round_dp(2))
+            }
+        }
+    }
+    
+    /// Nothing (or less than the broker's minimum notional) left to trade
+    fn is_complete(remaining: OrderAmount) -> bool {
+        match remaining {
+            OrderAmount::Qty(qty) => qty <= Decimal::ZERO,
+            OrderAmount::Notional(notional) => notional < MIN_NOTIONAL,
+        }
+    }
+    
+    /// Apply the failure policy to a flip that could not complete and record the incident
+    async fn abort_flip(
+        &self,
+        failure: LegFailure,
+        before: &[(&str, Decimal)],
+        signal: &TradingSignal,
+        cycle_id: &str,
+    ) -> Option<TradeResult> {
+        let policy = self.config.flip_failure_policy.as_str();
+        error!("  Position flip failed at {} leg: {}", failure.leg, failure.error);
+        
+        let (outcome, halt) = match policy {
+            "rollback" => {
+                if self.rollback(before, signal, cycle_id).await {
+                    ("rolled back to pre-trade positions", false)
+                } else {
+                    ("rollback failed, trading halted", true)
+                }
+            }
+            "retry" => ("retries exhausted, trading halted", true),
+            _ => ("trading halted", true),
+        };
+        error!("  Flip failure policy '{}': {}", policy, outcome);
+        if halt {
+            self.breaker.trip(&format!("position flip failed at {} leg in cycle {}: {}", failure.leg, cycle_id, outcome));
+        }
+        
+        self.logger.log_incident(&serde_json::json!({
+            "cycle_id": cycle_id,
+            "kind": "flip_failed",
+            "signal_symbol": signal.symbol,
+            "leg": failure.leg,
+            "side": failure.side,
+            "symbol": failure.symbol,
+            "requested": failure.requested,
+            "filled_qty": failure.filled_qty,
+            "error": failure.error,
+            "policy": policy,
+            "outcome": outcome,
+            "positions_before": before.iter().map(|(s, q)| (s.to_string(), *q)).collect::<std::collections::HashMap<_, _>>(),
+        }));
+        info!(">>> TRADE EXECUTION FAILED <<<");
+        None
+    }
+    
+    /// Trade each symbol back to its pre-flip quantity. Returns false if any
+    /// correction could not be completed.
+    async fn rollback(&self, before: &[(&str, Decimal)], signal: &TradingSignal, cycle_id: &str) -> bool {
+        warn!("  Rolling back position flip...");
+        let mut ok = true;
+        for (symbol, original) in before {
+            let current = match self.held_qty(symbol).await {
+                Ok(current) => current,
+                Err(e) => {
+                    error!("  Rollback of {} skipped, position unknown: {}", symbol, e);
+                    ok = false;
+                    continue;
+                }
+            };
+            let (side, qty) = match original - current {
+                diff if diff.is_zero() => continue,
+                diff if diff > Decimal::ZERO => ("buy", diff),
+                diff => ("sell", -diff),
+            };
+            info!("  Rollback: {} {} {} (held {}, originally {})", side, qty, symbol, current, original);
+            let leg = Leg { name: "rollback", side, symbol, amount: OrderAmount::Qty(qty) };
+            if let Err(failure) = self.complete_leg(&leg, signal, cycle_id).await {
+                error!("  Rollback of {} failed: {}", symbol, failure.error);
+                ok = false;
+            }
+        }
+        ok
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trading::broker::{AccountInfo, Position};
+    use crate::trading::simulated_broker::SimulatedBroker;
+    use async_trait::async_trait;
+    use chrono::Utc;
+    use rust_decimal_macros::dec;
+    use std::collections::{BTreeMap, HashSet};
+    use std::sync::Mutex;
+    
+    /// Simulated broker whose position reads and orders can be made to fail
+    struct FlakyBroker {
+        inner: SimulatedBroker,
+        /// Symbols whose position cannot be read
+        unreadable: Mutex<HashSet<String>>,
+        /// "side symbol" pairs whose orders are refused
+        refused: Mutex<HashSet<String>>,
+        /// Most shares one market order fills; the rest is cancelled
+        fill_cap: Mutex<Option<Decimal>>,
+        /// Price the symbol moves to right after an order in it comes to rest
+        move_on_rest: Mutex<Option<Decimal>>,
+        /// Client order ID and type of every order sent
+        placed: Mutex<Vec<(String, OrderType)>>,
+    }
+    
+    impl FlakyBroker {
+        fn new() -> Arc<Self> {
+            Self::with_slippage(Decimal::ZERO)
+        }
+        
+        fn with_slippage(slippage_bps: Decimal) -> Arc<Self> {
+            let inner = SimulatedBroker::new(dec!(10000), Decimal::ZERO, slippage_bps);
+            inner.set_price("BOIL", dec!(20));
+            inner.set_price("KOLD", dec!(50));
+            Arc::new(Self {
+                inner,
+                unreadable: Mutex::new(HashSet::new()),
+                refused: Mutex::new(HashSet::new()),
+                fill_cap: Mutex::new(None),
+                move_on_rest: Mutex::new(None),
+                placed: Mutex::new(Vec::new()),
+            })
+        }
+        
+        async fn held(&self, symbol: &str) -> Decimal {
+            self.inner.get_current_position(symbol).await.unwrap().map(|p| p.qty).unwrap_or_default()
+        }
+    }
+    
+    #[async_trait]
+    impl Broker for FlakyBroker {
+        fn name(&self) -> &str {
+            "Flaky"
+        }
+        
+        async fn get_account_info(&self) -> BrokerResult<AccountInfo> {
+            self.inner.get_account_info().await
+        }
+        
+        async fn get_current_position(&self, symbol: &str) -> BrokerResult<Option<Position>> {
+            if self.unreadable.lock().unwrap().contains(symbol) {
+                return Err(BrokerError::HttpStatus { status: 503, body: "unavailable".to_string() });
+            }
+            self.inner.get_current_position(symbol).await
+        }
+        
+        async fn get_positions(&self) -> BrokerResult<Vec<Position>> {
+            self.inner.get_positions().await
+        }
+        
+        async fn get_current_price(&self, symbol: &str) -> BrokerResult<Decimal> {
+            self.inner.get_current_price(symbol).await
+        }
+        
+        async fn get_daily_closes(&self, symbol: &str, days: usize) -> BrokerResult<Vec<Decimal>> {
+            self.inner.get_daily_closes(symbol, days).await
+        }
+        
+        async fn get_latest_quote(&self, symbol: &str) -> BrokerResult<Quote> {
+            self.inner.get_latest_quote(symbol).await
+        }
+        
+        async fn place_order(&self, order: &OrderRequest) -> BrokerResult<TradeResult> {
+            if self.refused.lock().unwrap().contains(&format!("{} {}", order.side, order.symbol)) {
+                return Err(BrokerError::Rejected(format!("{} {} refused", order.side, order.symbol)));
+            }
+            self.placed.lock().unwrap().push((order.client_order_id.clone(), order.order_type));
+            let cap = *self.fill_cap.lock().unwrap();
+            let result = match (cap, order.amount) {
+                (Some(cap), OrderAmount::Qty(qty)) if order.order_type == OrderType::Market && qty > cap => {
+                    let mut partial = order.clone();
+                    partial.amount = OrderAmount::Qty(cap);
+                    let result = self.inner.place_order(&partial).await?;
+                    return Ok(TradeResult { qty, status: OrderStatus::Canceled, ..result });
+                }
+                _ => self.inner.place_order(order).await?,
+            };
+            if !result.status.is_terminal() {
+                if let Some(price) = *self.move_on_rest.lock().unwrap() {
+                    self.inner.set_price(&order.symbol, price);
+                }
+            }
+            Ok(result)
+        }
+        
+        async fn get_open_orders(&self) -> BrokerResult<Vec<TradeResult>> {
+            self.inner.get_open_orders().await
+        }
+        
+        async fn get_order(&self, order_id: &str) -> BrokerResult<TradeResult> {
+            self.inner.get_order(order_id).await
+        }
+        
+        async fn cancel_order(&self, order_id: &str) -> BrokerResult<()> {
+            self.inner.cancel_order(order_id).await
+        }
+    }
+    
+    /// $1,000 whole-share market orders with every risk limit off
+    fn config(name: &str) -> TradingConfig {
+        let kill_switch = std::env::temp_dir().join(format!("algotrade-executor-{}-{}", std::process::id(), name));
+        TradingConfig {
+            symbol: "BOIL".to_string(),
+            inverse_symbol: "KOLD".to_string(),
+            position_size: dec!(1000),
+            position_sizing: "fixed".to_string(),
+            order_qty_mode: "whole".to_string(),
+            rebalance_band_pct: 10.0,
+            max_order_notional: Decimal::ZERO,
+            max_gross_exposure: Decimal::ZERO,
+            max_exposure_pct_equity: Decimal::ZERO,
+            max_daily_trades: 0,
+            daily_loss_limit_pct: Decimal::ZERO,
+            weekly_loss_limit_pct: Decimal::ZERO,
+            max_drawdown_pct: Decimal::ZERO,
+            kill_switch_file: kill_switch.display().to_string(),
+            flip_failure_policy: "rollback".to_string(),
+            execution: ExecutionPolicy::default(),
+            symbol_execution: Default::default(),
+            ..TradingConfig::default()
+        }
+    }
+    
+    fn buy(symbol: &str) -> TradingSignal {
+        TradingSignal {
+            timestamp: Utc::now(),
+            components: BTreeMap::new(),
+            total_signal: 0.5,
+            action: "BUY".to_string(),
+            symbol: symbol.to_string(),
+            confidence: 1.0,
+            blocked_reason: None,
+        }
+    }
+    
+    #[tokio::test]
+    async fn flip_sells_the_opposite_leg_before_buying() {
+        let broker = FlakyBroker::new();
+        let executor = TradeExecutor::new(config("flip"), broker.clone());
+        
+        let opened = executor.execute_trade(&buy("KOLD"), "c1").await.unwrap();
+        assert_eq!(opened.filled_qty, Some(dec!(20)));
+        
+        let flipped = executor.execute_trade(&buy("BOIL"), "c2").await.unwrap();
+        assert_eq!((flipped.symbol.as_str(), flipped.side.as_str()), ("BOIL", "buy"));
+        assert_eq!(broker.held("BOIL").await, dec!(50));
+        assert_eq!(broker.held("KOLD").await, Decimal::ZERO);
+        
+        // The same signal again is within the rebalance band
+        assert!(executor.execute_trade(&buy("BOIL"), "c3").await.is_none());
+        assert_eq!(broker.held("BOIL").await, dec!(50));
+    }
+    
+    #[tokio::test]
+    async fn failed_second_leg_rolls_back_the_first() {
+        let broker = FlakyBroker::new();
+        let executor = TradeExecutor::new(config("rollback"), broker.clone());
+        executor.execute_trade(&buy("KOLD"), "c1").await.unwrap();
+        
+        broker.refused.lock().unwrap().insert("buy BOIL".to_string());
+        assert!(executor.execute_trade(&buy("BOIL"), "c2").await.is_none());
+        // KOLD was sold, BOIL refused, so KOLD is bought back
+        assert_eq!(broker.held("KOLD").await, dec!(20));
+        assert_eq!(broker.held("BOIL").await, Decimal::ZERO);
+        assert!(executor.breaker.tripped().is_none());
+        
+        // When the rollback fails too, trading halts
+        broker.refused.lock().unwrap().insert("buy KOLD".to_string());
+        assert!(executor.execute_trade(&buy("BOIL"), "c3").await.is_none());
+        assert_eq!(broker.held("KOLD").await, Decimal::ZERO);
+        assert!(executor.breaker.tripped().is_some());
+        broker.refused.lock().unwrap().clear();
+        assert!(executor.execute_trade(&buy("BOIL"), "c4").await.is_none());
+        assert_eq!(broker.held("BOIL").await, Decimal::ZERO);
+    }
+    
+    #[tokio::test]
+    async fn partial_fill_is_retried_for_the_remainder() {
+        let retry = |retries, name| TradingConfig { flip_failure_policy: "retry".to_string(), flip_max_retries: retries, ..config(name) };
+        let broker = FlakyBroker::new();
+        let executor = TradeExecutor::new(retry(2, "partial"), broker.clone());
+        *broker.fill_cap.lock().unwrap() = Some(dec!(20));
+        
+        // 50 shares fill as 20 + 20 + 10 over the first attempt and two retries
+        let result = executor.execute_trade(&buy("BOIL"), "c1").await.unwrap();
+        assert_eq!(result.filled_qty, Some(dec!(10)));
+        assert_eq!(broker.held("BOIL").await, dec!(50));
+        
+        // With retries exhausted the partial position is kept and trading halts
+        let broker = FlakyBroker::new();
+        let executor = TradeExecutor::new(retry(1, "partial-halt"), broker.clone());
+        *broker.fill_cap.lock().unwrap() = Some(dec!(5));
+        assert!(executor.execute_trade(&buy("KOLD"), "c1").await.is_none());
+        assert_eq!(broker.held("KOLD").await, dec!(10));
+        assert!(executor.breaker.tripped().is_some());
+    }
+    
+    /// Client order ID suffix and order type of every order sent
+    fn placed(broker: &FlakyBroker) -> Vec<(String, OrderType)> {
+        broker
+            .placed
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(id, order_type)| (id.rsplit('-').next().unwrap().to_string(), *order_type))
+            .collect()
+    }
+    
+    #[tokio::test]
+    async fn unfilled_limit_is_repriced_then_sent_at_market() {
+        let execution = ExecutionPolicy {
+            order_type: "limit".to_string(),
+            max_reprices: 1,
+            reprice_after_secs: 0,
+            fallback_to_market: true,
+            ..ExecutionPolicy::default()
+        };
+        let config = TradingConfig { execution, ..config("reprice") };
+        // Quoted 19.98 / 20.02, so a buy joining the bid rests
+        let broker = FlakyBroker::with_slippage(dec!(10));
+        let executor = TradeExecutor::new(config, broker.clone());
+        
+        let result = executor.execute_trade(&buy("BOIL"), "c1").await.unwrap();
+        let bid = OrderType::Limit { limit_price: dec!(19.98) };
+        assert_eq!(placed(&broker), [
+            ("boil".to_string(), bid),
+            ("p1".to_string(), bid),
+            ("mkt".to_string(), OrderType::Market),
+        ]);
+        assert_eq!(result.status, OrderStatus::Filled);
+        assert_eq!(result.filled_avg_price, Some(dec!(20.02)));
+        assert_eq!(broker.held("BOIL").await, result.filled_qty.unwrap());
+        assert!(broker.inner.get_open_orders().await.unwrap().is_empty());
+    }
+    
+    #[tokio::test]
+    async fn stop_fills_when_the_price_trades_through_it() {
+        let execution = ExecutionPolicy {
+            order_type: "stop".to_string(),
+            max_reprices: 0,
+            fallback_to_market: true,
+            ..ExecutionPolicy::default()
+        };
+        let config = TradingConfig { execution, ..config("stop") };
+        let broker = FlakyBroker::new();
+        let executor = TradeExecutor::new(config, broker.clone());
+        
+        // The buy stop rests 20 bps above 20, then BOIL trades up through it
+        *broker.move_on_rest.lock().unwrap() = Some(dec!(20.5));
+        let result = executor.execute_trade(&buy("BOIL"), "c1").await.unwrap();
+        assert_eq!(placed(&broker), [("boil".to_string(), OrderType::Stop { stop_price: dec!(20.04) })]);
+        assert_eq!(result.status, OrderStatus::Filled);
+        assert_eq!(result.filled_avg_price, Some(dec!(20.5)));
+        assert_eq!(broker.held("BOIL").await, dec!(50));
+    }
+    
+    /// Sizing that always asks for a fixed dollar amount, however small
+    struct FixedNotional(Decimal);
+    
+    impl SizingPolicy for FixedNotional {
+        fn name(&self) -> &str {
+            "fixed_notional"
+        }
+        
+        fn target_notional(&self, _inputs: &SizingInputs) -> Option<Decimal> {
+            Some(self.0)
+        }
+    }
+    
+    #[tokio::test]
+    async fn less_than_one_share_places_no_orders() {
+        let broker = FlakyBroker::new();
+        let mut executor = TradeExecutor::new(config("tiny"), broker.clone());
+        
+        // No edge: the sizing policy wants no position at all
+        executor.sizer = Box::new(FixedNotional(Decimal::ZERO));
+        assert!(executor.execute_trade(&buy("BOIL"), "c1").await.is_none());
+        
+        // $15 does not buy one $20 share
+        executor.sizer = Box::new(FixedNotional(dec!(15)));
+        assert!(executor.execute_trade(&buy("BOIL"), "c2").await.is_none());
+        assert!(broker.placed.lock().unwrap().is_empty());
+        assert_eq!(broker.held("BOIL").await, Decimal::ZERO);
+        
+        executor.sizer = Box::new(FixedNotional(dec!(45)));
+        executor.execute_trade(&buy("BOIL"), "c3").await.unwrap();
+        assert_eq!(broker.held("BOIL").await, dec!(2));
+    }
+    
+    #[tokio::test]
+    async fn unreadable_position_places_no_orders() {
+        let broker = FlakyBroker::new();
+        let executor = TradeExecutor::new(config("unreadable"), broker.clone());
+        executor.execute_trade(&buy("KOLD"), "c1").await.unwrap();
+        
+        broker.unreadable.lock().unwrap().insert("KOLD".to_string());
+        assert!(executor.execute_trade(&buy("BOIL"), "c2").await.is_none());
+        assert_eq!(broker.held("KOLD").await, dec!(20));
+        assert_eq!(broker.held("BOIL").await, Decimal::ZERO);
+        assert!(broker.inner.get_open_orders().await.unwrap().is_empty());
+    }
+}
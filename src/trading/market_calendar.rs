@@ -0,0 +1,232 @@
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveTime, TimeZone, Utc, Weekday};
+use chrono_tz::America::New_York;
+use chrono_tz::Tz;
+
+/// Exchange time zone; all session times are defined in Eastern Time
+pub const MARKET_TZ: Tz = New_York;
+
+/// One regular trading session
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Session {
+    pub date: NaiveDate,
+    pub open: DateTime<Utc>,
+    pub close: DateTime<Utc>,
+}
+
+impl Session {
+    /// Build a session from Eastern Time wall clock times
+    pub fn from_local(date: NaiveDate, open: NaiveTime, close: NaiveTime) -> Option<Self> {
+        Some(Self {
+            date,
+            open: to_utc(date, open)?,
+            close: to_utc(date, close)?,
+        })
+    }
+    
+    pub fn contains(&self, time: DateTime<Utc>) -> bool {
+        self.open <= time && time < self.close
+    }
+}
+
+/// Convert an Eastern Time wall clock time to UTC. Session times never fall
+/// in a DST gap, so an ambiguous or missing local time is not expected.
+pub fn to_utc(date: NaiveDate, time: NaiveTime) -> Option<DateTime<Utc>> {
+    MARKET_TZ
+        .from_local_datetime(&date.and_time(time))
+        .earliest()
+        .map(|t| t.with_timezone(&Utc))
+}
+
+/// Regular NYSE session for `date` from the built-in holiday rules, used when
+/// Alpaca's calendar cannot be reached. `None` on weekends and holidays.
+pub fn local_session(date: NaiveDate) -> Option<Session> {
+    if is_weekend(date) || is_holiday(date) {
+        return None;
+    }
+    let open = NaiveTime::from_hms_opt(9, 30, 0)?;
+    let close = if is_early_close(date) {
+        NaiveTime::from_hms_opt(13, 0, 0)?
+    } else {
+        NaiveTime::from_hms_opt(16, 0, 0)?
+    };
+    Session::from_local(date, open, close)
+}
+
+fn is_weekend(date: NaiveDate) -> bool {
+    matches!(date.weekday(), Weekday::Sat | Weekday::Sun)
+}
+
+/// NYSE full-day closures
+pub fn is_holiday(date: NaiveDate) -> bool {
+    let year = date.year();
+    let mut holidays = vec![
+        nth_weekday(year, 1, Weekday::Mon, 3),  // Martin Luther King Jr. Day
+        nth_weekday(year, 2, Weekday::Mon, 3),  // Washington's Birthday
+        easter(year) - Duration::days(2),       // Good Friday
+        last_weekday(year, 5, Weekday::Mon),    // Memorial Day
+        observed(ymd(year, 7, 4)),              // Independence Day
+        nth_weekday(year, 9, Weekday::Mon, 1),  // Labor Day
+        nth_weekday(year, 11, Weekday::Thu, 4), // Thanksgiving
+        observed(ymd(year, 12, 25)),            // Christmas
+    ];
+    if year >= 2022 {
+        holidays.push(observed(ymd(year, 6, 19))); // Juneteenth
+    }
+    // New Year's Day falling on a Saturday is not observed on the preceding Friday
+    let new_year = ymd(year, 1, 1);
+    if new_year.weekday() != Weekday::Sat {
+        holidays.push(observed(new_year));
+    }
+    holidays.contains(&date)
+}
+
+/// US federal holidays, which move government releases such as the EIA storage report
+pub fn is_federal_holiday(date: NaiveDate) -> bool {
+    let year = date.year();
+    let mut holidays = vec![
+        observed(ymd(year, 1, 1)),              // New Year's Day
+        nth_weekday(year, 1, Weekday::Mon, 3),  // Martin Luther King Jr. Day
+        nth_weekday(year, 2, Weekday::Mon, 3),  // Washington's Birthday
+        last_weekday(year, 5, Weekday::Mon),    // Memorial Day
+        observed(ymd(year, 7, 4)),              // Independence Day
+        nth_weekday(year, 9, Weekday::Mon, 1),  // Labor Day
+        nth_weekday(year, 10, Weekday::Mon, 2), // Columbus Day
+        observed(ymd(year, 11, 11)),            // Veterans Day
+        nth_weekday(year, 11, Weekday::Thu, 4), // Thanksgiving
+        observed(ymd(year, 12, 25)),            // Christmas
+    ];
+    if year >= 2021 {
+        holidays.push(observed(ymd(year, 6, 19))); // Juneteenth
+    }
+    // Next year's New Year's Day observed on Friday, December 31
+    holidays.push(observed(ymd(year + 1, 1, 1)));
+    holidays.contains(&date)
+}
+
+/// Days the NYSE closes at 13:00 ET
+fn is_early_close(date: NaiveDate) -> bool {
+    let year = date.year();
+    let day_after_thanksgiving = nth_weekday(year, 11, Weekday::Thu, 4) + Duration::days(1);
+    date == day_after_thanksgiving
+        || date == ymd(year, 12, 24)
+        || (date == ymd(year, 7, 3) && !is_holiday(date))
+}
+
+fn ymd(year: i32, month: u32, day: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(year, month, day).expect("valid calendar date")
+}
+
+/// Weekend holidays move to the adjacent weekday
+fn observed(date: NaiveDate) -> NaiveDate {
+    match date.weekday() {
+        Weekday::Sat => date - Duration::days(1),
+        Weekday::Sun => date + Duration::days(1),
+        _ => date,
+    }
+}
+
+/// `n`-th (1-based) `weekday` of a month
+pub fn nth_weekday(year: i32, month: u32, weekday: Weekday, n: u32) -> NaiveDate {
+    NaiveDate::from_weekday_of_month_opt(year, month, weekday, n as u8).expect("valid weekday of month")
+}
+
+fn last_weekday(year: i32, month: u32, weekday: Weekday) -> NaiveDate {
+    let next_month = if month == 12 { ymd(year + 1, 1, 1) } else { ymd(year, month + 1, 1) };
+    let mut date = next_month - Duration::days(1);
+    while date.weekday() != weekday {
+        date -= Duration::days(1);
+    }
+    date
+}
+
+/// Gregorian Easter Sunday (anonymous Gregorian algorithm)
+fn easter(year: i32) -> NaiveDate {
+    let a = year % 19;
+    let b = year / 100;
+    let c = year % 100;
+    let d = b / 4;
+    let e = b % 4;
+    let f = (b + 8) / 25;
+    let g = (b - f + 1) / 3;
+    let h = (19 * a + b - d - g + 15) % 30;
+    let i = c / 4;
+    let k = c % 4;
+    let l = (32 + 2 * e + 2 * i - h - k) % 7;
+    let m = (a + 11 * h + 22 * l) / 451;
+    let month = (h + l - 7 * m + 114) / 31;
+    let day = (h + l - 7 * m + 114) % 31 + 1;
+    ymd(year, month as u32, day as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn utc(text: &str) -> DateTime<Utc> {
+        text.parse().unwrap()
+    }
+    
+    #[test]
+    fn nyse_holidays_2024() {
+        for (month, day) in [(1, 1), (1, 15), (2, 19), (3, 29), (5, 27), (6, 19), (7, 4), (9, 2), (11, 28), (12, 25)] {
+            assert!(is_holiday(ymd(2024, month, day)), "2024-{:02}-{:02} should be a holiday", month, day);
+        }
+        // Columbus and Veterans Day are federal holidays but trading days
+        assert!(!is_holiday(ymd(2024, 10, 14)));
+        assert!(!is_holiday(ymd(2024, 11, 11)));
+    }
+    
+    #[test]
+    fn weekend_holidays_are_observed() {
+        // Independence Day 2021 and Christmas 2022 fell on Sundays
+        assert!(is_holiday(ymd(2021, 7, 5)));
+        assert!(is_holiday(ymd(2022, 12, 26)));
+        // Independence Day 2026 falls on a Saturday
+        assert!(is_holiday(ymd(2026, 7, 3)));
+        // Juneteenth 2022 fell on a Sunday and was first observed that year
+        assert!(is_holiday(ymd(2022, 6, 20)));
+        assert!(!is_holiday(ymd(2021, 6, 18)));
+        // New Year's Day 2022 was a Saturday; the market stayed open the Friday before
+        assert!(!is_holiday(ymd(2021, 12, 31)));
+    }
+    
+    #[test]
+    fn good_friday_follows_easter() {
+        assert_eq!(easter(2024), ymd(2024, 3, 31));
+        assert_eq!(easter(2025), ymd(2025, 4, 20));
+        assert!(is_holiday(ymd(2025, 4, 18)));
+    }
+    
+    #[test]
+    fn sessions_in_utc_across_dst() {
+        let winter = local_session(ymd(2024, 1, 2)).unwrap();
+        assert_eq!(winter.open, utc("2024-01-02T14:30:00Z"));
+        assert_eq!(winter.close, utc("2024-01-02T21:00:00Z"));
+        
+        let summer = local_session(ymd(2024, 3, 11)).unwrap();
+        assert_eq!(summer.open, utc("2024-03-11T13:30:00Z"));
+        assert!(summer.contains(utc("2024-03-11T19:59:00Z")));
+        assert!(!summer.contains(utc("2024-03-11T20:00:00Z")));
+    }
+    
+    #[test]
+    fn early_closes_and_closed_days() {
+        assert_eq!(local_session(ymd(2024, 11, 29)).unwrap().close, utc("2024-11-29T18:00:00Z"));
+        assert_eq!(local_session(ymd(2024, 12, 24)).unwrap().close, utc("2024-12-24T18:00:00Z"));
+        assert_eq!(local_session(ymd(2024, 7, 3)).unwrap().close, utc("2024-07-03T17:00:00Z"));
+        assert!(local_session(ymd(2024, 7, 4)).is_none());
+        assert!(local_session(ymd(2024, 10, 26)).is_none()); // Saturday
+    }
+    
+    #[test]
+    fn federal_holidays_include_columbus_and_veterans_day() {
+        assert!(is_federal_holiday(ymd(2024, 10, 14)));
+        assert!(is_federal_holiday(ymd(2024, 11, 11)));
+        // Veterans Day 2023 was a Saturday
+        assert!(is_federal_holiday(ymd(2023, 11, 10)));
+        // New Year's Day 2022 observed on Friday, December 31
+        assert!(is_federal_holiday(ymd(2021, 12, 31)));
+        assert!(!is_federal_holiday(ymd(2024, 3, 29)));
+    }
+}
+
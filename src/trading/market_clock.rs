@@ -0,0 +1,196 @@
+use crate::config::TradingConfig;
+use crate::trading::error::{BrokerError, BrokerResult};
+use crate::trading::market_calendar::{self, Session, MARKET_TZ};
+use crate::utils::HttpClient;
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, Utc};
+use log::{info, warn};
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+struct AlpacaClock {
+    is_open: bool,
+    next_open: DateTime<Utc>,
+    next_close: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AlpacaCalendarDay {
+    date: NaiveDate,
+    open: String,
+    close: String,
+}
+
+/// Whether the market is open right now and when that changes
+#[derive(Debug, Clone)]
+pub struct ClockStatus {
+    pub is_open: bool,
+    pub next_open: DateTime<Utc>,
+    pub next_close: DateTime<Utc>,
+    /// "alpaca" or "local calendar"
+    pub source: &'static str,
+}
+
+/// Market hours from Alpaca's `/v2/clock` and `/v2/calendar`, falling back to
+/// the built-in NYSE calendar when the API is unavailable or not configured
+/// (e.g. with the simulated broker).
+pub struct MarketClock {
+    config: TradingConfig,
+    client: HttpClient,
+    use_api: bool,
+}
+
+impl MarketClock {
+    pub fn new(config: TradingConfig) -> Self {
+        let client = HttpClient::new(&config);
+        let use_api = config.broker == "alpaca";
+        Self { config, client, use_api }
+    }
+    
+    pub async fn status(&self) -> BrokerResult<ClockStatus> {
+        if self.use_api {
+            match self.fetch_clock().await {
+                Ok(clock) => {
+                    return Ok(ClockStatus {
+                        is_open: clock.is_open,
+                        next_open: clock.next_open,
+                        next_close: clock.next_close,
+                        source: "alpaca",
+                    })
+                }
+                Err(e) => warn!("  Alpaca clock unavailable ({}), using local calendar", e),
+            }
+        }
+        Self::local_status(Utc::now())
+    }
+    
+    /// Whether the market is open now; a clock that cannot be read counts as closed
+    pub async fn is_open(&self) -> bool {
+        match self.status().await {
+            Ok(status) => status.is_open,
+            Err(e) => {
+                warn!("  Market clock unavailable ({}), treating the market as closed", e);
+                false
+            }
+        }
+    }
+    
+    /// Trading sessions from `start` through `end` inclusive
+    pub async fn sessions(&self, start: NaiveDate, end: NaiveDate) -> Vec<Session> {
+        if self.use_api {
+            match self.fetch_calendar(start, end).await {
+                Ok(sessions) => return sessions,
+                Err(e) => warn!("  Alpaca calendar unavailable ({}), using local calendar", e),
+            }
+        }
+        start
+            .iter_days()
+            .take_while(|date| *date <= end)
+            .filter_map(market_calendar::local_session)
+            .collect()
+    }
+    
+    /// Next time at least `now` that is `offset_minutes` after a session open
+    /// and still before that session's close
+    pub async fn next_trade_time(&self, now: DateTime<Utc>, offset_minutes: i64) -> Option<DateTime<Utc>> {
+        let today = now.with_timezone(&MARKET_TZ).date_naive();
+        // Two weeks always contains a session, even around year-end holidays
+        self.sessions(today, today + Duration::days(14))
+            .await
+            .into_iter()
+            .map(|session| (session.open + Duration::minutes(offset_minutes), session.close))
+            .find(|(at, close)| *at >= now && at < close)
+            .map(|(at, _)| at)
+    }
+    
+    fn local_status(now: DateTime<Utc>) -> BrokerResult<ClockStatus> {
+        let today = now.with_timezone(&MARKET_TZ).date_naive();
+        let upcoming: Vec<Session> = today
+            .iter_days()
+            .take(15)
+            .filter_map(market_calendar::local_session)
+            .collect();
+        let current = upcoming.iter().find(|s| s.contains(now));
+        let next = upcoming
+            .iter()
+            .find(|s| s.open > now)
+            .copied()
+            .ok_or_else(|| BrokerError::NotFound(format!("no trading session in the two weeks from {}", today)))?;
+        
+        Ok(ClockStatus {
+            is_open: current.is_some(),
+            next_open: next.open,
+            next_close: current.map(|s| s.close).unwrap_or(next.close),
+            source: "local calendar",
+        })
+    }
+    
+    async fn fetch_clock(&self) -> BrokerResult<AlpacaClock> {
+        let url = format!("{}/v2/clock", self.config.alpaca_base_url);
+        let request = self.client
+            .get(&url)
+            .header("APCA-API-KEY-ID", &self.config.alpaca_api_key)
+            .header("APCA-API-SECRET-KEY", &self.config.alpaca_secret_key);
+        
+        let response = self.client.send(request).await?;
+        if !response.status().is_success() {
+            return Err(BrokerError::from_response(response).await);
+        }
+        Ok(response.json().await?)
+    }
+    
+    async fn fetch_calendar(&self, start: NaiveDate, end: NaiveDate) -> BrokerResult<Vec<Session>> {
+        let url = format!("{}/v2/calendar", self.config.alpaca_base_url);
+        let request = self.client
+            .get(&url)
+            .query(&[("start", start.to_string()), ("end", end.to_string())])
+            .header("APCA-API-KEY-ID", &self.config.alpaca_api_key)
+            .header("APCA-API-SECRET-KEY", &self.config.alpaca_secret_key);
+        
+        let response = self.client.send(request).await?;
+        if !response.status().is_success() {
+            return Err(BrokerError::from_response(response).await);
+        }
+        let days: Vec<AlpacaCalendarDay> = response.json().await?;
+        
+        days.into_iter()
+            .map(|day| {
+                let open = NaiveTime::parse_from_str(&day.open, "%H:%M")
+                    .map_err(|e| BrokerError::Parse(format!("calendar open '{}': {}", day.open, e)))?;
+                let close = NaiveTime::parse_from_str(&day.close, "%H:%M")
+                    .map_err(|e| BrokerError::Parse(format!("calendar close '{}': {}", day.close, e)))?;
+                Session::from_local(day.date, open, close)
+                    .ok_or_else(|| BrokerError::Parse(format!("invalid session time on {}", day.date)))
+            })
+            .collect()
+    }
+}
+
+/// Log the clock state in the same style as the rest of the cycle
+pub fn log_status(status: &ClockStatus) {
+    info!("  Market clock ({}): {}", status.source, if status.is_open { "OPEN" } else { "CLOSED" });
+    info!("    Next open: {}", status.next_open.with_timezone(&MARKET_TZ).format("%Y-%m-%d %H:%M %Z"));
+    info!("    Next close: {}", status.next_close.with_timezone(&MARKET_TZ).format("%Y-%m-%d %H:%M %Z"));
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn utc(text: &str) -> DateTime<Utc> {
+        text.parse().unwrap()
+    }
+    
+    #[test]
+    fn local_status_follows_the_nyse_calendar() {
+        // Wednesday 2024-10-16, 11:00 New York
+        let open = MarketClock::local_status(utc("2024-10-16T15:00:00Z")).unwrap();
+        assert!(open.is_open);
+        assert_eq!(open.next_close, utc("2024-10-16T20:00:00Z"));
+        
+        // Saturday: closed until Monday's open
+        let weekend = MarketClock::local_status(utc("2024-10-19T15:00:00Z")).unwrap();
+        assert!(!weekend.is_open);
+        assert_eq!(weekend.next_open, utc("2024-10-21T13:30:00Z"));
+    }
+}
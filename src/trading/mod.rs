@@ -1,4 +1,20 @@
+pub mod broker;
+pub mod circuit_breaker;
+pub mod error;
 pub mod alpaca_trader;
+pub mod alpaca_stream;
+pub mod executor;
+pub mod market_calendar;
+pub mod market_clock;
+pub mod order;
+pub mod protection;
+pub mod reconcile;
+pub mod risk;
+pub mod simulated_broker;
+pub mod sizing;
 
+pub use broker::Broker;
 pub use alpaca_trader::AlpacaTrader;
+pub use executor::TradeExecutor;
+pub use simulated_broker::SimulatedBroker;
 
@@ -0,0 +1,268 @@
+use crate::config::TradingConfig;
+use crate::trading::alpaca_stream::OrderUpdates;
+use crate::trading::broker::{Broker, TradeResult};
+use log::{info, warn};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Lifecycle state of a submitted order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OrderStatus {
+    New,
+    Accepted,
+    PartiallyFilled,
+    Filled,
+    Canceled,
+    Rejected,
+    Expired,
+}
+
+impl OrderStatus {
+    /// Map an Alpaca order status onto the lifecycle.
+    ///
+    /// Alpaca's intermediate states (pending_new, pending_cancel, held, ...)
+    /// all mean "working, not done yet" and collapse to `Accepted`.
+    pub fn from_alpaca(status: &str) -> Self {
+        match status {
+            "new" => OrderStatus::New,
+            "partially_filled" => OrderStatus::PartiallyFilled,
+            "filled" => OrderStatus::Filled,
+            "canceled" | "replaced" => OrderStatus::Canceled,
+            "rejected" => OrderStatus::Rejected,
+            "expired" => OrderStatus::Expired,
+            _ => OrderStatus::Accepted,
+        }
+    }
+    
+    /// No further fills can happen once an order reaches a terminal state
+    pub fn is_terminal(&self) -> bool {
+        matches!(
+            self,
+            OrderStatus::Filled | OrderStatus::Canceled | OrderStatus::Rejected | OrderStatus::Expired
+        )
+    }
+}
+
+impl std::fmt::Display for OrderStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            OrderStatus::New => "new",
+            OrderStatus::Accepted => "accepted",
+            OrderStatus::PartiallyFilled => "partially_filled",
+            OrderStatus::Filled => "filled",
+            OrderStatus::Canceled => "canceled",
+            OrderStatus::Rejected => "rejected",
+            OrderStatus::Expired => "expired",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Size of a market order: a share count (fractional where the broker
+/// allows it) or a dollar amount the broker converts to shares
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OrderAmount {
+    Qty(Decimal),
+    Notional(Decimal),
+}
+
+impl OrderAmount {
+    /// Shares or dollars, whichever this amount is expressed in
+    pub fn value(&self) -> Decimal {
+        match self {
+            OrderAmount::Qty(value) | OrderAmount::Notional(value) => *value,
+        }
+    }
+}
+
+impl std::fmt::Display for OrderAmount {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OrderAmount::Qty(qty) => write!(f, "{} shares", qty),
+            OrderAmount::Notional(notional) => write!(f, "${:.2}", notional),
+        }
+    }
+}
+
+/// How an order is priced
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum OrderType {
+    #[default]
+    Market,
+    /// Fills at `limit_price` or better
+    Limit { limit_price: Decimal },
+    /// Becomes a market order once the price trades through `stop_price`
+    Stop { stop_price: Decimal },
+}
+
+impl OrderType {
+    /// Round a price to Alpaca's tick size: cents from $1 up, hundredths of a cent below
+    pub fn round_price(price: Decimal) -> Decimal {
+        if price >= Decimal::ONE {
+            price.round_dp(2)
+        } else {
+            price.round_dp(4)
+        }
+    }
+}
+
+impl std::fmt::Display for OrderType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OrderType::Market => write!(f, "market"),
+            OrderType::Limit { limit_price } => write!(f, "limit @ ${}", limit_price),
+            OrderType::Stop { stop_price } => write!(f, "stop @ ${}", stop_price),
+        }
+    }
+}
+
+/// An order to submit. `client_order_id` must be unique per order; a
+/// resubmission with the same ID never creates a second order.
+#[derive(Debug, Clone)]
+pub struct OrderRequest {
+    pub symbol: String,
+    pub side: String,
+    pub amount: OrderAmount,
+    pub client_order_id: String,
+    pub order_type: OrderType,
+    /// How long the broker follows the order before returning it unfinished;
+    /// `ORDER_FILL_TIMEOUT_SECS` when unset
+    pub fill_timeout: Option<Duration>,
+}
+
+impl OrderRequest {
+    /// A market order; see `with_type` for limit and stop orders
+    pub fn new(side: &str, amount: OrderAmount, symbol: &str, client_order_id: &str) -> Self {
+        Self {
+            symbol: symbol.to_string(),
+            side: side.to_string(),
+            amount,
+            client_order_id: client_order_id.to_string(),
+            order_type: OrderType::Market,
+            fill_timeout: None,
+        }
+    }
+    
+    pub fn with_type(mut self, order_type: OrderType) -> Self {
+        self.order_type = order_type;
+        self
+    }
+    
+    pub fn with_fill_timeout(mut self, fill_timeout: Duration) -> Self {
+        self.fill_timeout = Some(fill_timeout);
+        self
+    }
+}
+
+/// Follows a submitted order until it reaches a terminal state or the fill deadline passes
+#[derive(Clone)]
+pub struct OrderTracker {
+    fill_timeout: Duration,
+    poll_interval: Duration,
+    updates: Option<OrderUpdates>,
+}
+
+impl OrderTracker {
+    pub fn new(config: &TradingConfig) -> Self {
+        Self {
+            fill_timeout: Duration::from_secs(config.order_fill_timeout_secs),
+            poll_interval: Duration::from_millis(config.order_poll_interval_ms),
+            updates: None,
+        }
+    }
+    
+    /// Prefer streamed order updates, falling back to polling between events
+    pub fn with_updates(mut self, updates: OrderUpdates) -> Self {
+        self.updates = Some(updates);
+        self
+    }
+    
+    /// Follow `order` until it is terminal, from streamed updates while the
+    /// stream is connected and from `broker` polls otherwise. On timeout the
+    /// latest known state is returned, so callers see e.g. a partial fill
+    /// rather than a stale "new".
+    pub async fn await_terminal(&self, broker: &dyn Broker, order: TradeResult) -> TradeResult {
+        self.await_terminal_within(broker, order, self.fill_timeout).await
+    }
+    
+    /// `await_terminal` with a timeout other than `ORDER_FILL_TIMEOUT_SECS`
+    pub async fn await_terminal_within(&self, broker: &dyn Broker, order: TradeResult, fill_timeout: Duration) -> TradeResult {
+        let deadline = Instant::now() + fill_timeout;
+        let mut current = order;
+        // While the stream is connected and nothing was missed, REST polls add nothing
+        let mut synced = false;
+        if let Some(updates) = &self.updates {
+            updates.watch(&current.order_id);
+            synced = updates.is_connected();
+        }
+        
+        while !current.status.is_terminal() {
+            if Instant::now() >= deadline {
+                warn!(
+                    "  Order {} still {} after {}s (filled {:?} of {})",
+                    current.order_id, current.status, fill_timeout.as_secs(),
+                    current.filled_qty, current.qty
+                );
+                if let Some(updates) = &self.updates {
+                    updates.forget(&current.order_id);
+                }
+                return current;
+            }
+            
+            if let Some(updates) = &self.updates {
+                tokio::select! {
+                    _ = updates.changed() => {}
+                    _ = tokio::time::sleep(self.poll_interval) => {}
+                }
+                if let Some(update) = updates.latest(&current.order_id) {
+                    // Another partial fill can raise the filled quantity without a new status
+                    let filled_changed = update.filled_qty != current.filled_qty
+                        || update.filled_avg_price != current.filled_avg_price;
+                    if update.status != current.status || filled_changed {
+                        if update.status != current.status {
+                            info!("  Order {} status: {} -> {} (streamed)", current.order_id, current.status, update.status);
+                        }
+                        if filled_changed {
+                            info!("  Order {} filled {:?} of {} (streamed)", current.order_id, update.filled_qty, update.qty);
+                        }
+                        current = update;
+                        continue;
+                    }
+                }
+                // Poll once after a reconnect to catch events sent while disconnected
+                let connected = updates.is_connected();
+                if connected && synced {
+                    continue;
+                }
+                synced = connected;
+            } else {
+                tokio::time::sleep(self.poll_interval).await;
+            }
+            
+            match broker.get_order(&current.order_id).await {
+                Ok(update) => {
+                    if update.status != current.status {
+                        info!("  Order {} status: {} -> {}", current.order_id, current.status, update.status);
+                    }
+                    current = update;
+                }
+                Err(e) => warn!("  Failed to poll order {}: {}", current.order_id, e),
+            }
+        }
+        
+        if let Some(updates) = &self.updates {
+            updates.forget(&current.order_id);
+        }
+        info!(
+            "  Order {} {} (filled {:?} @ {:?})",
+            current.order_id, current.status, current.filled_qty, current.filled_avg_price
+        );
+        current
+    }
+}
+
@@ -0,0 +1,371 @@
+use crate::config::TradingConfig;
+use crate::trading::broker::{Broker, TradeResult};
+use crate::trading::order::{OrderAmount, OrderRequest, OrderStatus};
+use log::{info, error, warn};
+use rust_decimal::prelude::FromPrimitive;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// Rule that closed a position outside the regular trading cycle
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExitReason {
+    StopLoss,
+    TrailingStop,
+    TakeProfit,
+}
+
+impl std::fmt::Display for ExitReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let code = match self {
+            ExitReason::StopLoss => "stop_loss",
+            ExitReason::TrailingStop => "trailing_stop",
+            ExitReason::TakeProfit => "take_profit",
+        };
+        f.write_str(code)
+    }
+}
+
+/// A position closed by a protective rule
+#[derive(Debug, Clone, Serialize)]
+pub struct ProtectiveExit {
+    pub reason: ExitReason,
+    pub entry_price: Decimal,
+    pub peak_price: Decimal,
+    pub trigger_price: Decimal,
+    pub trade: TradeResult,
+}
+
+/// Stop-loss, trailing-stop and take-profit protection for open positions.
+///
+/// BOIL and KOLD are 2x leveraged and the trading cycle may only run once a
+/// day, so `check` is called between cycles: it compares each position's
+/// current price with its average entry price and with the highest price seen
+/// while held, and sells the whole position when a rule triggers. Rules are
+/// percentages of the entry (or peak) price; 0 disables a rule. While an exit
+/// order is still working no other exit is sent for that symbol.
+pub struct PositionMonitor {
+    config: TradingConfig,
+    broker: Arc<dyn Broker>,
+    stop_loss: Option<Decimal>,
+    trailing_stop: Option<Decimal>,
+    take_profit: Option<Decimal>,
+    /// Highest price seen per held symbol, for the trailing stop
+    peaks: Mutex<HashMap<String, Decimal>>,
+    /// Exit order per symbol that was still working when last seen
+    pending: Mutex<HashMap<String, String>>,
+}
+
+impl PositionMonitor {
+    pub fn new(config: TradingConfig, broker: Arc<dyn Broker>) -> Self {
+        let fraction = |pct: f64| Decimal::from_f64(pct / 100.0).filter(|f| *f > Decimal::ZERO);
+        Self {
+            stop_loss: fraction(config.stop_loss_pct),
+            trailing_stop: fraction(config.trailing_stop_pct),
+            take_profit: fraction(config.take_profit_pct),
+            config,
+            broker,
+            peaks: Mutex::new(HashMap::new()),
+            pending: Mutex::new(HashMap::new()),
+        }
+    }
+    
+    pub fn is_enabled(&self) -> bool {
+        self.stop_loss.is_some() || self.trailing_stop.is_some() || self.take_profit.is_some()
+    }
+    
+    /// Rule triggered by a position bought at `entry` that has traded as high
+    /// as `peak` and is now at `price`. Losses are checked before gains.
+    pub fn evaluate(&self, entry: Decimal, peak: Decimal, price: Decimal) -> Option<ExitReason> {
+        if let Some(stop) = self.stop_loss {
+            if price <= entry * (Decimal::ONE - stop) {
+                return Some(ExitReason::StopLoss);
+            }
+        }
+        if let Some(trail) = self.trailing_stop {
+            if price <= peak * (Decimal::ONE - trail) {
+                return Some(ExitReason::TrailingStop);
+            }
+        }
+        if let Some(target) = self.take_profit {
+            if price >= entry * (Decimal::ONE + target) {
+                return Some(ExitReason::TakeProfit);
+            }
+        }
+        None
+    }
+    
+    /// Check every traded symbol and close positions whose rule triggered.
+    /// `check_id` makes the exit orders' `client_order_id`s unique.
+    pub async fn check(&self, check_id: &str) -> Vec<ProtectiveExit> {
+        let mut exits = Vec::new();
+        if !self.is_enabled() {
+            return exits;
+        }
+        
+        for symbol in [&self.config.symbol, &self.config.inverse_symbol] {
+            let position = match self.broker.get_current_position(symbol).await {
+                Ok(Some(position)) if position.qty > Decimal::ZERO => position,
+                Ok(_) => {
+                    self.peaks.lock().unwrap().remove(symbol.as_str());
+                    self.pending.lock().unwrap().remove(symbol.as_str());
+                    continue;
+                }
+                Err(e) => {
+                    warn!("  Protection: could not read {} position: {}", symbol, e);
+                    continue;
+                }
+            };
+            
+            let pending = self.pending.lock().unwrap().get(symbol.as_str()).cloned();
+            if let Some(order_id) = pending {
+                match self.broker.get_order(&order_id).await {
+                    Ok(order) if !order.status.is_terminal() => {
+                        info!("  Protection: exit order {} for {} still {}, not sending another", order_id, symbol, order.status);
+                        continue;
+                    }
+                    Ok(_) => {
+                        self.pending.lock().unwrap().remove(symbol.as_str());
+                    }
+                    Err(e) => {
+                        warn!("  Protection: could not read exit order {} for {}: {}", order_id, symbol, e);
+                        continue;
+                    }
+                }
+            }
+            let price = match self.broker.get_current_price(symbol).await {
+                Ok(price) => price,
+                Err(e) => {
+                    warn!("  Protection: could not get {} price: {}", symbol, e);
+                    continue;
+                }
+            };
+            
+            let entry = position.avg_entry_price;
+            let peak = {
+                let mut peaks = self.peaks.lock().unwrap();
+                let peak = peaks.entry(symbol.to_string()).or_insert(entry);
+                *peak = (*peak).max(price);
+                *peak
+            };
+            
+            let Some(reason) = self.evaluate(entry, peak, price) else {
+                continue;
+            };
+            
+            warn!(
+                "  Protection: {} triggered for {} (entry ${:.2}, peak ${:.2}, now ${:.2}), selling {} shares",
+                reason, symbol, entry, peak, price, position.qty
+            );
+            let client_order_id = format!("hoc-{}-exit-{}-{}", check_id, reason, symbol).to_lowercase();
+            let request = OrderRequest::new("sell", OrderAmount::Qty(position.qty), symbol, &client_order_id);
+            match self.broker.place_order(&request).await {
+                Ok(trade) => {
+                    info!("  Protective exit order {} {}", trade.order_id, trade.status);
+                    if trade.status == OrderStatus::Filled {
+                        self.peaks.lock().unwrap().remove(symbol.as_str());
+                    } else if !trade.status.is_terminal() {
+                        self.pending.lock().unwrap().insert(symbol.to_string(), trade.order_id.clone());
+                    }
+                    exits.push(ProtectiveExit {
+                        reason,
+                        entry_price: entry,
+                        peak_price: peak,
+                        trigger_price: price,
+                        trade,
+                    });
+                }
+                Err(e) => error!("  Protective exit for {} failed: {}", symbol, e),
+            }
+        }
+        exits
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trading::broker::{AccountInfo, Position, Quote};
+    use crate::trading::error::BrokerResult;
+    use crate::trading::simulated_broker::SimulatedBroker;
+    use async_trait::async_trait;
+    use rust_decimal_macros::dec;
+    
+    /// Simulated broker whose sells are accepted but never fill
+    struct RestingSells {
+        inner: Arc<SimulatedBroker>,
+        sells: Mutex<Vec<TradeResult>>,
+    }
+    
+    #[async_trait]
+    impl Broker for RestingSells {
+        fn name(&self) -> &str {
+            "RestingSells"
+        }
+        
+        async fn get_account_info(&self) -> BrokerResult<AccountInfo> {
+            self.inner.get_account_info().await
+        }
+        
+        async fn get_current_position(&self, symbol: &str) -> BrokerResult<Option<Position>> {
+            self.inner.get_current_position(symbol).await
+        }
+        
+        async fn get_positions(&self) -> BrokerResult<Vec<Position>> {
+            self.inner.get_positions().await
+        }
+        
+        async fn get_current_price(&self, symbol: &str) -> BrokerResult<Decimal> {
+            self.inner.get_current_price(symbol).await
+        }
+        
+        async fn get_daily_closes(&self, symbol: &str, days: usize) -> BrokerResult<Vec<Decimal>> {
+            self.inner.get_daily_closes(symbol, days).await
+        }
+        
+        async fn get_latest_quote(&self, symbol: &str) -> BrokerResult<Quote> {
+            self.inner.get_latest_quote(symbol).await
+        }
+        
+        async fn place_order(&self, order: &OrderRequest) -> BrokerResult<TradeResult> {
+            let mut sells = self.sells.lock().unwrap();
+            let result = TradeResult {
+                order_id: format!("resting-{}", sells.len() + 1),
+                client_order_id: order.client_order_id.clone(),
+                symbol: order.symbol.clone(),
+                qty: order.amount.value(),
+                notional: None,
+                side: order.side.clone(),
+                order_type: order.order_type,
+                status: OrderStatus::Accepted,
+                filled_qty: Some(Decimal::ZERO),
+                filled_avg_price: None,
+                submitted_at: String::new(),
+            };
+            sells.push(result.clone());
+            Ok(result)
+        }
+        
+        async fn get_open_orders(&self) -> BrokerResult<Vec<TradeResult>> {
+            Ok(self.sells.lock().unwrap().iter().filter(|o| !o.status.is_terminal()).cloned().collect())
+        }
+        
+        async fn get_order(&self, order_id: &str) -> BrokerResult<TradeResult> {
+            let sells = self.sells.lock().unwrap();
+            Ok(sells.iter().find(|o| o.order_id == order_id).cloned().unwrap())
+        }
+        
+        async fn cancel_order(&self, order_id: &str) -> BrokerResult<()> {
+            let mut sells = self.sells.lock().unwrap();
+            sells.iter_mut().find(|o| o.order_id == order_id).unwrap().status = OrderStatus::Canceled;
+            Ok(())
+        }
+    }
+    
+    fn config(stop_loss_pct: f64, trailing_stop_pct: f64, take_profit_pct: f64) -> TradingConfig {
+        TradingConfig {
+            symbol: "BOIL".to_string(),
+            inverse_symbol: "KOLD".to_string(),
+            stop_loss_pct,
+            trailing_stop_pct,
+            take_profit_pct,
+            ..TradingConfig::default()
+        }
+    }
+    
+    /// 10 BOIL bought at $100
+    async fn broker() -> Arc<SimulatedBroker> {
+        let broker = SimulatedBroker::new(dec!(10000), Decimal::ZERO, Decimal::ZERO);
+        broker.set_price("BOIL", dec!(100));
+        broker.set_price("KOLD", dec!(50));
+        let request = OrderRequest::new("buy", OrderAmount::Qty(dec!(10)), "BOIL", "test-buy");
+        broker.place_order(&request).await.unwrap();
+        Arc::new(broker)
+    }
+    
+    async fn held(broker: &SimulatedBroker, symbol: &str) -> Decimal {
+        broker.get_current_position(symbol).await.unwrap().map(|p| p.qty).unwrap_or_default()
+    }
+    
+    #[tokio::test]
+    async fn rules_trigger_at_their_thresholds() {
+        let broker = broker().await;
+        let stop_loss = PositionMonitor::new(config(10.0, 0.0, 0.0), broker.clone());
+        assert_eq!(stop_loss.evaluate(dec!(100), dec!(100), dec!(91)), None);
+        assert_eq!(stop_loss.evaluate(dec!(100), dec!(100), dec!(90)), Some(ExitReason::StopLoss));
+        
+        let trailing = PositionMonitor::new(config(0.0, 5.0, 0.0), broker.clone());
+        assert_eq!(trailing.evaluate(dec!(100), dec!(110), dec!(105)), None);
+        assert_eq!(trailing.evaluate(dec!(100), dec!(110), dec!(104.5)), Some(ExitReason::TrailingStop));
+        
+        let take_profit = PositionMonitor::new(config(0.0, 0.0, 20.0), broker);
+        assert_eq!(take_profit.evaluate(dec!(100), dec!(120), dec!(119)), None);
+        assert_eq!(take_profit.evaluate(dec!(100), dec!(120), dec!(120)), Some(ExitReason::TakeProfit));
+    }
+    
+    #[tokio::test]
+    async fn losses_are_checked_before_gains() {
+        let monitor = PositionMonitor::new(config(10.0, 10.0, 20.0), broker().await);
+        // Far below the peak and still above the profit target
+        assert_eq!(monitor.evaluate(dec!(100), dec!(150), dec!(125)), Some(ExitReason::TrailingStop));
+        // Both stops hit: the stop-loss is reported
+        assert_eq!(monitor.evaluate(dec!(100), dec!(100), dec!(80)), Some(ExitReason::StopLoss));
+    }
+    
+    #[tokio::test]
+    async fn disabled_rules_never_trigger() {
+        let broker = broker().await;
+        let monitor = PositionMonitor::new(config(0.0, 0.0, 0.0), broker.clone());
+        assert!(!monitor.is_enabled());
+        assert_eq!(monitor.evaluate(dec!(100), dec!(200), dec!(1)), None);
+        broker.set_price("BOIL", dec!(1));
+        assert!(monitor.check("c1").await.is_empty());
+        assert_eq!(held(&broker, "BOIL").await, dec!(10));
+    }
+    
+    #[tokio::test]
+    async fn trailing_stop_follows_the_peak_and_resets_after_an_exit() {
+        let broker = broker().await;
+        let monitor = PositionMonitor::new(config(0.0, 10.0, 0.0), broker.clone());
+        assert!(monitor.check("c1").await.is_empty());
+        
+        broker.set_price("BOIL", dec!(120));
+        assert!(monitor.check("c2").await.is_empty());
+        // 10% below the $120 peak, though still above the entry
+        broker.set_price("BOIL", dec!(108));
+        let exits = monitor.check("c3").await;
+        assert_eq!(exits.len(), 1);
+        assert_eq!((exits[0].reason, exits[0].peak_price, exits[0].trigger_price), (ExitReason::TrailingStop, dec!(120), dec!(108)));
+        assert_eq!(held(&broker, "BOIL").await, Decimal::ZERO);
+        
+        // A new position starts from its own entry, not the old peak
+        assert!(monitor.check("c4").await.is_empty());
+        let request = OrderRequest::new("buy", OrderAmount::Qty(dec!(10)), "BOIL", "test-rebuy");
+        broker.place_order(&request).await.unwrap();
+        broker.set_price("BOIL", dec!(100));
+        assert!(monitor.check("c5").await.is_empty());
+        assert_eq!(held(&broker, "BOIL").await, dec!(10));
+    }
+    
+    #[tokio::test]
+    async fn working_exit_is_not_sent_again() {
+        let inner = broker().await;
+        let broker = Arc::new(RestingSells { inner: inner.clone(), sells: Mutex::new(Vec::new()) });
+        let monitor = PositionMonitor::new(config(10.0, 0.0, 0.0), broker.clone());
+        inner.set_price("BOIL", dec!(80));
+        
+        assert_eq!(monitor.check("c1").await.len(), 1);
+        assert!(monitor.check("c2").await.is_empty());
+        assert_eq!(broker.sells.lock().unwrap().len(), 1);
+        
+        // Once the working exit is gone, the still-held position is exited again
+        broker.cancel_order("resting-1").await.unwrap();
+        let exits = monitor.check("c3").await;
+        assert_eq!(exits.len(), 1);
+        assert_eq!(exits[0].trade.order_id, "resting-2");
+        assert_eq!(exits[0].trade.qty, dec!(10));
+    }
+}
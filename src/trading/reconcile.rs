@@ -0,0 +1,447 @@
+use crate::config::TradingConfig;
+use crate::storage::StateStore;
+use crate::trading::broker::{Broker, Position, TradeResult};
+use crate::trading::order::{OrderAmount, OrderRequest};
+use crate::utils::TradingLogger;
+use log::{info, error, warn};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// How long a completed check stays current
+const RECHECK_AFTER: Duration = Duration::from_secs(60);
+
+/// One way the broker's account differs from what the bot expects
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Drift {
+    /// Held quantity differs from the bot's recorded fills, e.g. after a manual trade
+    PositionMismatch { symbol: String, expected: Decimal, actual: Decimal },
+    /// Position in a symbol other than `SYMBOL` or `INVERSE_SYMBOL`
+    ForeignSymbol { symbol: String, qty: Decimal },
+    /// `SYMBOL` and `INVERSE_SYMBOL` are held at the same time
+    BothLegsHeld { symbol_qty: Decimal, inverse_qty: Decimal },
+    /// Open order the bot did not place
+    UnknownOrder { order_id: String, symbol: String, side: String },
+}
+
+impl std::fmt::Display for Drift {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Drift::PositionMismatch { symbol, expected, actual } => {
+                write!(f, "{} position is {} shares, expected {}", symbol, actual, expected)
+            }
+            Drift::ForeignSymbol { symbol, qty } => write!(f, "unexpected {} position of {} shares", symbol, qty),
+            Drift::BothLegsHeld { symbol_qty, inverse_qty } => {
+                write!(f, "both legs held ({} and {} shares)", symbol_qty, inverse_qty)
+            }
+            Drift::UnknownOrder { order_id, symbol, side } => {
+                write!(f, "open {} {} order {} was not placed by the bot", side, symbol, order_id)
+            }
+        }
+    }
+}
+
+/// Compares the broker's positions and open orders with the bot's records.
+///
+/// Expected positions come from the `StateStore`; without one only the
+/// checks that need no history run (foreign symbols, both legs held, orders
+/// without the bot's `hoc-` client order ID). Drift is recorded in
+/// `logs/incidents.log` and handled by `RECONCILE_POLICY`. Flattening only
+/// ever trades `SYMBOL` and `INVERSE_SYMBOL`: orders the bot did not place
+/// are cancelled, and both symbols are sold only when their positions drift.
+pub struct Reconciler {
+    config: TradingConfig,
+    broker: Arc<dyn Broker>,
+    logger: TradingLogger,
+    store: Option<Arc<StateStore>>,
+    /// When the last check read the broker's state and whether it could trade
+    last_check: Mutex<Option<(Instant, bool)>>,
+}
+
+impl Reconciler {
+    pub fn new(config: TradingConfig, broker: Arc<dyn Broker>) -> Self {
+        let logger = TradingLogger::new(config.clone());
+        Self {
+            config,
+            broker,
+            logger,
+            store: None,
+            last_check: Mutex::new(None),
+        }
+    }
+    
+    /// Compare positions against the fills recorded in `store`
+    pub fn with_store(mut self, store: Arc<StateStore>) -> Self {
+        self.store = Some(store);
+        self
+    }
+    
+    /// Check the broker's account for drift and apply the policy. Flattening
+    /// needs `can_trade`; otherwise drift is only reported.
+    pub async fn reconcile(&self, check_id: &str, can_trade: bool) -> Vec<Drift> {
+        let positions = match self.broker.get_positions().await {
+            Ok(positions) => positions,
+            Err(e) => {
+                warn!("  Could not read {} positions to reconcile: {}", self.broker.name(), e);
+                return Vec::new();
+            }
+        };
+        let open_orders = match self.broker.get_open_orders().await {
+            Ok(orders) => orders,
+            Err(e) => {
+                warn!("  Could not read {} open orders to reconcile: {}", self.broker.name(), e);
+                return Vec::new();
+            }
+        };
+        info!("  Broker reports {} position(s) and {} open order(s)", positions.len(), open_orders.len());
+        *self.last_check.lock().unwrap() = Some((Instant::now(), can_trade));
+        
+        let expected = self.expected_positions(check_id, &positions, &open_orders).await;
+        let drift = self.find_drift(&positions, &open_orders, expected.as_ref());
+        if drift.is_empty() {
+            info!("  Positions and orders match the bot's records");
+            return drift;
+        }
+        for item in &drift {
+            warn!("  DRIFT: {}", item);
+        }
+        
+        let mut policy = self.config.reconcile_policy.as_str();
+        if policy == "flatten" && !can_trade {
+            warn!("  Market closed, flattening deferred until the market is open");
+            policy = "alert";
+        }
+        let outcome = match policy {
+            "adopt" => {
+                self.adopt(&positions, &open_orders, expected.as_ref(), check_id);
+                "adopted broker state".to_string()
+            }
+            "flatten" => {
+                self.adopt(&positions, &open_orders, expected.as_ref(), check_id);
+                self.flatten(&drift, &positions, &open_orders, check_id).await
+            }
+            _ => "alert only".to_string(),
+        };
+        info!("  Reconcile policy '{}': {}", policy, outcome);
+        
+        self.logger.log_incident(&serde_json::json!({
+            "cycle_id": check_id,
+            "kind": "reconciliation",
+            "drift": drift,
+            "policy": policy,
+            "outcome": outcome,
+            "positions": positions.iter().map(|p| (p.symbol.clone(), p.qty)).collect::<HashMap<_, _>>(),
+            "open_orders": open_orders.iter().map(|o| &o.order_id).collect::<Vec<_>>(),
+        }));
+        drift
+    }
+    
+    /// Whether a check ran in the last minute, with trading allowed if
+    /// `can_trade` is, so running another would find nothing new
+    pub fn checked_recently(&self, can_trade: bool) -> bool {
+        match *self.last_check.lock().unwrap() {
+            Some((at, could_trade)) => at.elapsed() < RECHECK_AFTER && (could_trade || !can_trade),
+            None => false,
+        }
+    }
+    
+    /// Shares the recorded fills add up to, after bringing the store's
+    /// working orders up to date. An empty store takes the broker's positions
+    /// as its starting point.
+    async fn expected_positions(
+        &self,
+        check_id: &str,
+        positions: &[Position],
+        open_orders: &[TradeResult],
+    ) -> Option<HashMap<String, Decimal>> {
+        let store = self.store.as_ref()?;
+        
+        // Orders that were still working when last recorded may have filled since
+        match store.working_orders() {
+            Ok(ids) => {
+                for id in ids {
+                    let latest = match open_orders.iter().find(|o| o.order_id == id) {
+                        Some(order) => Ok(order.clone()),
+                        None => self.broker.get_order(&id).await,
+                    };
+                    match latest {
+                        Ok(order) => store.record_order(check_id, &order),
+                        Err(e) => warn!("  Could not refresh order {}: {}", id, e),
+                    }
+                }
+            }
+            Err(e) => warn!("  Could not read working orders from the state store: {}", e),
+        }
+        
+        match store.expected_positions() {
+            Ok(Some(expected)) => Some(expected),
+            Ok(None) => {
+                if !positions.is_empty() {
+                    info!("  No recorded history yet, taking the broker's positions as the baseline");
+                }
+                for position in positions {
+                    store.record_adjustment(&position.symbol, position.qty, "initial baseline");
+                }
+                None
+            }
+            Err(e) => {
+                error!("  Could not read expected positions from the state store: {}", e);
+                None
+            }
+        }
+    }
+    
+    fn find_drift(
+        &self,
+        positions: &[Position],
+        open_orders: &[TradeResult],
+        expected: Option<&HashMap<String, Decimal>>,
+    ) -> Vec<Drift> {
+        let traded = [self.config.symbol.as_str(), self.config.inverse_symbol.as_str()];
+        let held = |symbol: &str| {
+            positions.iter().find(|p| p.symbol == symbol).map(|p| p.qty).unwrap_or_default()
+        };
+        let mut drift = Vec::new();
+        
+        if let Some(expected) = expected {
+            for symbol in traded {
+                let (want, actual) = (expected.get(symbol).copied().unwrap_or_default(), held(symbol));
+                if want != actual {
+                    drift.push(Drift::PositionMismatch { symbol: symbol.to_string(), expected: want, actual });
+                }
+            }
+        }
+        for position in positions {
+            if !traded.contains(&position.symbol.as_str()) && !position.qty.is_zero() {
+                drift.push(Drift::ForeignSymbol { symbol: position.symbol.clone(), qty: position.qty });
+            }
+        }
+        let (symbol_qty, inverse_qty) = (held(traded[0]), held(traded[1]));
+        if !symbol_qty.is_zero() && !inverse_qty.is_zero() {
+            drift.push(Drift::BothLegsHeld { symbol_qty, inverse_qty });
+        }
+        for order in open_orders {
+            if !order.client_order_id.starts_with("hoc-") {
+                drift.push(Drift::UnknownOrder {
+                    order_id: order.order_id.clone(),
+                    symbol: order.symbol.clone(),
+                    side: order.side.clone(),
+                });
+            }
+        }
+        drift
+    }
+    
+    /// Make the broker's positions and open orders the expected ones
+    fn adopt(&self, positions: &[Position], open_orders: &[TradeResult], expected: Option<&HashMap<String, Decimal>>, check_id: &str) {
+        let (Some(store), Some(expected)) = (&self.store, expected) else {
+            return;
+        };
+        let symbols: BTreeSet<&str> = positions
+            .iter()
+            .map(|p| p.symbol.as_str())
+            .chain(expected.keys().map(String::as_str))
+            .collect();
+        for symbol in symbols {
+            let actual = positions.iter().find(|p| p.symbol == symbol).map(|p| p.qty).unwrap_or_default();
+            let diff = actual - expected.get(symbol).copied().unwrap_or_default();
+            if !diff.is_zero() {
+                info!("  Adopting {} position: {:+} shares", symbol, diff);
+                store.record_adjustment(symbol, diff, "adopted broker position");
+            }
+        }
+        // Their fills count towards expected positions from now on
+        for order in open_orders {
+            store.record_order(check_id, order);
+        }
+    }
+    
+    /// Cancel open orders the bot did not place and, if the positions in
+    /// `SYMBOL` or `INVERSE_SYMBOL` drifted, cancel their orders and close
+    /// both. Positions in other symbols are left alone. Returns what was done.
+    async fn flatten(&self, drift: &[Drift], positions: &[Position], open_orders: &[TradeResult], check_id: &str) -> String {
+        let traded = [self.config.symbol.as_str(), self.config.inverse_symbol.as_str()];
+        let position_drift = drift
+            .iter()
+            .any(|d| matches!(d, Drift::PositionMismatch { .. } | Drift::BothLegsHeld { .. }));
+        
+        let mut cancelled = 0;
+        for order in open_orders {
+            let unknown = !order.client_order_id.starts_with("hoc-");
+            let drifted = position_drift && traded.contains(&order.symbol.as_str());
+            if !unknown && !drifted {
+                continue;
+            }
+            info!("  Cancelling open order {} ({} {})", order.order_id, order.side, order.symbol);
+            match self.broker.cancel_order(&order.order_id).await {
+                Ok(()) => cancelled += 1,
+                Err(e) => error!("  Failed to cancel order {}: {}", order.order_id, e),
+            }
+        }
+        
+        let mut unclosed = Vec::new();
+        if position_drift {
+            for position in positions.iter().filter(|p| traded.contains(&p.symbol.as_str()) && !p.qty.is_zero()) {
+                let side = if position.qty > Decimal::ZERO { "sell" } else { "buy" };
+                let qty = position.qty.abs();
+                info!("  Flattening: {} {} {}", side, qty, position.symbol);
+                let client_order_id = format!("hoc-{}-reconcile-{}", check_id, position.symbol).to_lowercase();
+                let request = OrderRequest::new(side, OrderAmount::Qty(qty), &position.symbol, &client_order_id);
+                match self.broker.place_order(&request).await {
+                    Ok(result) => {
+                        info!("  {} {} {} ({})", side, qty, position.symbol, result.status);
+                        if let Some(store) = &self.store {
+                            store.record_order(check_id, &result);
+                        }
+                    }
+                    Err(e) => {
+                        error!("  Failed to close {}: {}", position.symbol, e);
+                        unclosed.push(position.symbol.clone());
+                    }
+                }
+            }
+        }
+        
+        let mut outcome = Vec::new();
+        if cancelled > 0 {
+            outcome.push(format!("cancelled {} order(s)", cancelled));
+        }
+        if position_drift {
+            if unclosed.is_empty() {
+                outcome.push(format!("flattened {} and {}", traded[0], traded[1]));
+            } else {
+                outcome.push(format!("flattened, but could not close {}", unclosed.join(", ")));
+            }
+        }
+        if outcome.is_empty() {
+            return "nothing the bot trades to flatten".to_string();
+        }
+        outcome.join(", ")
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trading::order::OrderType;
+    use crate::trading::simulated_broker::SimulatedBroker;
+    use rust_decimal_macros::dec;
+    use std::path::Path;
+    
+    /// Simulated account holding 10 BOIL bought by the bot and 5 UNG bought by hand
+    async fn setup(policy: &str) -> (Arc<SimulatedBroker>, Arc<StateStore>, Reconciler) {
+        let broker = Arc::new(SimulatedBroker::new(dec!(10000), Decimal::ZERO, Decimal::ZERO));
+        broker.set_price("BOIL", dec!(20));
+        broker.set_price("KOLD", dec!(50));
+        broker.set_price("UNG", dec!(15));
+        broker.place_order(&OrderRequest::new("buy", OrderAmount::Qty(dec!(10)), "BOIL", "hoc-1-boil")).await.unwrap();
+        broker.place_order(&OrderRequest::new("buy", OrderAmount::Qty(dec!(5)), "UNG", "manual-ung")).await.unwrap();
+        
+        let config = TradingConfig {
+            symbol: "BOIL".to_string(),
+            inverse_symbol: "KOLD".to_string(),
+            reconcile_policy: policy.to_string(),
+            ..TradingConfig::default()
+        };
+        let store = Arc::new(StateStore::open(Path::new(":memory:")).unwrap());
+        let reconciler = Reconciler::new(config, broker.clone()).with_store(store.clone());
+        (broker, store, reconciler)
+    }
+    
+    async fn held(broker: &SimulatedBroker, symbol: &str) -> Decimal {
+        broker.get_current_position(symbol).await.unwrap().map(|p| p.qty).unwrap_or_default()
+    }
+    
+    fn kinds(drift: &[Drift]) -> Vec<String> {
+        drift.iter().map(|d| serde_json::to_value(d).unwrap()["kind"].as_str().unwrap().to_string()).collect()
+    }
+    
+    /// Buy shares of `symbol` outside the bot
+    async fn manual_buy(broker: &SimulatedBroker, symbol: &str, qty: Decimal, id: &str) {
+        broker.place_order(&OrderRequest::new("buy", OrderAmount::Qty(qty), symbol, id)).await.unwrap();
+    }
+    
+    #[tokio::test]
+    async fn matching_records_report_no_drift() {
+        let (broker, _store, reconciler) = setup("alert").await;
+        broker.place_order(&OrderRequest::new("sell", OrderAmount::Qty(dec!(5)), "UNG", "manual-ung-exit")).await.unwrap();
+        
+        // The first check takes the broker's positions as the baseline
+        assert!(reconciler.reconcile("c1", true).await.is_empty());
+        assert!(reconciler.reconcile("c2", true).await.is_empty());
+    }
+    
+    #[tokio::test]
+    async fn alert_reports_position_drift_without_changing_anything() {
+        let (broker, store, reconciler) = setup("alert").await;
+        reconciler.reconcile("c1", true).await;
+        manual_buy(&broker, "BOIL", dec!(5), "manual-boil").await;
+        
+        let drift = reconciler.reconcile("c2", true).await;
+        assert_eq!(kinds(&drift), ["position_mismatch", "foreign_symbol"]);
+        assert_eq!(held(&broker, "BOIL").await, dec!(15));
+        assert_eq!(store.expected_positions().unwrap().unwrap()["BOIL"], dec!(10));
+    }
+    
+    #[tokio::test]
+    async fn adopt_takes_the_broker_positions_as_expected() {
+        let (broker, _store, reconciler) = setup("adopt").await;
+        reconciler.reconcile("c1", true).await;
+        manual_buy(&broker, "BOIL", dec!(5), "manual-boil").await;
+        
+        let drift = reconciler.reconcile("c2", true).await;
+        assert_eq!(kinds(&drift), ["position_mismatch", "foreign_symbol"]);
+        assert_eq!(held(&broker, "BOIL").await, dec!(15));
+        
+        let drift = reconciler.reconcile("c3", true).await;
+        assert_eq!(kinds(&drift), ["foreign_symbol"]);
+    }
+    
+    #[tokio::test]
+    async fn flatten_closes_only_the_traded_symbols_on_position_drift() {
+        let (broker, _store, reconciler) = setup("flatten").await;
+        
+        // A foreign position alone is reported but never sold
+        let drift = reconciler.reconcile("c1", true).await;
+        assert_eq!(kinds(&drift), ["foreign_symbol"]);
+        assert_eq!(held(&broker, "BOIL").await, dec!(10));
+        assert_eq!(held(&broker, "UNG").await, dec!(5));
+        
+        manual_buy(&broker, "BOIL", dec!(5), "manual-boil").await;
+        manual_buy(&broker, "KOLD", dec!(2), "manual-kold").await;
+        let drift = reconciler.reconcile("c2", true).await;
+        assert!(kinds(&drift).contains(&"position_mismatch".to_string()));
+        assert_eq!(held(&broker, "BOIL").await, Decimal::ZERO);
+        assert_eq!(held(&broker, "KOLD").await, Decimal::ZERO);
+        assert_eq!(held(&broker, "UNG").await, dec!(5));
+    }
+    
+    #[tokio::test]
+    async fn flatten_waits_for_the_market_to_open() {
+        let (broker, _store, reconciler) = setup("flatten").await;
+        reconciler.reconcile("c1", true).await;
+        manual_buy(&broker, "BOIL", dec!(5), "manual-boil").await;
+        
+        reconciler.reconcile("c2", false).await;
+        assert_eq!(held(&broker, "BOIL").await, dec!(15));
+    }
+    
+    #[tokio::test]
+    async fn flatten_cancels_unknown_orders_without_selling() {
+        let (broker, _store, reconciler) = setup("flatten").await;
+        reconciler.reconcile("c1", true).await;
+        let resting = OrderRequest::new("buy", OrderAmount::Qty(dec!(1)), "BOIL", "manual-limit")
+            .with_type(OrderType::Limit { limit_price: dec!(1) });
+        broker.place_order(&resting).await.unwrap();
+        
+        let drift = reconciler.reconcile("c2", true).await;
+        assert_eq!(kinds(&drift), ["foreign_symbol", "unknown_order"]);
+        assert!(broker.get_open_orders().await.unwrap().is_empty());
+        assert_eq!(held(&broker, "BOIL").await, dec!(10));
+        assert_eq!(held(&broker, "UNG").await, dec!(5));
+    }
+}
@@ -0,0 +1,437 @@
+use crate::config::TradingConfig;
+use crate::trading::broker::{AccountInfo, Broker, Position, Quote, TradeResult};
+use crate::trading::error::{BrokerError, BrokerResult};
+use crate::trading::market_calendar::MARKET_TZ;
+use crate::trading::order::{OrderAmount, OrderRequest};
+use async_trait::async_trait;
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc};
+use log::{info, warn};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Why the risk engine refused a trade
+#[derive(Debug, Clone, Serialize)]
+pub struct RiskViolation {
+    /// Limit that was hit, e.g. "max_order_notional" or "kill_switch"
+    pub rule: &'static str,
+    pub reason: String,
+}
+
+impl std::fmt::Display for RiskViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.rule, self.reason)
+    }
+}
+
+/// Counters and equity baselines for the current trading day and week
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct RiskState {
+    day: Option<NaiveDate>,
+    day_start_equity: Decimal,
+    trades_today: u32,
+    /// Monday of the current week
+    week: Option<NaiveDate>,
+    week_start_equity: Decimal,
+}
+
+/// Pre-trade checks every order of a trade must pass before any is sent.
+///
+/// Limits set to 0 are disabled. Order and exposure limits are in dollars;
+/// loss limits are percentages of the broker's prior-close equity
+/// (`AccountInfo::last_equity`) as of the first check of the trading day or
+/// week (US/Eastern), so a loss since the close counts even when that first
+/// check is the day's only trade. Exposure and loss limits only stop
+/// trades that buy, so positions can always be reduced. The kill switch
+/// refuses every trade here; `KillSwitchBroker` also stops the orders that
+/// bypass these checks.
+pub struct RiskEngine {
+    config: TradingConfig,
+    broker: Arc<dyn Broker>,
+    state: Mutex<RiskState>,
+    state_file: Option<PathBuf>,
+}
+
+impl RiskEngine {
+    /// Engine whose daily counters live in memory only
+    pub fn new(config: TradingConfig, broker: Arc<dyn Broker>) -> Self {
+        Self {
+            config,
+            broker,
+            state: Mutex::new(RiskState::default()),
+            state_file: None,
+        }
+    }
+    
+    /// Keep the daily and weekly counters in `path` so restarts (and separate
+    /// `once` runs) share them
+    pub fn with_state_file(mut self, path: &Path) -> Self {
+        if let Ok(text) = std::fs::read_to_string(path) {
+            match serde_json::from_str(&text) {
+                Ok(state) => self.state = Mutex::new(state),
+                Err(e) => warn!("Ignoring unreadable risk state {}: {}", path.display(), e),
+            }
+        }
+        self.state_file = Some(path.to_path_buf());
+        self
+    }
+    
+    /// Reason the kill switch file gives, if it exists
+    pub fn kill_switch(&self) -> Option<String> {
+        kill_switch_reason(Path::new(&self.config.kill_switch_file))
+    }
+    
+    /// Check the orders of one trade at time `as_of`. Approved orders count
+    /// towards the daily trade limit.
+    pub async fn approve(&self, orders: &[OrderRequest], as_of: DateTime<Utc>) -> Result<(), RiskViolation> {
+        let violation = |rule, reason: String| Err(RiskViolation { rule, reason });
+        
+        if let Some(reason) = self.kill_switch() {
+            return violation("kill_switch", format!("{} exists ({})", self.config.kill_switch_file, reason));
+        }
+        
+        let account = self.broker.get_account_info().await
+            .map_err(|e| RiskViolation { rule: "account_unavailable", reason: e.to_string() })?;
+        let positions = self.broker.get_positions().await
+            .map_err(|e| RiskViolation { rule: "account_unavailable", reason: e.to_string() })?;
+        let state = self.roll_over(as_of, &account);
+        
+        let mut buys = Decimal::ZERO;
+        let mut sells = Decimal::ZERO;
+        for order in orders {
+            let notional = match order.amount {
+                OrderAmount::Notional(notional) => notional,
+                OrderAmount::Qty(qty) => {
+                    let price = self.broker.get_current_price(&order.symbol).await
+                        .map_err(|e| RiskViolation { rule: "price_unavailable", reason: e.to_string() })?;
+                    qty * price
+                }
+            };
+            let max = self.config.max_order_notional;
+            if max > Decimal::ZERO && notional > max {
+                return violation("max_order_notional", format!(
+                    "{} {} {} is ${:.2}, limit ${:.2}", order.side, order.amount, order.symbol, notional, max
+                ));
+            }
+            if order.side == "buy" {
+                buys += notional;
+            } else {
+                sells += notional;
+            }
+        }
+        
+        let max_trades = self.config.max_daily_trades;
+        if max_trades > 0 && state.trades_today + orders.len() as u32 > max_trades {
+            return violation("max_daily_trades", format!(
+                "{} order(s) today, {} more would exceed {}", state.trades_today, orders.len(), max_trades
+            ));
+        }
+        
+        if buys > Decimal::ZERO {
+            let gross: Decimal = positions.iter().map(|p| p.market_value.abs()).sum();
+            let after = (gross - sells).max(Decimal::ZERO) + buys;
+            let max = self.config.max_gross_exposure;
+            if max > Decimal::ZERO && after > max {
+                return violation("max_gross_exposure", format!(
+                    "gross exposure would be ${:.2}, limit ${:.2}", after, max
+                ));
+            }
+            let max_pct = self.config.max_exposure_pct_equity;
+            if max_pct > Decimal::ZERO && account.equity > Decimal::ZERO {
+                let pct = after / account.equity * Decimal::ONE_HUNDRED;
+                if pct > max_pct {
+                    return violation("max_exposure_pct_equity", format!(
+                        "gross exposure would be {:.1}% of ${:.2} equity, limit {}%", pct, account.equity, max_pct
+                    ));
+                }
+            }
+            
+            for (rule, start, limit, period) in [
+                ("daily_loss_limit", state.day_start_equity, self.config.daily_loss_limit_pct, "today"),
+                ("weekly_loss_limit", state.week_start_equity, self.config.weekly_loss_limit_pct, "this week"),
+            ] {
+                if limit <= Decimal::ZERO || start <= Decimal::ZERO {
+                    continue;
+                }
+                let loss_pct = (start - account.equity) / start * Decimal::ONE_HUNDRED;
+                if loss_pct >= limit {
+                    return violation(rule, format!(
+                        "equity down {:.2}% {} (${:.2} -> ${:.2}), limit {}%", loss_pct, period, start, account.equity, limit
+                    ));
+                }
+            }
+        }
+        
+        let mut state = self.state.lock().unwrap();
+        state.trades_today += orders.len() as u32;
+        info!("  Risk checks passed ({} order(s) today)", state.trades_today);
+        self.save(&state);
+        Ok(())
+    }
+    
+    /// Start new day/week counters when `as_of` has moved past the stored
+    /// ones, measuring from the previous close
+    fn roll_over(&self, as_of: DateTime<Utc>, account: &AccountInfo) -> RiskState {
+        // Brokers that cannot report a previous close fall back to current equity
+        let equity = if account.last_equity > Decimal::ZERO { account.last_equity } else { account.equity };
+        let mut state = self.state.lock().unwrap();
+        let today = as_of.with_timezone(&MARKET_TZ).date_naive();
+        let monday = today - Duration::days(today.weekday().num_days_from_monday() as i64);
+        let mut changed = false;
+        if state.day != Some(today) {
+            state.day = Some(today);
+            state.day_start_equity = equity;
+            state.trades_today = 0;
+            changed = true;
+        }
+        if state.week != Some(monday) {
+            state.week = Some(monday);
+            state.week_start_equity = equity;
+            changed = true;
+        }
+        if changed {
+            self.save(&state);
+        }
+        state.clone()
+    }
+    
+    fn save(&self, state: &RiskState) {
+        let Some(path) = &self.state_file else {
+            return;
+        };
+        let result = serde_json::to_string_pretty(state)
+            .map_err(std::io::Error::other)
+            .and_then(|json| std::fs::write(path, json));
+        if let Err(e) = result {
+            warn!("Failed to save risk state to {}: {}", path.display(), e);
+        }
+    }
+}
+
+/// Reason the kill switch file at `path` gives, if it exists
+fn kill_switch_reason(path: &Path) -> Option<String> {
+    path.exists().then(|| {
+        std::fs::read_to_string(path)
+            .map(|s| s.trim().to_string())
+            .ok()
+            .filter(|s| !s.is_empty())
+            .unwrap_or_else(|| "no reason given".to_string())
+    })
+}
+
+/// Broker that refuses to place any order while the kill switch file exists.
+///
+/// Protective exits, circuit-breaker and reconciliation flattening place
+/// orders without going through `RiskEngine::approve`; wrapping the broker
+/// they share stops those too. Reads and cancels pass straight through.
+pub struct KillSwitchBroker {
+    inner: Arc<dyn Broker>,
+    path: PathBuf,
+}
+
+impl KillSwitchBroker {
+    pub fn new(config: &TradingConfig, inner: Arc<dyn Broker>) -> Self {
+        Self {
+            inner,
+            path: PathBuf::from(&config.kill_switch_file),
+        }
+    }
+}
+
+#[async_trait]
+impl Broker for KillSwitchBroker {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+    
+    fn ledger_id(&self) -> String {
+        self.inner.ledger_id()
+    }
+    
+    async fn get_account_info(&self) -> BrokerResult<AccountInfo> {
+        self.inner.get_account_info().await
+    }
+    
+    async fn get_current_position(&self, symbol: &str) -> BrokerResult<Option<Position>> {
+        self.inner.get_current_position(symbol).await
+    }
+    
+    async fn get_positions(&self) -> BrokerResult<Vec<Position>> {
+        self.inner.get_positions().await
+    }
+    
+    async fn get_current_price(&self, symbol: &str) -> BrokerResult<Decimal> {
+        self.inner.get_current_price(symbol).await
+    }
+    
+    async fn get_daily_closes(&self, symbol: &str, days: usize) -> BrokerResult<Vec<Decimal>> {
+        self.inner.get_daily_closes(symbol, days).await
+    }
+    
+    async fn get_latest_quote(&self, symbol: &str) -> BrokerResult<Quote> {
+        self.inner.get_latest_quote(symbol).await
+    }
+    
+    async fn place_order(&self, order: &OrderRequest) -> BrokerResult<TradeResult> {
+        if let Some(reason) = kill_switch_reason(&self.path) {
+            warn!("  Kill switch engaged, not sending {} {} {}", order.side, order.amount, order.symbol);
+            return Err(BrokerError::KillSwitch(format!("{} exists ({})", self.path.display(), reason)));
+        }
+        self.inner.place_order(order).await
+    }
+    
+    async fn get_open_orders(&self) -> BrokerResult<Vec<TradeResult>> {
+        self.inner.get_open_orders().await
+    }
+    
+    async fn get_order(&self, order_id: &str) -> BrokerResult<TradeResult> {
+        self.inner.get_order(order_id).await
+    }
+    
+    async fn cancel_order(&self, order_id: &str) -> BrokerResult<()> {
+        self.inner.cancel_order(order_id).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trading::simulated_broker::SimulatedBroker;
+    use rust_decimal_macros::dec;
+    
+    /// Config with every limit disabled and a kill switch file that does not exist
+    fn unlimited(name: &str) -> TradingConfig {
+        let kill_switch = std::env::temp_dir().join(format!("algotrade-risk-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_file(&kill_switch);
+        TradingConfig {
+            max_order_notional: Decimal::ZERO,
+            max_gross_exposure: Decimal::ZERO,
+            max_exposure_pct_equity: Decimal::ZERO,
+            max_daily_trades: 0,
+            daily_loss_limit_pct: Decimal::ZERO,
+            weekly_loss_limit_pct: Decimal::ZERO,
+            kill_switch_file: kill_switch.display().to_string(),
+            ..TradingConfig::default()
+        }
+    }
+    
+    fn broker() -> Arc<SimulatedBroker> {
+        let broker = SimulatedBroker::new(dec!(10000), Decimal::ZERO, Decimal::ZERO);
+        broker.set_price("BOIL", dec!(20));
+        broker.set_price("KOLD", dec!(50));
+        Arc::new(broker)
+    }
+    
+    fn buy(notional: Decimal) -> OrderRequest {
+        OrderRequest::new("buy", OrderAmount::Notional(notional), "BOIL", "test-buy")
+    }
+    
+    fn sell(qty: Decimal) -> OrderRequest {
+        OrderRequest::new("sell", OrderAmount::Qty(qty), "BOIL", "test-sell")
+    }
+    
+    fn at(text: &str) -> DateTime<Utc> {
+        text.parse().unwrap()
+    }
+    
+    async fn rule(engine: &RiskEngine, orders: &[OrderRequest], as_of: &str) -> Option<&'static str> {
+        engine.approve(orders, at(as_of)).await.err().map(|v| v.rule)
+    }
+    
+    #[tokio::test]
+    async fn order_notional_limit_prices_quantities() {
+        let config = TradingConfig { max_order_notional: dec!(1000), ..unlimited("notional") };
+        let engine = RiskEngine::new(config, broker());
+        let now = "2024-10-24T15:00:00Z";
+        assert_eq!(rule(&engine, &[buy(dec!(1000))], now).await, None);
+        assert_eq!(rule(&engine, &[buy(dec!(1000.01))], now).await, Some("max_order_notional"));
+        // 51 shares at $20
+        assert_eq!(rule(&engine, &[sell(dec!(51))], now).await, Some("max_order_notional"));
+    }
+    
+    #[tokio::test]
+    async fn daily_trade_limit_resets_next_day() {
+        let config = TradingConfig { max_daily_trades: 3, ..unlimited("trades") };
+        let engine = RiskEngine::new(config, broker());
+        assert_eq!(rule(&engine, &[buy(dec!(100)), buy(dec!(100))], "2024-10-24T14:00:00Z").await, None);
+        assert_eq!(rule(&engine, &[buy(dec!(100)), buy(dec!(100))], "2024-10-24T15:00:00Z").await, Some("max_daily_trades"));
+        assert_eq!(rule(&engine, &[buy(dec!(100))], "2024-10-24T16:00:00Z").await, None);
+        assert_eq!(rule(&engine, &[buy(dec!(100))], "2024-10-24T17:00:00Z").await, Some("max_daily_trades"));
+        // 23:30 UTC is still the same trading day in New York
+        assert_eq!(rule(&engine, &[buy(dec!(100))], "2024-10-24T23:30:00Z").await, Some("max_daily_trades"));
+        assert_eq!(rule(&engine, &[buy(dec!(100))], "2024-10-25T14:00:00Z").await, None);
+    }
+    
+    #[tokio::test]
+    async fn exposure_limits_stop_buys_but_not_sells() {
+        let broker = broker();
+        broker.place_order(&buy(dec!(4000))).await.unwrap();
+        let now = "2024-10-24T15:00:00Z";
+        
+        let config = TradingConfig { max_gross_exposure: dec!(5000), ..unlimited("gross") };
+        let engine = RiskEngine::new(config, broker.clone());
+        assert_eq!(rule(&engine, &[buy(dec!(1000))], now).await, None);
+        assert_eq!(rule(&engine, &[buy(dec!(1001))], now).await, Some("max_gross_exposure"));
+        // Selling 100 shares ($2000) first leaves room for the buy
+        assert_eq!(rule(&engine, &[sell(dec!(100)), buy(dec!(3000))], now).await, None);
+        assert_eq!(rule(&engine, &[sell(dec!(200))], now).await, None);
+        
+        let config = TradingConfig { max_exposure_pct_equity: dec!(50), ..unlimited("pct") };
+        let engine = RiskEngine::new(config, broker);
+        assert_eq!(rule(&engine, &[buy(dec!(1000))], now).await, None);
+        assert_eq!(rule(&engine, &[buy(dec!(1001))], now).await, Some("max_exposure_pct_equity"));
+        assert_eq!(rule(&engine, &[sell(dec!(200))], now).await, None);
+    }
+    
+    #[tokio::test]
+    async fn loss_limits_measure_from_the_previous_close() {
+        let broker = broker();
+        // Wednesday after the close: $5,000 of BOIL and $5,000 cash
+        broker.set_time(at("2024-10-23T21:00:00Z"));
+        broker.place_order(&buy(dec!(5000))).await.unwrap();
+        let config = TradingConfig {
+            daily_loss_limit_pct: dec!(2),
+            weekly_loss_limit_pct: dec!(4),
+            ..unlimited("loss")
+        };
+        let engine = RiskEngine::new(config, broker.clone());
+        
+        // Thursday before the open equity is down 2.5% from Wednesday's close,
+        // so the day's first check, usually its only trade, is already blocked
+        broker.set_time(at("2024-10-24T13:00:00Z"));
+        broker.set_price("BOIL", dec!(19));
+        assert_eq!(rule(&engine, &[buy(dec!(100))], "2024-10-24T14:00:00Z").await, Some("daily_loss_limit"));
+        assert_eq!(rule(&engine, &[sell(dec!(10))], "2024-10-24T14:00:00Z").await, None);
+        
+        // Friday is down 1.5% from Thursday's $9,750 close but 4% on the week
+        broker.set_time(at("2024-10-25T13:00:00Z"));
+        broker.set_price("BOIL", dec!(18.4));
+        assert_eq!(rule(&engine, &[buy(dec!(100))], "2024-10-25T14:00:00Z").await, Some("weekly_loss_limit"));
+        // Monday starts a new week from Friday's close
+        broker.set_time(at("2024-10-28T14:00:00Z"));
+        assert_eq!(rule(&engine, &[buy(dec!(100))], "2024-10-28T14:00:00Z").await, None);
+    }
+    
+    #[tokio::test]
+    async fn kill_switch_refuses_trades_and_orders() {
+        let config = unlimited("kill");
+        let path = PathBuf::from(&config.kill_switch_file);
+        let broker = broker();
+        let engine = RiskEngine::new(config.clone(), broker.clone());
+        let guarded = KillSwitchBroker::new(&config, broker);
+        let now = "2024-10-24T15:00:00Z";
+        assert_eq!(rule(&engine, &[buy(dec!(100))], now).await, None);
+        
+        std::fs::write(&path, "manual halt\n").unwrap();
+        assert_eq!(engine.kill_switch().as_deref(), Some("manual halt"));
+        assert_eq!(rule(&engine, &[sell(dec!(1))], now).await, Some("kill_switch"));
+        assert!(matches!(guarded.place_order(&buy(dec!(100))).await, Err(BrokerError::KillSwitch(_))));
+        assert!(guarded.get_account_info().await.is_ok());
+        
+        std::fs::remove_file(&path).unwrap();
+        assert!(engine.kill_switch().is_none());
+        assert!(guarded.place_order(&buy(dec!(100))).await.is_ok());
+    }
+}
+
@@ -0,0 +1,473 @@
+use crate::backtest::data::load_bars_csv;
+use crate::config::TradingConfig;
+use crate::trading::broker::{AccountInfo, Broker, Position, Quote, TradeResult};
+use crate::trading::error::{BrokerError, BrokerResult};
+use crate::trading::market_calendar::{self, MARKET_TZ};
+use crate::trading::order::{OrderAmount, OrderRequest, OrderStatus, OrderType};
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use log::{info, warn};
+use rust_decimal::prelude::FromPrimitive;
+use rust_decimal::{Decimal, RoundingStrategy};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::Mutex;
+
+/// Decimal places of fractional share quantities, as on Alpaca
+const QTY_DECIMALS: u32 = 9;
+
+#[derive(Debug, Clone, Default)]
+struct SimPosition {
+    qty: Decimal,
+    cost_basis: Decimal,
+}
+
+#[derive(Debug)]
+struct SimState {
+    cash: Decimal,
+    positions: HashMap<String, SimPosition>,
+    prices: HashMap<String, Decimal>,
+    /// Every price set per symbol, oldest first
+    price_history: HashMap<String, Vec<Decimal>>,
+    next_order_id: u64,
+    clock: Option<DateTime<Utc>>,
+    fills: Vec<TradeResult>,
+    client_order_ids: HashSet<String>,
+    orders: HashMap<String, TradeResult>,
+    /// Equity when the session `last_close` ended
+    last_equity: Decimal,
+    last_close: Option<NaiveDate>,
+}
+
+/// In-process paper broker with a cash ledger and instant market fills.
+///
+/// The last price supplied through `set_price`, widened by `slippage_bps` on
+/// each side, is the quote: market orders fill completely at the ask (buys)
+/// or bid (sells), and every order pays a flat `commission_per_order`. Limit
+/// and stop orders fill the same way when the quote allows it on submission;
+/// otherwise they rest until a later `set_price` moves the quote through
+/// them, or until they are cancelled. Short
+/// selling is not supported. Equity is snapshotted at every NYSE close (by
+/// `set_time`, or the wall clock) and reported as `last_equity`. The account
+/// only lives as long as the instance, so each instance has its own ledger ID.
+pub struct SimulatedBroker {
+    commission_per_order: Decimal,
+    slippage_bps: Decimal,
+    ledger_id: String,
+    state: Mutex<SimState>,
+}
+
+impl SimulatedBroker {
+    pub fn new(starting_cash: Decimal, commission_per_order: Decimal, slippage_bps: Decimal) -> Self {
+        Self {
+            commission_per_order,
+            slippage_bps,
+            ledger_id: format!("simulated:{}", Utc::now().format("%Y%m%dT%H%M%S%.6fZ")),
+            state: Mutex::new(SimState {
+                cash: starting_cash,
+                positions: HashMap::new(),
+                prices: HashMap::new(),
+                price_history: HashMap::new(),
+                next_order_id: 1,
+                clock: None,
+                fills: Vec::new(),
+                client_order_ids: HashSet::new(),
+                orders: HashMap::new(),
+                last_equity: starting_cash,
+                last_close: None,
+            }),
+        }
+    }
+    
+    /// Broker priced from `SIM_BARS`, whose closes become the price history
+    /// with the last one as the current price, and `SIM_PRICES` for symbols
+    /// without bars
+    pub fn from_config(config: &TradingConfig) -> anyhow::Result<Self> {
+        let broker = Self::new(
+            config.sim_starting_cash,
+            config.sim_commission_per_order,
+            config.sim_slippage_bps,
+        );
+        for (symbol, path) in &config.sim_bars {
+            for bar in load_bars_csv(Path::new(path))? {
+                let close = Decimal::from_f64(bar.close)
+                    .ok_or_else(|| anyhow::anyhow!("{}: close {} is not a price", path, bar.close))?;
+                broker.set_price(symbol, close);
+            }
+        }
+        for (symbol, price) in config.sim_prices.iter().filter(|(symbol, _)| !config.sim_bars.contains_key(*symbol)) {
+            broker.set_price(symbol, *price);
+        }
+        Ok(broker)
+    }
+    
+    /// Update the price used for fills and mark-to-market of `symbol`, and
+    /// fill resting orders the new quote trades through. Each call also
+    /// counts as one daily close for `get_daily_closes`.
+    pub fn set_price(&self, symbol: &str, price: Decimal) {
+        let mut state = self.state.lock().unwrap();
+        Self::roll_close(&mut state);
+        state.prices.insert(symbol.to_string(), price);
+        state.price_history.entry(symbol.to_string()).or_default().push(price);
+        self.fill_resting(&mut state, symbol);
+    }
+    
+    /// Pin the timestamp stamped on fills (used when replaying history)
+    pub fn set_time(&self, time: DateTime<Utc>) {
+        let mut state = self.state.lock().unwrap();
+        state.clock = Some(time);
+    }
+    
+    /// Drain every fill recorded since the last call
+    pub fn take_fills(&self) -> Vec<TradeResult> {
+        let mut state = self.state.lock().unwrap();
+        std::mem::take(&mut state.fills)
+    }
+    
+    fn price_of(state: &SimState, symbol: &str) -> BrokerResult<Decimal> {
+        state
+            .prices
+            .get(symbol)
+            .copied()
+            .ok_or_else(|| BrokerError::PriceUnavailable(symbol.to_string()))
+    }
+    
+    fn quote_of(&self, state: &SimState, symbol: &str) -> BrokerResult<Quote> {
+        let price = Self::price_of(state, symbol)?;
+        let slippage = price * self.slippage_bps / Decimal::from(10_000);
+        Ok(Quote { bid: price - slippage, ask: price + slippage })
+    }
+    
+    /// Whether an order of `order_type` trades at `fill_price`
+    fn is_marketable(order_type: &OrderType, side: &str, fill_price: Decimal) -> bool {
+        match (order_type, side) {
+            (OrderType::Market, _) => true,
+            (OrderType::Limit { limit_price }, "buy") => *limit_price >= fill_price,
+            (OrderType::Limit { limit_price }, _) => *limit_price <= fill_price,
+            (OrderType::Stop { stop_price }, "buy") => fill_price >= *stop_price,
+            (OrderType::Stop { stop_price }, _) => fill_price <= *stop_price,
+        }
+    }
+    
+    /// Move cash and shares for `shares` of `symbol` filled at `fill_price`
+    fn settle(&self, state: &mut SimState, side: &str, symbol: &str, shares: Decimal, fill_price: Decimal) -> BrokerResult<()> {
+        match side {
+            "buy" => {
+                let cost = fill_price * shares + self.commission_per_order;
+                if cost > state.cash {
+                    return Err(BrokerError::InsufficientBuyingPower(format!(
+                        "need ${:.2}, have ${:.2}",
+                        cost, state.cash
+                    )));
+                }
+                state.cash -= cost;
+                let pos = state.positions.entry(symbol.to_string()).or_default();
+                pos.qty += shares;
+                pos.cost_basis += fill_price * shares;
+            }
+            "sell" => {
+                let held = state.positions.get(symbol).map(|p| p.qty).unwrap_or_default();
+                if shares > held {
+                    return Err(BrokerError::InsufficientQuantity(format!(
+                        "{} requested: {}, available: {}",
+                        symbol, shares, held
+                    )));
+                }
+                let pos = state.positions.get_mut(symbol).unwrap();
+                let released_basis = pos.cost_basis * shares / pos.qty;
+                pos.qty -= shares;
+                pos.cost_basis -= released_basis;
+                if pos.qty.is_zero() {
+                    state.positions.remove(symbol);
+                }
+                state.cash += fill_price * shares - self.commission_per_order;
+            }
+            other => {
+                warn!("  Simulated broker rejected unknown order side: {}", other);
+                return Err(BrokerError::InvalidOrder(format!("unsupported order side: {}", other)));
+            }
+        }
+        Ok(())
+    }
+    
+    /// Fill the resting orders in `symbol` that the current quote trades
+    /// through, oldest first. One that can no longer be paid for or covered
+    /// is rejected, as Alpaca does.
+    fn fill_resting(&self, state: &mut SimState, symbol: &str) {
+        let Ok(quote) = self.quote_of(state, symbol) else {
+            return;
+        };
+        let mut resting: Vec<TradeResult> = state
+            .orders
+            .values()
+            .filter(|o| o.symbol == symbol && !o.status.is_terminal())
+            .cloned()
+            .collect();
+        resting.sort_by(|a, b| a.submitted_at.cmp(&b.submitted_at).then_with(|| a.order_id.cmp(&b.order_id)));
+        
+        for order in resting {
+            let fill_price = if order.side == "buy" { quote.ask } else { quote.bid };
+            if !Self::is_marketable(&order.order_type, &order.side, fill_price) {
+                continue;
+            }
+            let settled = self.settle(state, &order.side, symbol, order.qty, fill_price);
+            let Some(resting) = state.orders.get_mut(&order.order_id) else {
+                continue;
+            };
+            match settled {
+                Ok(()) => {
+                    resting.status = OrderStatus::Filled;
+                    resting.filled_qty = Some(order.qty);
+                    resting.filled_avg_price = Some(fill_price);
+                    let filled = resting.clone();
+                    info!("Simulated {} order filled: {:?}", filled.order_type, filled);
+                    state.fills.push(filled);
+                }
+                Err(e) => {
+                    warn!("  Simulated {} order {} rejected: {}", order.order_type, order.order_id, e);
+                    resting.status = OrderStatus::Rejected;
+                }
+            }
+        }
+    }
+    
+    fn equity_of(state: &SimState) -> Decimal {
+        let positions_value: Decimal = state
+            .positions
+            .iter()
+            .map(|(symbol, pos)| pos.qty * state.prices.get(symbol).copied().unwrap_or_default())
+            .sum();
+        state.cash + positions_value
+    }
+    
+    /// Snapshot equity as the close of the last session that ended by the
+    /// broker's clock. Equity only moves through `set_price` and fills, which
+    /// call this first, so the equity at the first call after a close is the
+    /// equity the session closed with.
+    fn roll_close(state: &mut SimState) {
+        let now = state.clock.unwrap_or_else(Utc::now);
+        let today = now.with_timezone(&MARKET_TZ).date_naive();
+        let last_close = (0..15)
+            .filter_map(|days_back| market_calendar::local_session(today - Duration::days(days_back)))
+            .find(|session| session.close <= now)
+            .map(|session| session.date);
+        if last_close != state.last_close {
+            state.last_equity = Self::equity_of(state);
+            state.last_close = last_close;
+        }
+    }
+    
+    fn to_position(symbol: &str, pos: &SimPosition, price: Decimal) -> Position {
+        let market_value = pos.qty * price;
+        let unrealized_pl = market_value - pos.cost_basis;
+        Position {
+            symbol: symbol.to_string(),
+            qty: pos.qty,
+            market_value,
+            avg_entry_price: if pos.qty.is_zero() { Decimal::ZERO } else { pos.cost_basis / pos.qty },
+            unrealized_pl,
+            unrealized_plpc: if pos.cost_basis.is_zero() { Decimal::ZERO } else { unrealized_pl / pos.cost_basis },
+        }
+    }
+}
+
+#[async_trait]
+impl Broker for SimulatedBroker {
+    fn name(&self) -> &str {
+        "Simulated"
+    }
+    
+    fn ledger_id(&self) -> String {
+        self.ledger_id.clone()
+    }
+    
+    async fn get_account_info(&self) -> BrokerResult<AccountInfo> {
+        let mut state = self.state.lock().unwrap();
+        Self::roll_close(&mut state);
+        let mut positions_value = Decimal::ZERO;
+        for (symbol, pos) in &state.positions {
+            positions_value += pos.qty * Self::price_of(&state, symbol)?;
+        }
+        let equity = state.cash + positions_value;
+        
+        Ok(AccountInfo {
+            equity,
+            buying_power: state.cash,
+            cash: state.cash,
+            portfolio_value: equity,
+            last_equity: state.last_equity,
+        })
+    }
+    
+    async fn get_current_position(&self, symbol: &str) -> BrokerResult<Option<Position>> {
+        let state = self.state.lock().unwrap();
+        match state.positions.get(symbol) {
+            Some(pos) => {
+                let price = Self::price_of(&state, symbol)?;
+                Ok(Some(Self::to_position(symbol, pos, price)))
+            }
+            None => Ok(None),
+        }
+    }
+    
+    async fn get_positions(&self) -> BrokerResult<Vec<Position>> {
+        let state = self.state.lock().unwrap();
+        let mut positions = Vec::new();
+        for (symbol, pos) in &state.positions {
+            let price = Self::price_of(&state, symbol)?;
+            positions.push(Self::to_position(symbol, pos, price));
+        }
+        positions.sort_by(|a, b| a.symbol.cmp(&b.symbol));
+        Ok(positions)
+    }
+    
+    async fn get_current_price(&self, symbol: &str) -> BrokerResult<Decimal> {
+        let state = self.state.lock().unwrap();
+        Self::price_of(&state, symbol)
+    }
+    
+    async fn get_daily_closes(&self, symbol: &str, days: usize) -> BrokerResult<Vec<Decimal>> {
+        let state = self.state.lock().unwrap();
+        let history = state.price_history.get(symbol).map(Vec::as_slice).unwrap_or_default();
+        Ok(history[history.len().saturating_sub(days)..].to_vec())
+    }
+    
+    async fn get_latest_quote(&self, symbol: &str) -> BrokerResult<Quote> {
+        let state = self.state.lock().unwrap();
+        self.quote_of(&state, symbol)
+    }
+    
+    async fn place_order(&self, request: &OrderRequest) -> BrokerResult<TradeResult> {
+        let OrderRequest { symbol, side, amount, client_order_id, order_type, .. } = request;
+        if amount.value() <= Decimal::ZERO {
+            return Err(BrokerError::InvalidOrder(format!("order amount must be positive, got {}", amount)));
+        }
+        if matches!(amount, OrderAmount::Notional(_)) && *order_type != OrderType::Market {
+            return Err(BrokerError::InvalidOrder("notional orders must be market orders".to_string()));
+        }
+        
+        let mut state = self.state.lock().unwrap();
+        Self::roll_close(&mut state);
+        // Mirror Alpaca: a reused client_order_id is rejected rather than filled twice
+        if !client_order_id.is_empty() && state.client_order_ids.contains(client_order_id) {
+            return Err(BrokerError::DuplicateClientOrderId(client_order_id.to_string()));
+        }
+        let quote = self.quote_of(&state, symbol)?;
+        let fill_price = if side == "buy" { quote.ask } else { quote.bid };
+        let shares = match amount {
+            OrderAmount::Qty(qty) => *qty,
+            // Notional orders buy as many (fractional) shares as the amount covers at the fill price
+            OrderAmount::Notional(notional) => {
+                (notional / fill_price).round_dp_with_strategy(QTY_DECIMALS, RoundingStrategy::ToZero)
+            }
+        };
+        
+        let order_id = format!("sim-{}", state.next_order_id);
+        let submitted_at = state.clock.unwrap_or_else(Utc::now).to_rfc3339();
+        let mut result = TradeResult {
+            order_id,
+            client_order_id: client_order_id.to_string(),
+            symbol: symbol.to_string(),
+            qty: match amount {
+                OrderAmount::Qty(qty) => *qty,
+                OrderAmount::Notional(_) => Decimal::ZERO,
+            },
+            notional: match amount {
+                OrderAmount::Qty(_) => None,
+                OrderAmount::Notional(notional) => Some(*notional),
+            },
+            side: side.to_string(),
+            order_type: *order_type,
+            status: OrderStatus::New,
+            filled_qty: Some(Decimal::ZERO),
+            filled_avg_price: None,
+            submitted_at,
+        };
+        
+        if !Self::is_marketable(order_type, side, fill_price) {
+            state.next_order_id += 1;
+            state.client_order_ids.insert(client_order_id.to_string());
+            state.orders.insert(result.order_id.clone(), result.clone());
+            info!("Simulated {} order resting: {:?}", order_type, result);
+            return Ok(result);
+        }
+        
+        self.settle(&mut state, side, symbol, shares, fill_price)?;
+        
+        state.next_order_id += 1;
+        state.client_order_ids.insert(client_order_id.to_string());
+        result.status = OrderStatus::Filled;
+        result.filled_qty = Some(shares);
+        result.filled_avg_price = Some(fill_price);
+        
+        state.fills.push(result.clone());
+        state.orders.insert(result.order_id.clone(), result.clone());
+        info!("Simulated order filled: {:?}", result);
+        Ok(result)
+    }
+    
+    async fn get_open_orders(&self) -> BrokerResult<Vec<TradeResult>> {
+        // Only limit and stop orders that could not fill on submission stay open
+        let state = self.state.lock().unwrap();
+        let mut orders: Vec<TradeResult> = state.orders.values().filter(|o| !o.status.is_terminal()).cloned().collect();
+        orders.sort_by(|a, b| a.submitted_at.cmp(&b.submitted_at).then_with(|| a.order_id.cmp(&b.order_id)));
+        Ok(orders)
+    }
+    
+    async fn get_order(&self, order_id: &str) -> BrokerResult<TradeResult> {
+        let state = self.state.lock().unwrap();
+        state
+            .orders
+            .get(order_id)
+            .cloned()
+            .ok_or_else(|| BrokerError::NotFound(format!("order {}", order_id)))
+    }
+    
+    async fn cancel_order(&self, order_id: &str) -> BrokerResult<()> {
+        let mut state = self.state.lock().unwrap();
+        match state.orders.get_mut(order_id) {
+            Some(order) if !order.status.is_terminal() => {
+                order.status = OrderStatus::Canceled;
+                Ok(())
+            }
+            _ => Err(BrokerError::NotFound(format!("order {} is not open", order_id))),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+    
+    fn resting(side: &str, qty: Decimal, id: &str, order_type: OrderType) -> OrderRequest {
+        OrderRequest::new(side, OrderAmount::Qty(qty), "BOIL", id).with_type(order_type)
+    }
+    
+    #[tokio::test]
+    async fn resting_orders_fill_when_the_price_moves_through_them() {
+        let broker = SimulatedBroker::new(dec!(1000), Decimal::ZERO, Decimal::ZERO);
+        broker.set_price("BOIL", dec!(20));
+        broker.place_order(&OrderRequest::new("buy", OrderAmount::Qty(dec!(10)), "BOIL", "entry")).await.unwrap();
+        
+        let stop = broker.place_order(&resting("sell", dec!(10), "stop", OrderType::Stop { stop_price: dec!(18) })).await.unwrap();
+        let limit = broker.place_order(&resting("buy", dec!(100), "limit", OrderType::Limit { limit_price: dec!(15) })).await.unwrap();
+        assert_eq!(broker.get_open_orders().await.unwrap().len(), 2);
+        broker.take_fills();
+        
+        // Not far enough to trigger either
+        broker.set_price("BOIL", dec!(19));
+        assert!(broker.take_fills().is_empty());
+        
+        // The stop sells at the new price; the limit can no longer be paid for
+        broker.set_price("BOIL", dec!(14));
+        let fills = broker.take_fills();
+        assert_eq!(fills.len(), 1);
+        assert_eq!((fills[0].order_id.as_str(), fills[0].filled_avg_price), (stop.order_id.as_str(), Some(dec!(14))));
+        assert_eq!(broker.get_order(&stop.order_id).await.unwrap().status, OrderStatus::Filled);
+        assert_eq!(broker.get_order(&limit.order_id).await.unwrap().status, OrderStatus::Rejected);
+        assert!(broker.get_current_position("BOIL").await.unwrap().is_none());
+        assert_eq!(broker.get_account_info().await.unwrap().cash, dec!(940));
+    }
+}
@@ -0,0 +1,246 @@
+use crate::config::TradingConfig;
+use log::warn;
+use rust_decimal::prelude::{FromPrimitive, ToPrimitive};
+use rust_decimal::Decimal;
+
+/// Trading days per year used to annualize daily volatility
+const TRADING_DAYS: f64 = 252.0;
+
+/// Market and account data a sizing policy may use
+#[derive(Debug, Clone, Default)]
+pub struct SizingInputs {
+    pub confidence: Decimal,
+    /// Account equity, fetched only for policies that ask for it
+    pub equity: Option<Decimal>,
+    /// Annualized realized volatility of the symbol being bought, as a fraction
+    pub volatility: Option<f64>,
+}
+
+/// Decides how many dollars to commit to a new position.
+///
+/// The executor turns the notional into shares at the current price, so
+/// policies never deal with prices or rounding. Money is computed in
+/// `Decimal`; only the volatility statistic is floating point.
+pub trait SizingPolicy: Send + Sync {
+    /// Name used in logs
+    fn name(&self) -> &str;
+    
+    /// Whether `SizingInputs::equity` must be filled in
+    fn needs_equity(&self) -> bool {
+        false
+    }
+    
+    /// Whether `SizingInputs::volatility` must be filled in
+    fn needs_volatility(&self) -> bool {
+        false
+    }
+    
+    /// Target notional in dollars, or `None` when the inputs are insufficient
+    fn target_notional(&self, inputs: &SizingInputs) -> Option<Decimal>;
+}
+
+/// Always `POSITION_SIZE` dollars
+pub struct FixedNotional {
+    pub position_size: Decimal,
+}
+
+impl SizingPolicy for FixedNotional {
+    fn name(&self) -> &str {
+        "fixed"
+    }
+    
+    fn target_notional(&self, _inputs: &SizingInputs) -> Option<Decimal> {
+        Some(self.position_size)
+    }
+}
+
+/// `POSITION_SIZE` at full confidence (2.0), half of it right at the buy threshold
+pub struct ConfidenceScaled {
+    pub position_size: Decimal,
+}
+
+impl SizingPolicy for ConfidenceScaled {
+    fn name(&self) -> &str {
+        "confidence"
+    }
+    
+    fn target_notional(&self, inputs: &SizingInputs) -> Option<Decimal> {
+        let two = Decimal::TWO;
+        Some(self.position_size * inputs.confidence.clamp(Decimal::ZERO, two) / two)
+    }
+}
+
+/// Scales `POSITION_SIZE` so the position runs at `VOL_TARGET_PCT` annualized
+/// volatility, never by more than `VOL_MAX_SCALE`
+pub struct VolatilityTarget {
+    pub position_size: Decimal,
+    /// Annualized, as a fraction
+    pub target_vol: Decimal,
+    pub max_scale: Decimal,
+}
+
+impl SizingPolicy for VolatilityTarget {
+    fn name(&self) -> &str {
+        "vol_target"
+    }
+    
+    fn needs_volatility(&self) -> bool {
+        true
+    }
+    
+    fn target_notional(&self, inputs: &SizingInputs) -> Option<Decimal> {
+        let volatility = Decimal::from_f64(inputs.volatility.filter(|v| *v > 0.0)?)?;
+        Some(self.position_size * (self.target_vol / volatility).min(self.max_scale))
+    }
+}
+
+/// Fraction of equity given by the Kelly criterion for the configured win
+/// rate and payoff ratio, scaled down by `KELLY_FRACTION`
+pub struct FractionalKelly {
+    pub win_rate: Decimal,
+    pub payoff_ratio: Decimal,
+    pub fraction: Decimal,
+}
+
+impl FractionalKelly {
+    /// Full Kelly fraction `p - (1 - p) / b`, floored at zero (no edge, no bet)
+    pub fn kelly_fraction(&self) -> Decimal {
+        (self.win_rate - (Decimal::ONE - self.win_rate) / self.payoff_ratio).max(Decimal::ZERO)
+    }
+}
+
+impl SizingPolicy for FractionalKelly {
+    fn name(&self) -> &str {
+        "kelly"
+    }
+    
+    fn needs_equity(&self) -> bool {
+        true
+    }
+    
+    fn target_notional(&self, inputs: &SizingInputs) -> Option<Decimal> {
+        Some(inputs.equity? * self.kelly_fraction() * self.fraction)
+    }
+}
+
+/// `POSITION_PCT_EQUITY` percent of account equity
+pub struct PercentOfEquity {
+    pub percent: Decimal,
+}
+
+impl SizingPolicy for PercentOfEquity {
+    fn name(&self) -> &str {
+        "percent_equity"
+    }
+    
+    fn needs_equity(&self) -> bool {
+        true
+    }
+    
+    fn target_notional(&self, inputs: &SizingInputs) -> Option<Decimal> {
+        Some(inputs.equity? * self.percent / Decimal::ONE_HUNDRED)
+    }
+}
+
+/// Build the policy selected by `POSITION_SIZING`
+pub fn from_config(config: &TradingConfig) -> Box<dyn SizingPolicy> {
+    match config.position_sizing.as_str() {
+        "confidence" => Box::new(ConfidenceScaled {
+            position_size: config.position_size,
+        }),
+        "vol_target" => Box::new(VolatilityTarget {
+            position_size: config.position_size,
+            target_vol: config.vol_target_pct / Decimal::ONE_HUNDRED,
+            max_scale: config.vol_max_scale,
+        }),
+        "kelly" => Box::new(FractionalKelly {
+            win_rate: config.kelly_win_rate,
+            payoff_ratio: config.kelly_payoff_ratio,
+            fraction: config.kelly_fraction,
+        }),
+        "percent_equity" => Box::new(PercentOfEquity {
+            percent: config.position_pct_equity,
+        }),
+        "fixed" => Box::new(FixedNotional {
+            position_size: config.position_size,
+        }),
+        other => {
+            warn!("Unknown POSITION_SIZING '{}', using fixed", other);
+            Box::new(FixedNotional {
+                position_size: config.position_size,
+            })
+        }
+    }
+}
+
+/// Annualized standard deviation of daily log returns, oldest close first.
+/// Needs at least three closes.
+pub fn realized_volatility(closes: &[Decimal]) -> Option<f64> {
+    let closes: Vec<f64> = closes.iter().filter_map(|c| c.to_f64()).collect();
+    let returns: Vec<f64> = closes
+        .windows(2)
+        .filter(|w| w[0] > 0.0 && w[1] > 0.0)
+        .map(|w| (w[1] / w[0]).ln())
+        .collect();
+    if returns.len() < 2 {
+        return None;
+    }
+    let mean = returns.iter().sum::<f64>() / returns.len() as f64;
+    let variance = returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (returns.len() - 1) as f64;
+    Some(variance.sqrt() * TRADING_DAYS.sqrt())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+    
+    fn inputs(confidence: Decimal, equity: Option<Decimal>, volatility: Option<f64>) -> SizingInputs {
+        SizingInputs { confidence, equity, volatility }
+    }
+    
+    #[test]
+    fn confidence_scales_and_clamps() {
+        let policy = ConfidenceScaled { position_size: dec!(1000) };
+        assert_eq!(policy.target_notional(&inputs(dec!(1), None, None)), Some(dec!(500)));
+        assert_eq!(policy.target_notional(&inputs(dec!(3), None, None)), Some(dec!(1000)));
+        assert_eq!(policy.target_notional(&inputs(dec!(-1), None, None)), Some(dec!(0)));
+    }
+    
+    #[test]
+    fn volatility_target_caps_the_scale() {
+        let policy = VolatilityTarget { position_size: dec!(1000), target_vol: dec!(0.4), max_scale: dec!(2) };
+        assert_eq!(policy.target_notional(&inputs(dec!(1), None, Some(0.8))), Some(dec!(500)));
+        assert_eq!(policy.target_notional(&inputs(dec!(1), None, Some(0.1))), Some(dec!(2000)));
+        assert_eq!(policy.target_notional(&inputs(dec!(1), None, Some(0.0))), None);
+        assert_eq!(policy.target_notional(&inputs(dec!(1), None, None)), None);
+    }
+    
+    #[test]
+    fn kelly_and_percent_of_equity_need_equity() {
+        let kelly = FractionalKelly { win_rate: dec!(0.55), payoff_ratio: dec!(1.5), fraction: dec!(0.5) };
+        // 0.55 - 0.45 / 1.5 = 0.25
+        assert_eq!(kelly.kelly_fraction(), dec!(0.25));
+        assert_eq!(kelly.target_notional(&inputs(dec!(1), Some(dec!(10000)), None)), Some(dec!(1250)));
+        assert_eq!(kelly.target_notional(&inputs(dec!(1), None, None)), None);
+        let no_edge = FractionalKelly { win_rate: dec!(0.4), payoff_ratio: dec!(1), fraction: dec!(0.5) };
+        assert_eq!(no_edge.kelly_fraction(), Decimal::ZERO);
+        
+        let percent = PercentOfEquity { percent: dec!(12.5) };
+        assert_eq!(percent.target_notional(&inputs(dec!(1), Some(dec!(10000)), None)), Some(dec!(1250)));
+        assert_eq!(percent.target_notional(&inputs(dec!(1), None, None)), None);
+    }
+    
+    #[test]
+    fn realized_volatility_annualizes_log_returns() {
+        assert_eq!(realized_volatility(&[dec!(10), dec!(11)]), None);
+        assert_eq!(realized_volatility(&[dec!(10), dec!(10), dec!(10)]), Some(0.0));
+        // Log returns r, -r, r have a sample standard deviation of r * sqrt(4/3)
+        let r: f64 = 0.01;
+        let up = Decimal::from_f64(100.0 * r.exp()).unwrap();
+        let closes = [dec!(100), up, dec!(100), up];
+        let expected = r * (4.0_f64 / 3.0).sqrt() * TRADING_DAYS.sqrt();
+        assert!((realized_volatility(&closes).unwrap() - expected).abs() < 1e-9);
+    }
+}
+
@@ -0,0 +1,319 @@
+use crate::config::TradingConfig;
+use log::warn;
+use rand::Rng;
+use reqwest::header::HeaderMap;
+use reqwest::{Method, RequestBuilder, Response, StatusCode};
+use std::time::Duration;
+
+/// How many times and how patiently to retry transient failures
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl RetryPolicy {
+    pub fn from_config(config: &TradingConfig) -> Self {
+        Self {
+            max_retries: config.http_max_retries,
+            base_delay: Duration::from_millis(config.http_backoff_base_ms),
+            max_delay: Duration::from_millis(config.http_backoff_max_ms),
+        }
+    }
+    
+    /// Exponential backoff with "equal jitter": half the delay is fixed and
+    /// half is random, so concurrent clients spread out without ever
+    /// retrying immediately.
+    fn backoff(&self, attempt: u32) -> Duration {
+        let exp = self.base_delay.saturating_mul(2u32.saturating_pow(attempt));
+        let capped = exp.min(self.max_delay);
+        let half = capped / 2;
+        let jitter_ms = rand::thread_rng().gen_range(0..=half.as_millis() as u64);
+        half + Duration::from_millis(jitter_ms)
+    }
+}
+
+/// Shared HTTP layer for every data fetcher and broker.
+///
+/// Applies the configured timeout to every request and retries timeouts,
+/// connection failures, 429 and 5xx responses with exponential backoff,
+/// honoring `Retry-After` up to the policy's `max_delay`. Requests with non-idempotent methods (POST, PATCH)
+/// are only retried when sent through `send_with_idempotency_key`.
+#[derive(Clone)]
+pub struct HttpClient {
+    client: reqwest::Client,
+    policy: RetryPolicy,
+}
+
+impl HttpClient {
+    pub fn new(config: &TradingConfig) -> Self {
+        Self::build(config, None)
+    }
+    
+    pub fn with_user_agent(config: &TradingConfig, user_agent: &str) -> Self {
+        Self::build(config, Some(user_agent))
+    }
+    
+    fn build(config: &TradingConfig, user_agent: Option<&str>) -> Self {
+        let mut builder = reqwest::Client::builder()
+            .timeout(Duration::from_secs(config.http_timeout_secs))
+            .connect_timeout(Duration::from_secs(config.http_timeout_secs.min(10)));
+        if let Some(agent) = user_agent {
+            builder = builder.user_agent(agent);
+        }
+        // Same failure mode as reqwest::Client::new(): only fails if the TLS backend cannot initialize
+        let client = builder.build().expect("failed to initialize HTTP client");
+        
+        Self {
+            client,
+            policy: RetryPolicy::from_config(config),
+        }
+    }
+    
+    pub fn get(&self, url: &str) -> RequestBuilder {
+        self.client.get(url)
+    }
+    
+    pub fn post(&self, url: &str) -> RequestBuilder {
+        self.client.post(url)
+    }
+    
+    pub fn delete(&self, url: &str) -> RequestBuilder {
+        self.client.delete(url)
+    }
+    
+    /// Send a request, retrying transient failures if its method is idempotent
+    pub async fn send(&self, request: RequestBuilder) -> reqwest::Result<Response> {
+        self.execute(request, false).await
+    }
+    
+    /// Send a request whose side effects are deduplicated server-side by
+    /// `idempotency_key` (e.g. an order's client_order_id), which makes it
+    /// safe to retry even for POST.
+    pub async fn send_with_idempotency_key(
+        &self,
+        request: RequestBuilder,
+        idempotency_key: &str,
+    ) -> reqwest::Result<Response> {
+        if idempotency_key.is_empty() {
+            return self.execute(request, false).await;
+        }
+        self.execute(request, true).await
+    }
+    
+    async fn execute(&self, request: RequestBuilder, keyed: bool) -> reqwest::Result<Response> {
+        let built = request.build()?;
+        let retryable = keyed || is_idempotent(built.method());
+        let max_retries = if retryable { self.policy.max_retries } else { 0 };
+        
+        let mut attempt = 0;
+        loop {
+            // Streaming bodies cannot be cloned; such requests are sent once
+            let Some(current) = built.try_clone() else {
+                return self.client.execute(built).await;
+            };
+            
+            match self.client.execute(current).await {
+                Ok(response) if attempt < max_retries && is_retryable_status(response.status()) => {
+                    // A server asking for hours must not stall the cycle (or an order) that long
+                    let delay = retry_after(response.headers())
+                        .map(|delay| delay.min(self.policy.max_delay))
+                        .unwrap_or_else(|| self.policy.backoff(attempt));
+                    warn!(
+                        "  {} {} returned {}, retrying in {:.1}s (attempt {}/{})",
+                        built.method(), built.url().path(), response.status(),
+                        delay.as_secs_f64(), attempt + 1, max_retries
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+                Ok(response) => return Ok(response),
+                Err(e) if attempt < max_retries && is_retryable_error(&e) => {
+                    let delay = self.policy.backoff(attempt);
+                    warn!(
+                        "  {} {} failed ({}), retrying in {:.1}s (attempt {}/{})",
+                        built.method(), built.url().path(), e,
+                        delay.as_secs_f64(), attempt + 1, max_retries
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+                Err(e) => return Err(e),
+            }
+            attempt += 1;
+        }
+    }
+}
+
+fn is_idempotent(method: &Method) -> bool {
+    matches!(*method, Method::GET | Method::HEAD | Method::PUT | Method::DELETE | Method::OPTIONS)
+}
+
+fn is_retryable_status(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS
+        || status == StatusCode::REQUEST_TIMEOUT
+        || status.is_server_error()
+}
+
+fn is_retryable_error(e: &reqwest::Error) -> bool {
+    e.is_timeout() || e.is_connect() || e.is_request()
+}
+
+/// Parse a `Retry-After` header given either as seconds or an HTTP date
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(reqwest::header::RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let when = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    (when.with_timezone(&chrono::Utc) - chrono::Utc::now()).to_std().ok()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::{HeaderValue, RETRY_AFTER};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    
+    fn policy() -> RetryPolicy {
+        RetryPolicy {
+            max_retries: 2,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_millis(1000),
+        }
+    }
+    
+    fn client() -> HttpClient {
+        let config = TradingConfig {
+            http_max_retries: 2,
+            http_backoff_base_ms: 1,
+            http_backoff_max_ms: 5,
+            ..TradingConfig::default()
+        };
+        HttpClient::new(&config)
+    }
+    
+    fn headers(retry_after: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, HeaderValue::from_str(retry_after).unwrap());
+        headers
+    }
+    
+    /// Local server answering every request with `response`; returns its URL
+    /// and the number of requests it has received
+    async fn serve(response: &'static str) -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/v2/orders", listener.local_addr().unwrap());
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = requests.clone();
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut request = Vec::new();
+                let mut buf = [0u8; 1024];
+                while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                    let n = socket.read(&mut buf).await.unwrap();
+                    if n == 0 {
+                        break;
+                    }
+                    request.extend_from_slice(&buf[..n]);
+                }
+                counter.fetch_add(1, Ordering::SeqCst);
+                socket.write_all(response.as_bytes()).await.unwrap();
+                socket.shutdown().await.unwrap();
+            }
+        });
+        (url, requests)
+    }
+    
+    const UNAVAILABLE: &str = "HTTP/1.1 503 Service Unavailable\r\ncontent-length: 0\r\nconnection: close\r\n\r\n";
+    
+    #[test]
+    fn only_idempotent_methods_are_retried_by_default() {
+        for method in [Method::GET, Method::HEAD, Method::PUT, Method::DELETE, Method::OPTIONS] {
+            assert!(is_idempotent(&method), "{}", method);
+        }
+        assert!(!is_idempotent(&Method::POST));
+        assert!(!is_idempotent(&Method::PATCH));
+    }
+    
+    #[test]
+    fn throttling_timeouts_and_server_errors_are_retryable() {
+        for status in [429, 408, 500, 502, 503, 504] {
+            assert!(is_retryable_status(StatusCode::from_u16(status).unwrap()), "{}", status);
+        }
+        for status in [200, 400, 401, 403, 404, 422] {
+            assert!(!is_retryable_status(StatusCode::from_u16(status).unwrap()), "{}", status);
+        }
+    }
+    
+    #[test]
+    fn retry_after_reads_seconds_and_http_dates() {
+        assert_eq!(retry_after(&headers("120")), Some(Duration::from_secs(120)));
+        
+        let in_a_minute = (chrono::Utc::now() + chrono::Duration::seconds(60)).to_rfc2822();
+        let delay = retry_after(&headers(&in_a_minute)).unwrap();
+        assert!(delay > Duration::from_secs(55) && delay <= Duration::from_secs(60), "{:?}", delay);
+        
+        let past = (chrono::Utc::now() - chrono::Duration::seconds(60)).to_rfc2822();
+        assert_eq!(retry_after(&headers(&past)), None);
+        assert_eq!(retry_after(&headers("soon")), None);
+        assert_eq!(retry_after(&HeaderMap::new()), None);
+    }
+    
+    #[test]
+    fn backoff_doubles_up_to_the_maximum_with_jitter() {
+        let policy = policy();
+        for attempt in 0..10 {
+            let capped = (policy.base_delay * 2u32.pow(attempt)).min(policy.max_delay);
+            for _ in 0..20 {
+                let delay = policy.backoff(attempt);
+                assert!(delay >= capped / 2 && delay <= capped, "attempt {}: {:?}", attempt, delay);
+            }
+        }
+        // Huge attempt counts saturate instead of overflowing
+        assert!(policy.backoff(u32::MAX) <= policy.max_delay);
+    }
+    
+    #[tokio::test]
+    async fn post_without_idempotency_key_is_sent_once() {
+        let (url, requests) = serve(UNAVAILABLE).await;
+        let client = client();
+        
+        let response = client.send(client.post(&url).body("{}")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+        
+        // An empty key gives no protection either
+        client.send_with_idempotency_key(client.post(&url).body("{}"), "").await.unwrap();
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+    }
+    
+    #[tokio::test]
+    async fn idempotent_requests_are_retried() {
+        let (url, requests) = serve(UNAVAILABLE).await;
+        let client = client();
+        
+        client.send(client.get(&url)).await.unwrap();
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+        
+        client.send_with_idempotency_key(client.post(&url).body("{}"), "hoc-1").await.unwrap();
+        assert_eq!(requests.load(Ordering::SeqCst), 6);
+    }
+    
+    #[tokio::test]
+    async fn long_retry_after_is_capped() {
+        let (url, requests) = serve("HTTP/1.1 429 Too Many Requests\r\nretry-after: 86400\r\ncontent-length: 0\r\nconnection: close\r\n\r\n").await;
+        let client = client();
+        
+        let response = tokio::time::timeout(Duration::from_secs(10), client.send(client.get(&url)))
+            .await
+            .expect("Retry-After was not capped")
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+    }
+}
@@ -1,4 +1,8 @@
+pub mod http_client;
+pub mod shutdown;
 pub mod trading_logger;
 
+pub use http_client::HttpClient;
+pub use shutdown::Shutdown;
 pub use trading_logger::TradingLogger;
 
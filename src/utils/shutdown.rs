@@ -0,0 +1,68 @@
+use log::{error, warn};
+use std::time::Duration;
+use tokio::sync::watch;
+
+/// Cooperative shutdown flag set by SIGINT/SIGTERM.
+///
+/// Signals never interrupt work directly: the bot checks the flag at safe
+/// points (between cycles, before placing orders) and wakes from sleeps early.
+/// A second signal exits immediately with status 130.
+#[derive(Clone)]
+pub struct Shutdown {
+    rx: watch::Receiver<Option<&'static str>>,
+}
+
+impl Shutdown {
+    /// Start listening for termination signals on a background task
+    pub fn install() -> Self {
+        let (tx, rx) = watch::channel(None);
+        tokio::spawn(async move {
+            let signal = wait_for_signal().await;
+            warn!("Received {}, finishing in-flight work before shutting down (send again to force exit)", signal);
+            let _ = tx.send(Some(signal));
+            
+            let signal = wait_for_signal().await;
+            error!("Received second {}, exiting immediately", signal);
+            std::process::exit(130);
+        });
+        Self { rx }
+    }
+    
+    /// Name of the signal that requested shutdown, if any
+    pub fn requested(&self) -> Option<&'static str> {
+        *self.rx.borrow()
+    }
+    
+    /// Sleep for `duration` unless shutdown is requested first.
+    /// Returns true when the sleep was cut short.
+    pub async fn sleep(&self, duration: Duration) -> bool {
+        let mut rx = self.rx.clone();
+        tokio::select! {
+            _ = tokio::time::sleep(duration) => false,
+            _ = rx.wait_for(|signal| signal.is_some()) => true,
+        }
+    }
+}
+
+#[cfg(unix)]
+async fn wait_for_signal() -> &'static str {
+    use tokio::signal::unix::{signal, SignalKind};
+    match signal(SignalKind::terminate()) {
+        Ok(mut sigterm) => tokio::select! {
+            _ = tokio::signal::ctrl_c() => "SIGINT",
+            _ = sigterm.recv() => "SIGTERM",
+        },
+        Err(e) => {
+            warn!("Could not install SIGTERM handler: {}", e);
+            let _ = tokio::signal::ctrl_c().await;
+            "SIGINT"
+        }
+    }
+}
+
+#[cfg(not(unix))]
+async fn wait_for_signal() -> &'static str {
+    let _ = tokio::signal::ctrl_c().await;
+    "Ctrl+C"
+}
+
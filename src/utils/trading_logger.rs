@@ -1,43 +1,56 @@
 use crate::config::TradingConfig;
 use crate::signals::TradingSignal;
+use crate::trading::protection::ProtectiveExit;
 use chrono::Utc;
-use log::{info, error};
+use log::{info, error, warn};
 use serde::Serialize;
 use std::fs::OpenOptions;
 use std::io::Write;
+use std::path::PathBuf;
 
 pub struct TradingLogger {
-    _config: TradingConfig,
+    config: TradingConfig,
 }
 
 impl TradingLogger {
     pub fn new(config: TradingConfig) -> Self {
-        Self { _config: config }
+        Self { config }
+    }
+    
+    /// Path of one of the JSON logs inside `LOG_DIR`
+    fn path(&self, name: &str) -> PathBuf {
+        PathBuf::from(&self.config.log_dir).join(name)
+    }
+    
+    /// Append `value` as one JSON line to the log `name`
+    fn append_json(&self, name: &str, value: &serde_json::Value) {
+        if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(self.path(name)) {
+            if let Err(e) = writeln!(file, "{}", value) {
+                error!("Error writing to {}: {}", name, e);
+            }
+        }
     }
     
     pub fn log_signal(&self, signal: &TradingSignal) {
-        let signal_data = serde_json::json!({
+        let mut signal_data = serde_json::json!({
             "timestamp": signal.timestamp.to_rfc3339(),
-            "temperature_signal": signal.temperature_signal,
-            "inventory_signal": signal.inventory_signal,
-            "storm_signal": signal.storm_signal,
+            "components": signal.components,
             "total_signal": signal.total_signal,
             "action": signal.action,
             "confidence": signal.confidence,
+            "blocked_reason": signal.blocked_reason,
         });
+        // Flat `<name>_signal` values, as earlier versions of the log had
+        if let Some(data) = signal_data.as_object_mut() {
+            for (name, component) in &signal.components {
+                data.insert(format!("{}_signal", name), component.value.into());
+            }
+        }
         
         info!("TRADING SIGNAL: {}", serde_json::to_string_pretty(&signal_data).unwrap());
         
         // Save to separate signal log file
-        if let Ok(mut file) = OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open("logs/signals.log")
-        {
-            if let Err(e) = writeln!(file, "{}", serde_json::to_string(&signal_data).unwrap()) {
-                error!("Error writing to signals.log: {}", e);
-            }
-        }
+        self.append_json("signals.log", &signal_data);
     }
     
     pub fn log_trade(&self, trade_result: Option<&impl Serialize>) {
@@ -50,20 +63,29 @@ impl TradingLogger {
             info!("TRADE EXECUTED: {}", serde_json::to_string_pretty(&trade_data).unwrap());
             
             // Save to separate trade log file
-            if let Ok(mut file) = OpenOptions::new()
-                .create(true)
-                .append(true)
-                .open("logs/trades.log")
-            {
-                if let Err(e) = writeln!(file, "{}", serde_json::to_string(&trade_data).unwrap()) {
-                    error!("Error writing to trades.log: {}", e);
-                }
-            }
+            self.append_json("trades.log", &trade_data);
         } else {
             info!("No trade executed");
         }
     }
     
+    /// Record a position closed by a stop-loss, trailing-stop or take-profit rule
+    pub fn log_exit(&self, exit: &ProtectiveExit) {
+        let trade_data = serde_json::json!({
+            "timestamp": Utc::now().to_rfc3339(),
+            "exit_reason": exit.reason,
+            "entry_price": exit.entry_price,
+            "peak_price": exit.peak_price,
+            "trigger_price": exit.trigger_price,
+            "trade": exit.trade,
+        });
+        
+        info!("PROTECTIVE EXIT: {}", serde_json::to_string_pretty(&trade_data).unwrap());
+        
+        // Exits share the trade log with regular trades
+        self.append_json("trades.log", &trade_data);
+    }
+    
     pub fn log_portfolio(&self, portfolio: &impl Serialize) {
         let portfolio_data = serde_json::json!({
             "timestamp": Utc::now().to_rfc3339(),
@@ -73,15 +95,37 @@ impl TradingLogger {
         info!("PORTFOLIO STATUS: {}", serde_json::to_string_pretty(&portfolio_data).unwrap());
         
         // Save to separate portfolio log file
-        if let Ok(mut file) = OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open("logs/portfolio.log")
-        {
-            if let Err(e) = writeln!(file, "{}", serde_json::to_string(&portfolio_data).unwrap()) {
-                error!("Error writing to portfolio.log: {}", e);
-            }
+        self.append_json("portfolio.log", &portfolio_data);
+    }
+    
+    /// Record an execution incident (e.g. a failed position flip) that needs attention
+    pub fn log_incident(&self, incident: &serde_json::Value) {
+        let mut incident_data = serde_json::json!({
+            "timestamp": Utc::now().to_rfc3339(),
+        });
+        if let (Some(data), Some(fields)) = (incident_data.as_object_mut(), incident.as_object()) {
+            data.extend(fields.clone());
+        }
+        
+        error!("INCIDENT: {}", serde_json::to_string_pretty(&incident_data).unwrap());
+        
+        // Save to separate incident log file
+        self.append_json("incidents.log", &incident_data);
+    }
+    
+    /// Record a trade the risk engine refused
+    pub fn log_risk_violation(&self, violation: &serde_json::Value) {
+        let mut violation_data = serde_json::json!({
+            "timestamp": Utc::now().to_rfc3339(),
+        });
+        if let (Some(data), Some(fields)) = (violation_data.as_object_mut(), violation.as_object()) {
+            data.extend(fields.clone());
         }
+        
+        warn!("RISK VIOLATION: {}", serde_json::to_string_pretty(&violation_data).unwrap());
+        
+        // Save to separate risk log file
+        self.append_json("risk.log", &violation_data);
     }
     
     #[allow(dead_code)]
@@ -96,15 +140,7 @@ impl TradingLogger {
         error!("ERROR: {}", serde_json::to_string_pretty(&error_data).unwrap());
         
         // Save to separate error log file
-        if let Ok(mut file) = OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open("logs/errors.log")
-        {
-            if let Err(e) = writeln!(file, "{}", serde_json::to_string(&error_data).unwrap()) {
-                eprintln!("Error writing to errors.log: {}", e);
-            }
-        }
+        self.append_json("errors.log", &error_data);
     }
 }
 